@@ -0,0 +1,216 @@
+CREATE TABLE IF NOT EXISTS links (
+    user_id INTEGER NOT NULL,
+    link TEXT NOT NULL,
+    page_simhash TEXT,
+    csp_analysis_json TEXT,
+    tls_verification_json TEXT,
+    redirect_chain_json TEXT,
+    domain_family TEXT,
+    domain TEXT,
+    page_title TEXT,
+    page_metadata_json TEXT,
+    favicon_hash TEXT,
+    content_type TEXT,
+    crawl_allowed INTEGER,
+    rescan_interval_hours INTEGER,
+    is_hashed INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_links_user_id_domain_family ON links (user_id, domain_family);
+CREATE INDEX IF NOT EXISTS links_domain ON links (domain);
+
+CREATE TABLE IF NOT EXISTS link_global_stats (
+    link TEXT PRIMARY KEY,
+    submission_count INTEGER NOT NULL DEFAULT 0,
+    user_count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS robots_cache (
+    domain TEXT PRIMARY KEY,
+    robots_txt TEXT NOT NULL,
+    cached_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS shortener_cache (
+    short_url TEXT PRIMARY KEY,
+    resolved_url TEXT NOT NULL,
+    resolved_at TEXT NOT NULL,
+    hop_count INTEGER NOT NULL DEFAULT 0,
+    redirect_chain_json TEXT
+);
+
+CREATE TABLE IF NOT EXISTS link_health_checks (
+    id INTEGER PRIMARY KEY,
+    link TEXT NOT NULL,
+    domain TEXT,
+    checked_at TEXT NOT NULL,
+    status_code INTEGER,
+    redirect_url TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS link_health_checks_link ON link_health_checks (link, checked_at);
+CREATE INDEX IF NOT EXISTS link_health_checks_domain ON link_health_checks (domain, checked_at);
+
+CREATE TABLE IF NOT EXISTS outbound_messages (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    sent INTEGER NOT NULL DEFAULT 0,
+    sent_at TEXT,
+    retry_count INTEGER NOT NULL DEFAULT 0,
+    sending_since TEXT,
+    dropped INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS link_ips (
+    link TEXT NOT NULL,
+    domain TEXT,
+    ip TEXT NOT NULL,
+    ptr_record TEXT,
+    resolved_at TEXT NOT NULL,
+    PRIMARY KEY (link, ip)
+);
+
+CREATE INDEX IF NOT EXISTS link_ips_domain ON link_ips (domain);
+
+CREATE TABLE IF NOT EXISTS custom_scan_rules (
+    id INTEGER PRIMARY KEY,
+    pattern TEXT NOT NULL,
+    pattern_type TEXT NOT NULL CHECK (pattern_type IN ('regex', 'glob')),
+    action TEXT NOT NULL CHECK (action IN ('block', 'allow', 'flag')),
+    reason TEXT
+);
+
+CREATE TABLE IF NOT EXISTS link_flags (
+    link TEXT NOT NULL,
+    domain TEXT,
+    flag TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    flagged_at TEXT NOT NULL,
+    PRIMARY KEY (link, flag)
+);
+
+CREATE INDEX IF NOT EXISTS link_flags_domain ON link_flags (domain);
+
+CREATE TABLE IF NOT EXISTS ioc_records (
+    link TEXT NOT NULL,
+    ioc_type TEXT NOT NULL,
+    ioc_value TEXT NOT NULL,
+    extracted_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS domain_reputation_cache (
+    domain TEXT NOT NULL,
+    score REAL NOT NULL,
+    signals_json TEXT NOT NULL,
+    computed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS domain_reputation_cache_domain ON domain_reputation_cache (domain, computed_at);
+CREATE INDEX IF NOT EXISTS domain_reputation_cache_computed_at ON domain_reputation_cache (computed_at);
+
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id INTEGER PRIMARY KEY,
+    last_seen TEXT,
+    last_command TEXT,
+    privacy_mode INTEGER NOT NULL DEFAULT 0,
+    links_per_page INTEGER,
+    username TEXT
+);
+
+CREATE TABLE IF NOT EXISTS dialogues (
+    chat_id INTEGER PRIMARY KEY,
+    state_json TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS scan_sessions (
+    id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    started_by INTEGER NOT NULL,
+    started_at TEXT NOT NULL,
+    completed_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS session_links (
+    session_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    link TEXT NOT NULL
+);
+
+
+CREATE TABLE IF NOT EXISTS known_brand_favicons (
+    brand TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    favicon_hash TEXT NOT NULL,
+    PRIMARY KEY (brand, favicon_hash)
+);
+
+CREATE TABLE IF NOT EXISTS link_deletions (
+    user_id INTEGER NOT NULL,
+    link TEXT NOT NULL,
+    deleted_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ct_subdomains (
+    domain TEXT NOT NULL,
+    subdomain TEXT NOT NULL,
+    discovered_at TEXT NOT NULL,
+    PRIMARY KEY (domain, subdomain)
+);
+
+CREATE TABLE IF NOT EXISTS link_reminders (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    link TEXT NOT NULL,
+    trigger_on TEXT NOT NULL,
+    reminded_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS privacy_audit_log (
+    id INTEGER PRIMARY KEY,
+    admin_user_id INTEGER NOT NULL,
+    accessed_user_id INTEGER NOT NULL,
+    operation TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    accessed_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ct_monitored_domains (
+    domain TEXT PRIMARY KEY,
+    registered_at TEXT NOT NULL,
+    last_checked_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS ct_certificates (
+    domain TEXT NOT NULL,
+    cert_id INTEGER NOT NULL,
+    issued_at TEXT,
+    issuer TEXT,
+    PRIMARY KEY (domain, cert_id)
+);
+
+CREATE TABLE IF NOT EXISTS link_view_counts (
+    link TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    view_count INTEGER NOT NULL,
+    last_viewed_at TEXT NOT NULL,
+    PRIMARY KEY (link, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS share_requests (
+    requester_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    requested_at TEXT NOT NULL,
+    PRIMARY KEY (requester_id, target_id)
+);
+
+CREATE TABLE IF NOT EXISTS share_agreements (
+    user_id_a INTEGER NOT NULL,
+    user_id_b INTEGER NOT NULL,
+    agreed_at TEXT NOT NULL,
+    PRIMARY KEY (user_id_a, user_id_b)
+);
@@ -0,0 +1,130 @@
+use sqlite3::State;
+
+use crate::{database, domain_family, fingerprint, website};
+
+/// The official sites of brands that are often impersonated: banks, payment systems, email providers and social networks.
+///
+/// The favicons are downloaded from these sites, and the registrable domain of a site is the only one allowed to use its favicon.
+const KNOWN_BRAND_SITES: &[(&str, &str)] = &[
+    ("PayPal", "https://www.paypal.com/"),
+    ("Sberbank", "https://www.sberbank.ru/"),
+    ("T-Bank", "https://www.tbank.ru/"),
+    ("VTB", "https://www.vtb.ru/"),
+    ("Alfa-Bank", "https://alfabank.ru/"),
+    ("Google", "https://www.google.com/"),
+    ("Microsoft", "https://www.microsoft.com/"),
+    ("Yandex", "https://yandex.ru/"),
+    ("Mail.ru", "https://mail.ru/"),
+    ("Apple", "https://www.apple.com/"),
+    ("Facebook", "https://www.facebook.com/"),
+    ("Instagram", "https://www.instagram.com/"),
+    ("VK", "https://vk.com/"),
+    ("Telegram", "https://telegram.org/"),
+    ("X", "https://x.com/"),
+];
+
+/// Saves the favicon hash of a brand.
+///
+/// # Arguments
+///
+/// * `brand` - The name of the brand.
+/// * `domain` - The registrable domain of the brand, which is allowed to use the favicon.
+/// * `favicon_hash` - The FNV-1a hash of the favicon as a hexadecimal string.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_brand_favicon(brand: &str, domain: &str, favicon_hash: &str) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR REPLACE INTO known_brand_favicons (brand, domain, favicon_hash) VALUES (?, ?, ?)").unwrap();
+
+    db.bind(1, brand).unwrap();
+    db.bind(2, domain).unwrap();
+    db.bind(3, favicon_hash).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Checks whether a brand already has a saved favicon hash.
+fn is_brand_seeded(brand: &str) -> bool {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT 1 FROM known_brand_favicons WHERE brand = ? LIMIT 1").unwrap();
+
+    db.bind(1, brand).unwrap();
+
+    db.next().unwrap() == State::Row
+}
+
+/// Downloads the favicons of the known brands that have no saved hash yet and saves their hashes.
+///
+/// A brand whose favicon cannot be downloaded is tried again on the next start of the bot.
+pub async fn seed_known_brand_favicons() {
+    for (brand, site) in KNOWN_BRAND_SITES {
+        if is_brand_seeded(brand) {
+            continue;
+        }
+
+        let domain = domain_family::get_domain_family(site).expect("The site of a known brand has no registrable domain");
+
+        match website::fetch_favicon(site).await {
+            Ok(Some(favicon)) => add_brand_favicon(brand, &domain, &format!("{:016x}", fingerprint::fnv1a(&favicon))),
+            Ok(None) => warn!("The site of the brand {} has no favicon: {}", brand, site),
+            Err(err) => warn!("Failed to download the favicon of the brand {}: {}. Description: {}", brand, site, err)
+        }
+    }
+}
+
+/// Finds the brand whose favicon the link uses without belonging to the brand.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `favicon_hash` - The FNV-1a hash of the favicon of the link's site as a hexadecimal string.
+///
+/// # Returns
+///
+/// The name of the impersonated brand, or `None` if the favicon is not a known brand's one or the link belongs to the brand.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn find_impersonated_brand(link: &str, favicon_hash: &str) -> Option<String> {
+    let link_domain = domain_family::get_domain_family(link).ok();
+
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT brand, domain FROM known_brand_favicons WHERE favicon_hash = ?").unwrap();
+
+    db.bind(1, favicon_hash).unwrap();
+
+    let mut impersonated_brand: Option<String> = None;
+
+    while let State::Row = db.next().unwrap() {
+        let brand = db.read::<String>(0).unwrap();
+        let domain = db.read::<String>(1).unwrap();
+
+        // Brands may share a favicon, for example Google and its services
+        if link_domain.as_deref() == Some(domain.as_str()) {
+            return None;
+        }
+
+        impersonated_brand.get_or_insert(brand);
+    }
+
+    impersonated_brand
+}
+
+#[cfg(test)]
+mod brand_favicons_tests {
+    use crate::brand_favicons::*;
+
+    #[test]
+    fn test_find_impersonated_brand() {
+        add_brand_favicon("Test Bank", "test-bank.com", "0123456789abcdef");
+
+        assert_eq!(find_impersonated_brand("https://login.test-bank.com/", "0123456789abcdef"), None);
+        assert_eq!(find_impersonated_brand("https://test-bank-login.com/", "0123456789abcdef"), Some("Test Bank".to_string()));
+        assert_eq!(find_impersonated_brand("https://test-bank-login.com/", "fedcba9876543210"), None);
+    }
+}
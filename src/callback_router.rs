@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use teloxide::prelude::*;
+
+use crate::{HandlerResult, SparkleDialogue};
+
+/// The future returned by callback handlers.
+pub type CallbackFuture = Pin<Box<dyn Future<Output = HandlerResult> + Send>>;
+
+/// Handles the callback query of an inline keyboard button.
+///
+/// It is implemented for every async function or closure that takes the bot, the dialogue,
+/// the message with the keyboard and the callback query.
+pub trait CallbackHandler: Send + Sync {
+    fn handle(&self, bot: Bot, dialogue: SparkleDialogue, message: Message, q: CallbackQuery) -> CallbackFuture;
+}
+
+impl<F, Fut> CallbackHandler for F
+where
+    F: Fn(Bot, SparkleDialogue, Message, CallbackQuery) -> Fut + Send + Sync,
+    Fut: Future<Output = HandlerResult> + Send + 'static
+{
+    fn handle(&self, bot: Bot, dialogue: SparkleDialogue, message: Message, q: CallbackQuery) -> CallbackFuture {
+        Box::pin(self(bot, dialogue, message, q))
+    }
+}
+
+/// Routes callback queries to the handlers registered for the prefix of their data.
+///
+/// Handlers are registered by prefix (for example, `check_link` or `page:`), and the handler
+/// with the longest prefix matching the callback data is called, so `page:links:` wins over `page:`.
+#[derive(Default)]
+pub struct CallbackRouter {
+    routes: HashMap<String, Box<dyn CallbackHandler>>,
+}
+
+impl CallbackRouter {
+    /// Creates a router without routes.
+    pub fn new() -> CallbackRouter {
+        CallbackRouter::default()
+    }
+
+    /// Registers the handler for the callback data starting with the prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The prefix of the callback data.
+    /// * `handler` - The handler of the callback query.
+    pub fn route(mut self, prefix: &str, handler: impl CallbackHandler + 'static) -> CallbackRouter {
+        self.routes.insert(prefix.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Calls the handler registered for the data of the callback query.
+    ///
+    /// Queries without data or without a message, and queries no handler is registered for, are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `bot` - The bot instance.
+    /// * `dialogue` - The dialogue of the user.
+    /// * `q` - The callback query.
+    pub async fn dispatch(&self, bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+        let (Some(data), Some(message)) = (&q.data, q.message.clone()) else {
+            return Ok(());
+        };
+
+        match self.find_route(data) {
+            Some(prefix) => self.routes[prefix].handle(bot, dialogue, message, q).await,
+            None => {
+                warn!("No callback handler is registered for: {}", data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the longest registered prefix of the callback data.
+    fn find_route(&self, data: &str) -> Option<&str> {
+        self.routes.keys()
+            .filter(|prefix| data.starts_with(prefix.as_str()))
+            .max_by_key(|prefix| prefix.len())
+            .map(|prefix| prefix.as_str())
+    }
+}
+
+#[cfg(test)]
+mod callback_router_tests {
+    use crate::callback_router::CallbackRouter;
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let router = CallbackRouter::new()
+            .route("page:", |_, _, _, _| async { Ok(()) })
+            .route("page:links:", |_, _, _, _| async { Ok(()) });
+
+        assert_eq!(router.find_route("page:links:2"), Some("page:links:"));
+        assert_eq!(router.find_route("page:3"), Some("page:"));
+        assert_eq!(router.find_route("delete:1"), None);
+    }
+}
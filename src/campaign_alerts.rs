@@ -0,0 +1,139 @@
+use std::env;
+use sqlite3::State;
+use teloxide::Bot;
+use tokio::sync::broadcast;
+
+use crate::{custom_scan_rules, database, link_flags, message_queue};
+use crate::custom_scan_rules::RuleAction;
+use crate::event_bus::{BotEvent, EVENT_BUS};
+
+/// The period in which submissions are counted if `CAMPAIGN_WINDOW_MINUTES` is not set.
+const DEFAULT_CAMPAIGN_WINDOW_MINUTES: u32 = 60;
+/// The number of users that may submit the same link in the period without an alert if `CAMPAIGN_MIN_USERS` is not set.
+const DEFAULT_CAMPAIGN_MIN_USERS: u32 = 3;
+
+/// Represents a malicious link submitted by many users in a short time, which is typical for a phishing campaign.
+pub struct CampaignAlert {
+    pub link: String,
+    /// The number of different users who submitted the link in the period.
+    pub user_count: u32,
+}
+
+/// Finds malicious links submitted by more than `min_users` different users in the last `window_minutes` minutes.
+///
+/// A link is considered malicious if it has a warning or matches a custom rule that flags links.
+///
+/// # Arguments
+///
+/// * `window_minutes` - The length of the period.
+/// * `min_users` - The number of users that is still considered normal.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn detect_coordinated_submissions(window_minutes: u32, min_users: u32) -> Vec<CampaignAlert> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT link, COUNT(DISTINCT user_id) FROM links \
+        WHERE created_at > datetime('now', ?) \
+        GROUP BY link \
+        HAVING COUNT(DISTINCT user_id) > ?").unwrap();
+
+    db.bind(1, format!("-{window_minutes} minutes").as_str()).unwrap();
+    db.bind(2, min_users as i64).unwrap();
+
+    let mut vec: Vec<CampaignAlert> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let link = db.read::<String>(0).unwrap();
+
+        if is_link_malicious(&link) {
+            vec.push(CampaignAlert {
+                link,
+                user_count: db.read::<i64>(1).unwrap() as u32,
+            });
+        }
+    }
+
+    vec
+}
+
+/// Checks whether the link has a warning or matches a custom rule that flags links.
+///
+/// The custom rules are applied as well, because the warning of a rule may be saved after the link is added.
+fn is_link_malicious(link: &str) -> bool {
+    let is_flagged_by_rule = custom_scan_rules::apply_custom_rules(link)
+        .map(|rule_result| rule_result.action == RuleAction::Flag)
+        .unwrap_or(false);
+
+    is_flagged_by_rule || !link_flags::get_link_flags(link).is_empty()
+}
+
+/// Launches a subscriber that looks for phishing campaigns after every new link and alerts the administrator.
+///
+/// The period and the number of users are taken from the `CAMPAIGN_WINDOW_MINUTES` and `CAMPAIGN_MIN_USERS`
+/// environment variables. Nothing is launched if `ADMIN_ID` is not set.
+///
+/// # Arguments
+///
+/// * `bot` - Bot instance.
+pub fn launch_campaign_alerts(bot: Bot) {
+    let Some(admin_id) = env::var("ADMIN_ID").ok().and_then(|admin_id| admin_id.parse::<u64>().ok()) else {
+        return;
+    };
+
+    let window_minutes = env::var("CAMPAIGN_WINDOW_MINUTES").ok()
+        .and_then(|minutes| minutes.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_CAMPAIGN_WINDOW_MINUTES);
+    let min_users = env::var("CAMPAIGN_MIN_USERS").ok()
+        .and_then(|users| users.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_CAMPAIGN_MIN_USERS);
+
+    let mut receiver = EVENT_BUS.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(BotEvent::LinkAdded { link, .. }) => {
+                    // The alert is sent once, when the number of users first exceeds the threshold
+                    let alert = detect_coordinated_submissions(window_minutes, min_users).into_iter()
+                        .find(|alert| alert.link == link && alert.user_count == min_users + 1);
+
+                    if let Some(alert) = alert {
+                        warn!("Possible phishing campaign: {} was submitted by {} users", alert.link, alert.user_count);
+
+                        let text = format!("🚨 Возможная фишинговая кампания: ссылку {} добавили {} пользователей за {window_minutes} минут", alert.link, alert.user_count);
+                        message_queue::send_message_with_persistence(&bot, admin_id, &text).await;
+                    }
+                }
+                Ok(_) => (),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("The campaign alerts missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod campaign_alerts_tests {
+    use crate::{database, link_flags};
+    use crate::campaign_alerts::detect_coordinated_submissions;
+
+    #[test]
+    fn test_detect_coordinated_submissions() {
+        for user_id in 93001..93004 {
+            database::add_link(user_id, "https://campaign.example.com/");
+            database::add_link(user_id, "https://harmless-campaign.example.com/");
+        }
+
+        link_flags::flag_link("https://campaign.example.com/", "custom_rule", "Phishing");
+
+        let alerts = detect_coordinated_submissions(60, 2);
+
+        assert!(alerts.iter().any(|alert| alert.link == "https://campaign.example.com/" && alert.user_count == 3));
+        assert!(alerts.iter().all(|alert| alert.link != "https://harmless-campaign.example.com/"));
+        assert!(detect_coordinated_submissions(60, 3).iter().all(|alert| alert.link != "https://campaign.example.com/"));
+    }
+}
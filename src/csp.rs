@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+/// Directives that control which scripts and plugins may run on the page.
+/// Weak sources in other directives (for example, `data:` images) are harmless.
+const SCRIPT_DIRECTIVES: [&str; 4] = ["default-src", "script-src", "script-src-elem", "object-src"];
+
+/// Represents the weaknesses found in a Content-Security-Policy header.
+#[derive(Serialize, Deserialize, Default, PartialEq, Debug)]
+pub struct CspAnalysis {
+    pub has_unsafe_inline: bool,
+    pub has_unsafe_eval: bool,
+    pub allows_data_uris: bool,
+    pub allows_wildcard: bool,
+}
+
+impl CspAnalysis {
+    /// Checks whether the policy has at least one weakness.
+    pub fn has_weaknesses(&self) -> bool {
+        self.has_unsafe_inline || self.has_unsafe_eval || self.allows_data_uris || self.allows_wildcard
+    }
+}
+
+/// Analyzes the Content-Security-Policy header for known weaknesses.
+///
+/// Only the directives that control scripts (`default-src`, `script-src`, `script-src-elem`, `object-src`)
+/// are taken into account.
+///
+/// # Arguments
+///
+/// * `csp` - The value of the Content-Security-Policy header.
+///
+/// # Returns
+///
+/// A `CspAnalysis` describing the weaknesses of the policy.
+///
+/// # Example
+///
+/// ```
+/// let analysis = analyze_csp("default-src 'self'; script-src 'self' 'unsafe-eval'");
+/// assert!(analysis.has_unsafe_eval);
+/// ```
+pub fn analyze_csp(csp: &str) -> CspAnalysis {
+    let mut analysis = CspAnalysis::default();
+
+    for directive in csp.split(';') {
+        let mut tokens = directive.split_whitespace();
+
+        let name = match tokens.next() {
+            Some(name) => name.to_lowercase(),
+            None => continue
+        };
+
+        if !SCRIPT_DIRECTIVES.contains(&name.as_str()) {
+            continue;
+        }
+
+        for source in tokens {
+            match source.to_lowercase().as_str() {
+                "'unsafe-inline'" => analysis.has_unsafe_inline = true,
+                "'unsafe-eval'" => analysis.has_unsafe_eval = true,
+                "data:" => analysis.allows_data_uris = true,
+                "*" | "http:" | "https:" => analysis.allows_wildcard = true,
+                _ => ()
+            }
+        }
+    }
+
+    analysis
+}
+
+#[cfg(test)]
+mod csp_tests {
+    use crate::csp::*;
+
+    #[test]
+    fn test_strict_policy_has_no_weaknesses() {
+        let analysis = analyze_csp("default-src 'self'; img-src * data:; style-src 'self' 'unsafe-inline'");
+
+        assert!(!analysis.has_weaknesses());
+    }
+
+    #[test]
+    fn test_weak_policy() {
+        let analysis = analyze_csp("default-src *; script-src 'self' 'unsafe-inline' 'unsafe-eval' data:");
+
+        assert_eq!(analysis, CspAnalysis {
+            has_unsafe_inline: true,
+            has_unsafe_eval: true,
+            allows_data_uris: true,
+            allows_wildcard: true,
+        });
+    }
+}
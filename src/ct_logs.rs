@@ -0,0 +1,227 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::time::Duration;
+use reqwest::Client;
+use serde::Deserialize;
+use sqlite3::State;
+
+use crate::database;
+
+/// Represents the reasons the Certificate Transparency logs could not be queried.
+#[derive(Debug)]
+pub enum CtError {
+    /// The request to crt.sh failed.
+    Request(reqwest::Error),
+    /// crt.sh returned something other than the list of certificates.
+    InvalidResponse
+}
+
+impl fmt::Display for CtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CtError::Request(err) => write!(f, "the request to crt.sh failed: {err}"),
+            CtError::InvalidResponse => write!(f, "crt.sh returned an invalid response")
+        }
+    }
+}
+
+impl std::error::Error for CtError {}
+
+/// Represents a certificate found in the Certificate Transparency logs by crt.sh.
+#[derive(Deserialize)]
+struct CtEntry {
+    /// The names the certificate was issued for, separated by newlines.
+    name_value: String,
+}
+
+/// Represents a certificate issued for the domain or one of its subdomains, as returned by crt.sh.
+#[derive(Deserialize)]
+struct CtCertificateEntry {
+    id: i64,
+    issuer_name: String,
+    /// The beginning of the validity period, which is the time the certificate was issued.
+    not_before: String,
+}
+
+/// Represents a certificate found in the Certificate Transparency logs.
+#[derive(PartialEq, Debug)]
+pub struct CtCertificate {
+    /// The ID of the certificate in crt.sh.
+    pub cert_id: i64,
+    /// The time the certificate was issued, in the `YYYY-MM-DDTHH:MM:SS` format.
+    pub issued_at: String,
+    /// The distinguished name of the certificate authority.
+    pub issuer: String,
+}
+
+/// Queries crt.sh for the certificates issued for the subdomains of the domain.
+async fn fetch_ct_response(domain: &str) -> Result<String, CtError> {
+    // crt.sh is slow for popular domains
+    let client = Client::builder().timeout(Duration::from_secs(60)).build().unwrap();
+
+    client.get("https://crt.sh/")
+        .query(&[("q", format!("%.{domain}").as_str()), ("output", "json")])
+        .send().await.map_err(CtError::Request)?
+        .text().await.map_err(CtError::Request)
+}
+
+/// Finds the subdomains of the domain that certificates were issued for, using the crt.sh API.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain, for example `example.com`.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the sorted subdomains without duplicates.
+/// * An `Err` variant containing a `CtError` if crt.sh could not be queried.
+pub async fn query_ct_logs(domain: &str) -> Result<Vec<String>, CtError> {
+    let body = fetch_ct_response(domain).await?;
+
+    parse_ct_response(&body, domain)
+}
+
+/// Finds the certificates issued for the subdomains of the domain, using the crt.sh API.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain, for example `example.com`.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the certificates, the newest first.
+/// * An `Err` variant containing a `CtError` if crt.sh could not be queried.
+pub async fn query_ct_certificates(domain: &str) -> Result<Vec<CtCertificate>, CtError> {
+    let body = fetch_ct_response(domain).await?;
+
+    parse_ct_certificates(&body)
+}
+
+/// Extracts the subdomains of the domain from the response of crt.sh.
+///
+/// Wildcard names are counted as the domain they cover, and names outside the domain are skipped.
+fn parse_ct_response(body: &str, domain: &str) -> Result<Vec<String>, CtError> {
+    let entries: Vec<CtEntry> = serde_json::from_str(body).map_err(|_| CtError::InvalidResponse)?;
+    let suffix = format!(".{domain}");
+
+    let mut subdomains: Vec<String> = entries.iter()
+        .flat_map(|entry| entry.name_value.lines())
+        .map(|name| name.trim().trim_start_matches("*.").to_lowercase())
+        .filter(|name| name.ends_with(&suffix))
+        .collect();
+
+    subdomains.sort();
+    subdomains.dedup();
+
+    Ok(subdomains)
+}
+
+/// Extracts the certificates from the response of crt.sh. A certificate logged under several names is returned once.
+fn parse_ct_certificates(body: &str) -> Result<Vec<CtCertificate>, CtError> {
+    let entries: Vec<CtCertificateEntry> = serde_json::from_str(body).map_err(|_| CtError::InvalidResponse)?;
+
+    let mut certificates: Vec<CtCertificate> = entries.into_iter()
+        .map(|entry| CtCertificate {
+            cert_id: entry.id,
+            issued_at: entry.not_before,
+            issuer: entry.issuer_name,
+        })
+        .collect();
+
+    certificates.sort_by_key(|certificate| Reverse(certificate.cert_id));
+    certificates.dedup_by_key(|certificate| certificate.cert_id);
+
+    Ok(certificates)
+}
+
+/// Saves the subdomains of the domain found in the Certificate Transparency logs.
+///
+/// Subdomains that are already saved keep the time they were first discovered.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain.
+/// * `subdomains` - The subdomains.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn store_ct_subdomains(domain: &str, subdomains: &[String]) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR IGNORE INTO ct_subdomains (domain, subdomain, discovered_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+    for subdomain in subdomains {
+        db.reset().unwrap();
+        db.bind(1, domain).unwrap();
+        db.bind(2, subdomain.as_str()).unwrap();
+
+        db.next().unwrap();
+    }
+}
+
+/// Returns the saved subdomains of the domain found in the Certificate Transparency logs.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_ct_subdomains(domain: &str) -> Vec<String> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT subdomain FROM ct_subdomains WHERE domain = ? ORDER BY subdomain").unwrap();
+
+    db.bind(1, domain).unwrap();
+
+    let mut vec: Vec<String> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<String>(0).unwrap());
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod ct_logs_tests {
+    use crate::ct_logs::*;
+
+    #[test]
+    fn test_parse_ct_response() {
+        let body = r#"[
+            {"name_value": "login.ct-test.com\n*.mail.ct-test.com"},
+            {"name_value": "LOGIN.ct-test.com"},
+            {"name_value": "ct-test.com.evil.com"}
+        ]"#;
+
+        let subdomains = parse_ct_response(body, "ct-test.com").unwrap();
+
+        assert_eq!(subdomains, vec!["login.ct-test.com", "mail.ct-test.com"]);
+        assert!(matches!(parse_ct_response("<html>Rate limited</html>", "ct-test.com"), Err(CtError::InvalidResponse)));
+    }
+
+    #[test]
+    fn test_parse_ct_certificates() {
+        let body = r#"[
+            {"id": 10, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "not_before": "2024-01-01T00:00:00", "name_value": "a.ct-test.com"},
+            {"id": 12, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "not_before": "2024-02-01T00:00:00", "name_value": "b.ct-test.com"},
+            {"id": 10, "issuer_name": "C=US, O=Let's Encrypt, CN=R3", "not_before": "2024-01-01T00:00:00", "name_value": "c.ct-test.com"}
+        ]"#;
+
+        let certificates = parse_ct_certificates(body).unwrap();
+
+        assert_eq!(certificates.iter().map(|certificate| certificate.cert_id).collect::<Vec<i64>>(), vec![12, 10]);
+        assert_eq!(certificates[0].issued_at, "2024-02-01T00:00:00");
+    }
+
+    #[test]
+    fn test_store_ct_subdomains() {
+        store_ct_subdomains("ct-store-test.com", &["a.ct-store-test.com".to_string(), "b.ct-store-test.com".to_string()]);
+        store_ct_subdomains("ct-store-test.com", &["a.ct-store-test.com".to_string()]);
+
+        assert_eq!(get_ct_subdomains("ct-store-test.com"), vec!["a.ct-store-test.com", "b.ct-store-test.com"]);
+    }
+}
@@ -0,0 +1,249 @@
+use std::env;
+use std::time::Duration;
+use sqlite3::State;
+use teloxide::Bot;
+use tokio::sync::broadcast;
+
+use crate::{ct_logs, database, domain_family, message_queue};
+use crate::ct_logs::{CtCertificate, CtError};
+use crate::event_bus::{BotEvent, EVENT_BUS};
+
+/// The number of hours between the checks of the monitored domains if `CT_MONITOR_INTERVAL_HOURS` is not set.
+const DEFAULT_CT_MONITOR_INTERVAL_HOURS: u64 = 24;
+/// The number of new certificates listed in a notification, the rest are only counted.
+const MAX_CERTIFICATES_IN_NOTIFICATION: usize = 5;
+
+/// Starts monitoring the Certificate Transparency logs for the domain.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn register_domain(domain: &str) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR IGNORE INTO ct_monitored_domains (domain, registered_at) VALUES (?, datetime('now'))").unwrap();
+
+    db.bind(1, domain).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Returns the monitored domains that some user still has links to, the least recently checked first.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_monitored_domains() -> Vec<String> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT domain FROM ct_monitored_domains \
+        WHERE domain IN (SELECT domain_family FROM links WHERE is_hashed = 0) \
+        ORDER BY last_checked_at IS NOT NULL, last_checked_at").unwrap();
+
+    let mut vec: Vec<String> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<String>(0).unwrap());
+    }
+
+    vec
+}
+
+/// Saves the certificates of the domain and returns the ones that were not known before.
+///
+/// The first time a domain is checked, all of its certificates are saved as known, so users are only
+/// notified about the certificates issued after they started tracking the domain.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain.
+/// * `certificates` - The certificates found in the Certificate Transparency logs.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn store_new_certificates(domain: &str, certificates: Vec<CtCertificate>) -> Vec<CtCertificate> {
+    let connection = database::open_connection();
+
+    let mut db = connection.prepare("SELECT last_checked_at IS NULL FROM ct_monitored_domains WHERE domain = ?").unwrap();
+    db.bind(1, domain).unwrap();
+
+    let is_first_check = match db.next().unwrap() {
+        State::Row => db.read::<i64>(0).unwrap() != 0,
+        State::Done => true
+    };
+
+    let mut new_certificates: Vec<CtCertificate> = Vec::new();
+
+    for certificate in certificates {
+        let mut db = connection.prepare("INSERT OR IGNORE INTO ct_certificates (domain, cert_id, issued_at, issuer) VALUES (?, ?, ?, ?)").unwrap();
+
+        db.bind(1, domain).unwrap();
+        db.bind(2, certificate.cert_id).unwrap();
+        db.bind(3, certificate.issued_at.as_str()).unwrap();
+        db.bind(4, certificate.issuer.as_str()).unwrap();
+        db.next().unwrap();
+
+        let mut db = connection.prepare("SELECT changes()").unwrap();
+        db.next().unwrap();
+
+        if db.read::<i64>(0).unwrap() > 0 && !is_first_check {
+            new_certificates.push(certificate);
+        }
+    }
+
+    let mut db = connection.prepare("UPDATE ct_monitored_domains SET last_checked_at = datetime('now') WHERE domain = ?").unwrap();
+    db.bind(1, domain).unwrap();
+    db.next().unwrap();
+
+    new_certificates
+}
+
+/// Returns the users who have links to the domain.
+fn get_domain_watchers(domain: &str) -> Vec<u64> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT DISTINCT user_id FROM links WHERE domain_family = ? AND is_hashed = 0").unwrap();
+
+    db.bind(1, domain).unwrap();
+
+    let mut vec: Vec<u64> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<i64>(0).unwrap() as u64);
+    }
+
+    vec
+}
+
+/// Queries the Certificate Transparency logs for the domain and returns the certificates issued since the previous check.
+///
+/// # Arguments
+///
+/// * `domain` - The registrable domain.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the new certificates.
+/// * An `Err` variant containing a `CtError` if crt.sh could not be queried.
+pub async fn check_new_certificates(domain: &str) -> Result<Vec<CtCertificate>, CtError> {
+    let certificates = ct_logs::query_ct_certificates(domain).await?;
+
+    Ok(store_new_certificates(domain, certificates))
+}
+
+/// Launches the monitoring of the Certificate Transparency logs for the domains of the users' links.
+///
+/// The domain of every new link is registered for monitoring. The monitored domains are checked
+/// every `CT_MONITOR_INTERVAL_HOURS` hours, and the users who have links to a domain are notified
+/// about the certificates issued for it since the previous check.
+///
+/// # Arguments
+///
+/// * `bot` - Bot instance.
+pub fn launch_ct_monitor(bot: Bot) {
+    let interval_hours = env::var("CT_MONITOR_INTERVAL_HOURS").ok()
+        .and_then(|hours| hours.parse::<u64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_CT_MONITOR_INTERVAL_HOURS);
+
+    let mut receiver = EVENT_BUS.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                // Hashed links are not valid URLs, so their domains are never registered
+                Ok(BotEvent::LinkAdded { link, .. }) => {
+                    if let Ok(domain) = domain_family::get_domain_family(&link) {
+                        register_domain(&domain);
+                    }
+                }
+                Ok(_) => (),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("The Certificate Transparency monitor missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break
+            }
+        }
+    });
+
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_hours * 3600));
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            for domain in get_monitored_domains() {
+                let certificates = match check_new_certificates(&domain).await {
+                    Ok(certificates) => certificates,
+                    Err(err) => {
+                        warn!("Failed to check the certificates of {}: {}", domain, err);
+                        continue;
+                    }
+                };
+
+                if certificates.is_empty() {
+                    continue;
+                }
+
+                info!("Found {} new certificates for {}", certificates.len(), domain);
+
+                let text = format_certificates_notification(&domain, &certificates);
+
+                for user_id in get_domain_watchers(&domain) {
+                    message_queue::send_message_with_persistence(&bot, user_id, &text).await;
+                }
+            }
+        }
+    });
+}
+
+/// Creates the notification about the new certificates of the domain.
+fn format_certificates_notification(domain: &str, certificates: &[CtCertificate]) -> String {
+    let mut text = format!("🔏 Для домена {domain} выпущены новые сертификаты. Если их выпустил не владелец сайта, \
+        это может быть признаком фишинговой кампании\n");
+
+    for certificate in certificates.iter().take(MAX_CERTIFICATES_IN_NOTIFICATION) {
+        text = format!("{text}\n{} — {}", certificate.issued_at.replace('T', " "), certificate.issuer);
+    }
+
+    if certificates.len() > MAX_CERTIFICATES_IN_NOTIFICATION {
+        text = format!("{text}\n...и еще {}", certificates.len() - MAX_CERTIFICATES_IN_NOTIFICATION);
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod ct_monitor_tests {
+    use crate::ct_logs::CtCertificate;
+    use crate::ct_monitor::*;
+    use crate::database;
+
+    fn certificate(cert_id: i64) -> CtCertificate {
+        CtCertificate {
+            cert_id,
+            issued_at: "2024-01-01T00:00:00".to_string(),
+            issuer: "C=US, O=Let's Encrypt, CN=R3".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_only_certificates_after_the_first_check_are_new() {
+        database::open_connection().execute("DELETE FROM ct_certificates WHERE domain = 'ct-monitor-test.com'; \
+            DELETE FROM ct_monitored_domains WHERE domain = 'ct-monitor-test.com'").unwrap();
+
+        register_domain("ct-monitor-test.com");
+
+        assert!(store_new_certificates("ct-monitor-test.com", vec![certificate(1), certificate(2)]).is_empty());
+
+        let new_certificates = store_new_certificates("ct-monitor-test.com", vec![certificate(3), certificate(2)]);
+
+        assert_eq!(new_certificates, vec![certificate(3)]);
+        assert!(format_certificates_notification("ct-monitor-test.com", &new_certificates).contains("2024-01-01 00:00:00"));
+    }
+}
@@ -0,0 +1,278 @@
+use regex::Regex;
+use sqlite3::State;
+
+use crate::database;
+
+/// Represents how the pattern of a custom rule is written.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PatternType {
+    Regex,
+    Glob
+}
+
+/// Represents what happens to a link that matches a custom rule.
+///
+/// When a link matches several rules, `Block` wins over `Allow`, and `Allow` wins over `Flag`,
+/// so an operator can silence a flag for trusted sites, but can never accidentally unblock a link.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RuleAction {
+    Block,
+    Allow,
+    Flag
+}
+
+/// Represents a rule defined by the operator.
+pub struct CustomRule {
+    pub id: i64,
+    pub pattern: String,
+    pub pattern_type: PatternType,
+    pub action: RuleAction,
+    pub reason: String,
+}
+
+/// Represents the rule that decided the fate of a link.
+pub struct CustomRuleResult {
+    pub rule_id: i64,
+    pub action: RuleAction,
+    pub reason: String,
+}
+
+impl PatternType {
+    /// Parses the pattern type as it is stored in the database.
+    pub fn parse(pattern_type: &str) -> Option<PatternType> {
+        match pattern_type {
+            "regex" => Some(PatternType::Regex),
+            "glob" => Some(PatternType::Glob),
+            _ => None
+        }
+    }
+
+    /// Returns the pattern type as it is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatternType::Regex => "regex",
+            PatternType::Glob => "glob"
+        }
+    }
+}
+
+impl RuleAction {
+    /// Parses the action as it is stored in the database.
+    pub fn parse(action: &str) -> Option<RuleAction> {
+        match action {
+            "block" => Some(RuleAction::Block),
+            "allow" => Some(RuleAction::Allow),
+            "flag" => Some(RuleAction::Flag),
+            _ => None
+        }
+    }
+
+    /// Returns the action as it is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleAction::Block => "block",
+            RuleAction::Allow => "allow",
+            RuleAction::Flag => "flag"
+        }
+    }
+
+    /// Returns the priority of the action. The action with the highest priority wins.
+    fn priority(&self) -> u8 {
+        match self {
+            RuleAction::Block => 2,
+            RuleAction::Allow => 1,
+            RuleAction::Flag => 0
+        }
+    }
+}
+
+/// Compiles the pattern of a rule into a regular expression.
+///
+/// Glob patterns are matched against the whole URL: `*` matches any sequence of characters
+/// and `?` matches any single character.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern of the rule.
+/// * `pattern_type` - How the pattern is written.
+pub fn compile_pattern(pattern: &str, pattern_type: PatternType) -> Result<Regex, regex::Error> {
+    match pattern_type {
+        PatternType::Regex => Regex::new(pattern),
+        PatternType::Glob => {
+            let mut regex = String::from("(?i)^");
+
+            for char in pattern.chars() {
+                match char {
+                    '*' => regex.push_str(".*"),
+                    '?' => regex.push('.'),
+                    _ => regex.push_str(&regex::escape(&char.to_string()))
+                }
+            }
+
+            regex.push('$');
+
+            Regex::new(&regex)
+        }
+    }
+}
+
+/// Matches the URL against all custom rules.
+///
+/// # Arguments
+///
+/// * `url` - The URL to check.
+///
+/// # Returns
+///
+/// The matching rule with the highest-priority action, or `None` if no rule matches.
+/// Rules whose pattern cannot be compiled are skipped.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn apply_custom_rules(url: &str) -> Option<CustomRuleResult> {
+    let mut result: Option<CustomRuleResult> = None;
+
+    for rule in get_custom_rules() {
+        let regex = match compile_pattern(&rule.pattern, rule.pattern_type) {
+            Ok(regex) => regex,
+            Err(err) => {
+                warn!("The custom rule {} has an invalid pattern: {}", rule.id, err);
+                continue;
+            }
+        };
+
+        if !regex.is_match(url) {
+            continue;
+        }
+
+        if result.as_ref().map(|best| rule.action.priority() > best.action.priority()).unwrap_or(true) {
+            result = Some(CustomRuleResult {
+                rule_id: rule.id,
+                action: rule.action,
+                reason: rule.reason,
+            });
+        }
+    }
+
+    result
+}
+
+/// Returns all custom rules.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_custom_rules() -> Vec<CustomRule> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT id, pattern, pattern_type, action, reason FROM custom_scan_rules ORDER BY id").unwrap();
+
+    let mut vec: Vec<CustomRule> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let pattern_type = PatternType::parse(&db.read::<String>(2).unwrap());
+        let action = RuleAction::parse(&db.read::<String>(3).unwrap());
+
+        // Rules with unknown types or actions may have been added manually, skip them
+        if let (Some(pattern_type), Some(action)) = (pattern_type, action) {
+            vec.push(CustomRule {
+                id: db.read::<i64>(0).unwrap(),
+                pattern: db.read::<String>(1).unwrap(),
+                pattern_type,
+                action,
+                reason: db.read::<String>(4).unwrap_or_default(),
+            });
+        }
+    }
+
+    vec
+}
+
+/// Adds a new custom rule.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern of the rule.
+/// * `pattern_type` - How the pattern is written.
+/// * `action` - What happens to the matching links.
+/// * `reason` - The description of the rule shown to users.
+///
+/// # Returns
+///
+/// The ID of the new rule, or the error of compiling the pattern.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_custom_rule(pattern: &str, pattern_type: PatternType, action: RuleAction, reason: &str) -> Result<i64, regex::Error> {
+    compile_pattern(pattern, pattern_type)?;
+
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO custom_scan_rules (pattern, pattern_type, action, reason) VALUES (?, ?, ?, ?)").unwrap();
+
+    db.bind(1, pattern).unwrap();
+    db.bind(2, pattern_type.as_str()).unwrap();
+    db.bind(3, action.as_str()).unwrap();
+    db.bind(4, reason).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT last_insert_rowid()").unwrap();
+    db.next().unwrap();
+
+    Ok(db.read::<i64>(0).unwrap())
+}
+
+/// Deletes the custom rule.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the rule.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn delete_custom_rule(id: i64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("DELETE FROM custom_scan_rules WHERE id = ?").unwrap();
+
+    db.bind(1, id).unwrap();
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod custom_scan_rules_tests {
+    use crate::custom_scan_rules::*;
+
+    #[test]
+    fn test_compile_glob_pattern() {
+        let regex = compile_pattern("https://*.evil.com/*", PatternType::Glob).unwrap();
+
+        assert!(regex.is_match("https://login.evil.com/index.php"));
+        assert!(!regex.is_match("https://evil.com.example.org/"));
+    }
+
+    #[test]
+    fn test_block_wins_over_allow_and_flag() {
+        let flag = add_custom_rule("*.rules-test.example*", PatternType::Glob, RuleAction::Flag, "Flagged").unwrap();
+        let allow = add_custom_rule("rules-test\\.example", PatternType::Regex, RuleAction::Allow, "Trusted").unwrap();
+
+        let result = apply_custom_rules("https://www.rules-test.example/").unwrap();
+        assert_eq!(result.action, RuleAction::Allow);
+
+        let block = add_custom_rule("^https://www\\.rules-test", PatternType::Regex, RuleAction::Block, "Blocked").unwrap();
+
+        let result = apply_custom_rules("https://www.rules-test.example/").unwrap();
+        assert_eq!(result.action, RuleAction::Block);
+        assert_eq!(result.rule_id, block);
+
+        delete_custom_rule(flag);
+        delete_custom_rule(allow);
+        delete_custom_rule(block);
+
+        assert!(apply_custom_rules("https://www.rules-test.example/").is_none());
+    }
+}
@@ -1,5 +1,16 @@
 use std::env;
-use sqlite3::{State, Statement};
+use std::fmt;
+use std::fs;
+use std::sync::{Mutex, TryLockError};
+use std::time::Instant;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use sqlite3::{Connection, State, Statement};
+
+use crate::event_bus::{BotEvent, EVENT_BUS};
+use crate::user_activity::SQLITE_DATETIME_FORMAT;
+use crate::{domain_family, domain_reputation, fingerprint, url_normalizer, user_preferences};
 
 /// Represents a link associated with a user.
 pub struct Links {
@@ -7,6 +18,55 @@ pub struct Links {
     pub link: String,
 }
 
+/// Represents an error that aborted an operation on the database.
+#[derive(Debug)]
+pub enum DatabaseError {
+    /// A statement of the operation failed.
+    Query(sqlite3::Error)
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Query(err) => write!(f, "the database query failed: {err}")
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+/// Represents the result of deduplicating the links stored in the database.
+pub struct DeduplicationReport {
+    pub duplicate_rows_removed: u64,
+    pub links_consolidated: u64,
+}
+
+/// Represents the result of compacting the database.
+pub struct VacuumReport {
+    pub duration_ms: u128,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+}
+
+/// The number of hours after deleting a link during which adding it again has to be confirmed,
+/// if the `DEDUP_WINDOW_HOURS` environment variable is not set.
+const DEFAULT_DEDUP_WINDOW_HOURS: u32 = 24;
+
+lazy_static! {
+    /// Held while the database is being compacted, so two compactions never run at once.
+    static ref VACUUM_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Opens a connection to the database specified in the `DATABASE_URL` environment variable.
+///
+/// # Panics
+///
+/// This function panics if the `DATABASE_URL` environment variable is not set or if there is a failure connecting to the database.
+pub fn open_connection() -> Connection {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+    sqlite3::open(database_url).expect("Failed to connect to the database")
+}
+
 /// Adds a new link to the database for a given user.
 ///
 /// # Arguments
@@ -23,17 +83,68 @@ pub struct Links {
 /// This function panics if the `DATABASE_URL` environment variable is not set or if there is a failure connecting to the database.
 pub fn add_link(user_id: u64, link: &str) -> State {
     // Adding a new row to the database
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+    let connection = open_connection();
+
+    let is_hashed = user_preferences::get_user_preferences(user_id).privacy_mode;
+    let link = &get_stored_link(user_id, link);
 
-    let mut db = connection.prepare("INSERT INTO links VALUES (?, ?)").unwrap();
+    let mut db = connection.prepare("INSERT INTO links (user_id, link, domain_family, is_hashed, domain, created_at) VALUES (?, ?, ?, ?, ?, datetime('now'))").unwrap();
 
-    // The numbers 1 and 2 denote the location of the question mark in the query
+    // The numbers 1-5 denote the location of the question mark in the query
     db.bind(1, user_id.to_string().as_str()).unwrap();
-    db.bind(2, link).unwrap();
+    db.bind(2, link.as_str()).unwrap();
+
+    // The domain of a hashed link would reveal what the link is
+    match domain_family::get_domain_family(link) {
+        Ok(family) if !is_hashed => db.bind(3, family.as_str()).unwrap(),
+        _ => db.bind(3, ()).unwrap()
+    }
+
+    db.bind(4, is_hashed as i64).unwrap();
+
+    // The domain is stored separately, so the reputation of a domain can be computed without parsing every link.
+    // A hashed link is not a URL, so it has no domain
+    match domain_reputation::get_domain(link) {
+        Some(domain) => db.bind(5, domain.as_str()).unwrap(),
+        None => db.bind(5, ()).unwrap()
+    }
+
+    let state = db.next().unwrap();
+
+    // Keep the global statistics up to date, so the same link submitted by different users is counted once
+    let mut db = connection.prepare("INSERT INTO link_global_stats (link, submission_count, user_count) VALUES (?, 1, 1) \
+        ON CONFLICT(link) DO UPDATE SET submission_count = submission_count + 1, \
+        user_count = (SELECT COUNT(DISTINCT user_id) FROM links WHERE link = excluded.link)").unwrap();
+    db.bind(1, link.as_str()).unwrap();
+    db.next().unwrap();
+
+    EVENT_BUS.emit(BotEvent::LinkAdded { user_id, link: link.to_string() });
 
     // Save the changes to the database
-    db.next().unwrap()
+    state
+}
+
+/// Returns the link in the form it is stored for the user: the SHA-256 hash of the link
+/// if the user has turned on the privacy mode, and the link itself otherwise.
+///
+/// Hashes of the same link are equal, so hashed links can still be found and deduplicated.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `link` - The link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_stored_link(user_id: u64, link: &str) -> String {
+    if user_preferences::get_user_preferences(user_id).privacy_mode {
+        Sha256::digest(link.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+    else {
+        link.to_string()
+    }
 }
 
 /// Checks if a link exists for a given user.
@@ -48,8 +159,83 @@ pub fn add_link(user_id: u64, link: &str) -> State {
 /// Returns `true` if the link exists for the user, `false` otherwise.
 pub fn is_link_exists(user_id: u64, link: &str) -> bool {
     // We get the link list and check if there are any items in it
-    let vec: Vec<Links> = get_all_links_from_user(user_id, Option::from(link));
-    return vec.iter().count() > 0
+    let vec: Vec<Links> = get_all_links_from_user(user_id, Option::from(get_stored_link(user_id, link).as_str()));
+    !vec.is_empty()
+}
+
+/// Returns the link of the user that leads to the same page as the given link, but differs in tracking parameters.
+///
+/// Hashed links are never compared, as their query parameters are unknown.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `link` - The link to check.
+///
+/// # Returns
+///
+/// The link as the user added it, or `None` if there is no such link.
+///
+/// # Panics
+///
+/// This function panics if the `DATABASE_URL` environment variable is not set or if there is a failure connecting to the database.
+pub fn find_canonical_duplicate(user_id: u64, link: &str) -> Option<String> {
+    let canonical_url = url_normalizer::compute_canonical_url(link);
+
+    let connection = open_connection();
+    let mut db = connection.prepare("SELECT link FROM links WHERE user_id = ? AND is_hashed = 0 AND link != ?").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, link).unwrap();
+
+    while let State::Row = db.next().unwrap() {
+        let stored_link = db.read::<String>(0).unwrap();
+
+        if url_normalizer::compute_canonical_url(&stored_link) == canonical_url {
+            return Some(stored_link);
+        }
+    }
+
+    None
+}
+
+/// Returns the number of hours after deleting a link during which adding it again has to be confirmed.
+///
+/// The value is taken from the `DEDUP_WINDOW_HOURS` environment variable, 0 turns the confirmation off.
+pub fn get_dedup_window_hours() -> u32 {
+    env::var("DEDUP_WINDOW_HOURS").ok()
+        .and_then(|hours| hours.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_DEDUP_WINDOW_HOURS)
+}
+
+/// Returns the time the user last deleted the link, if it was deleted recently.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `link` - The link to check.
+/// * `window_hours` - How many hours ago the link may have been deleted.
+///
+/// # Returns
+///
+/// `None` if the user has not deleted the link within the last `window_hours` hours.
+///
+/// # Panics
+///
+/// This function panics if the `DATABASE_URL` environment variable is not set or if there is a failure connecting to the database.
+pub fn get_recent_deletion(user_id: u64, link: &str, window_hours: u32) -> Option<DateTime<Utc>> {
+    let connection = open_connection();
+    let mut db = connection.prepare("SELECT MAX(deleted_at) FROM link_deletions WHERE user_id = ? AND link = ? AND deleted_at > datetime('now', ?)").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, get_stored_link(user_id, link).as_str()).unwrap();
+    db.bind(3, format!("-{window_hours} hours").as_str()).unwrap();
+    db.next().unwrap();
+
+    // There is no deletion if the maximum is NULL
+    let deleted_at = db.read::<String>(0).ok()?;
+
+    NaiveDateTime::parse_from_str(&deleted_at, SQLITE_DATETIME_FORMAT).ok().map(|deleted_at| deleted_at.and_utc())
 }
 
 /// Returns a vector of links for a given user ID and optional link.
@@ -72,18 +258,15 @@ pub fn is_link_exists(user_id: u64, link: &str) -> bool {
 /// This function will panic if the `DATABASE_URL` environment variable is not set
 /// or if there is a problem connecting to the database.
 pub fn get_all_links_from_user(user_id: u64, link: Option<&str>) -> Vec<Links> {
-    let query: &str;
-
     // Depending on whether the reference is None, type in your query
-    if link.is_some() {
-        query = "SELECT * FROM links WHERE user_id = ? AND link = ?";
+    let query = if link.is_some() {
+        "SELECT * FROM links WHERE user_id = ? AND link = ?"
     }
     else {
-        query = "SELECT * FROM links WHERE user_id = ?";
-    }
+        "SELECT * FROM links WHERE user_id = ?"
+    };
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+    let connection = open_connection();
 
     let mut db = connection.prepare(query).unwrap();
     db.bind(1, user_id.to_string().as_str()).unwrap();
@@ -102,6 +285,31 @@ pub fn get_all_links_from_user(user_id: u64, link: Option<&str>) -> Vec<Links> {
     vec
 }
 
+/// Returns the links of the user that belong to the domain family, that is, share the registrable domain.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `family` - The registrable domain, for example `example.co.uk`.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_links_by_family(user_id: u64, family: &str) -> Vec<Links> {
+    let connection = open_connection();
+
+    let mut db = connection.prepare("SELECT * FROM links WHERE user_id = ? AND domain_family = ?").unwrap();
+    db.bind(1, user_id.to_string().as_str()).unwrap();
+    db.bind(2, family).unwrap();
+
+    let mut vec: Vec<Links> = Vec::new();
+
+    add_to_vec_from_database(db, &mut vec);
+
+    vec
+}
+
 /// Get all links from the database.
 ///
 /// # Returns
@@ -115,8 +323,7 @@ pub fn get_all_links_from_user(user_id: u64, link: Option<&str>) -> Vec<Links> {
 pub fn get_all_links() -> Vec<Links> {
     let query = "SELECT * FROM links";
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+    let connection = open_connection();
 
     let db = connection.prepare(query).unwrap();
 
@@ -175,14 +382,31 @@ fn add_to_vec_from_database(mut db: Statement, vec: &mut Vec<Links>) {
 /// or if there is a problem connecting to the database.
 pub fn clear_all_links(user_id: u64) -> State {
     // Specify in the request that we want to delete all histories in which the user ID matches the required one
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+    let connection = open_connection();
+
+    // The deletions are shown in the timeline of the user
+    let mut db = connection.prepare("INSERT INTO link_deletions (user_id, link, deleted_at) SELECT user_id, link, datetime('now') FROM links WHERE user_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("DELETE FROM link_reminders WHERE user_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("DELETE FROM link_view_counts WHERE user_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
     let mut db = connection.prepare("DELETE FROM links WHERE user_id = ?").unwrap();
 
     db.bind(1, user_id.to_string().as_str()).unwrap();
     
     // Also, don't forget to save the changes
-    db.next().unwrap()
+    let state = db.next().unwrap();
+
+    EVENT_BUS.emit(BotEvent::LinksCleared { user_id });
+
+    state
 }
 
 /// Deletes some links from the database for a given user ID.
@@ -206,20 +430,407 @@ pub fn clear_all_links(user_id: u64) -> State {
 /// delete_some_links(user_id, links);
 /// ```
 pub fn delete_some_links(user_id: u64, links: Vec<&str>) {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+    let connection = open_connection();
+
+    for link in &links {
+        let mut db = connection.prepare("INSERT INTO link_deletions (user_id, link, deleted_at) SELECT user_id, link, datetime('now') FROM links WHERE user_id = ? AND link = ?").unwrap();
+
+        db.bind(1, user_id as i64).unwrap();
+        db.bind(2, *link).unwrap();
+
+        db.next().unwrap();
+
+        let mut db = connection.prepare("DELETE FROM link_reminders WHERE user_id = ? AND link = ?").unwrap();
+
+        db.bind(1, user_id as i64).unwrap();
+        db.bind(2, *link).unwrap();
+
+        db.next().unwrap();
+
+        let mut db = connection.prepare("DELETE FROM link_view_counts WHERE user_id = ? AND link = ?").unwrap();
+
+        db.bind(1, user_id as i64).unwrap();
+        db.bind(2, *link).unwrap();
+
+        db.next().unwrap();
 
-    for link in links {
         let mut db = connection.prepare("DELETE FROM links WHERE user_id = ? AND link = ?").unwrap();
 
         db.bind(1, user_id.to_string().as_str()).unwrap();
-        db.bind(2, link).unwrap();
+        db.bind(2, *link).unwrap();
 
         db.next().unwrap();
     }
+
+    EVENT_BUS.emit(BotEvent::LinksDeleted { user_id, links: links.iter().map(|link| link.to_string()).collect() });
+}
+
+/// Saves the SimHash fingerprint of the page for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `simhash` - The fingerprint as a hexadecimal string.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_page_simhash(link: &str, simhash: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET page_simhash = ? WHERE link = ?").unwrap();
+
+    db.bind(1, simhash).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the title of the page for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `title` - The title of the page.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_page_title(link: &str, title: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET page_title = ? WHERE link = ?").unwrap();
+
+    db.bind(1, title).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the structured metadata of the page for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `page_metadata_json` - The Open Graph and schema.org metadata of the page serialized to JSON.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_page_metadata(link: &str, page_metadata_json: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET page_metadata_json = ? WHERE link = ?").unwrap();
+
+    db.bind(1, page_metadata_json).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the hash of the site's favicon for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `favicon_hash` - The FNV-1a hash of the favicon as a hexadecimal string.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_favicon_hash(link: &str, favicon_hash: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET favicon_hash = ? WHERE link = ?").unwrap();
+
+    db.bind(1, favicon_hash).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the MIME type of the resource at the link for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `content_type` - The MIME type without parameters, for example `text/html`.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_content_type(link: &str, content_type: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET content_type = ? WHERE link = ?").unwrap();
+
+    db.bind(1, content_type).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the result of verifying the certificate chain of the site for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `tls_verification_json` - The result of the verification as JSON.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_tls_verification(link: &str, tls_verification_json: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET tls_verification_json = ? WHERE link = ?").unwrap();
+
+    db.bind(1, tls_verification_json).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves whether the robots.txt file of the site allows the bot to download the page, for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `crawl_allowed` - Whether downloading the page is allowed.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_crawl_allowed(link: &str, crawl_allowed: bool) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET crawl_allowed = ? WHERE link = ?").unwrap();
+
+    db.bind(1, crawl_allowed as i64).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the analysis of the Content-Security-Policy header for every row with the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `csp_analysis_json` - The analysis serialized to JSON.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_csp_analysis(link: &str, csp_analysis_json: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET csp_analysis_json = ? WHERE link = ?").unwrap();
+
+    db.bind(1, csp_analysis_json).unwrap();
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the chain of redirects the user's link was reached through.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `link` - The link.
+/// * `redirect_chain_json` - The URLs of the chain serialized to JSON.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_redirect_chain(user_id: u64, link: &str, redirect_chain_json: &str) {
+    let connection = open_connection();
+    let mut db = connection.prepare("UPDATE links SET redirect_chain_json = ? WHERE user_id = ? AND link = ?").unwrap();
+
+    db.bind(1, redirect_chain_json).unwrap();
+    db.bind(2, user_id as f64).unwrap();
+    db.bind(3, link).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Finds the links whose pages are similar to the page of the given link.
+///
+/// SQLite cannot count bits, so the fingerprints are loaded from the database and
+/// the Hamming distance between them is calculated in place.
+///
+/// # Arguments
+///
+/// * `link` - The link to compare with.
+/// * `max_hamming_distance` - The maximum number of differing bits in the fingerprints.
+///
+/// # Returns
+///
+/// The links with similar pages, excluding the link itself. The vector is empty if
+/// the fingerprint of the link has not been calculated yet.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn find_similar_links(link: &str, max_hamming_distance: u8) -> Vec<Links> {
+    let connection = open_connection();
+
+    let mut db = connection.prepare("SELECT page_simhash FROM links WHERE link = ? AND page_simhash IS NOT NULL LIMIT 1").unwrap();
+    db.bind(1, link).unwrap();
+
+    let simhash = match db.next().unwrap() {
+        State::Row => u64::from_str_radix(&db.read::<String>(0).unwrap(), 16).unwrap_or_default(),
+        State::Done => return Vec::new()
+    };
+
+    let mut db = connection.prepare("SELECT user_id, link, page_simhash FROM links WHERE link != ? AND page_simhash IS NOT NULL").unwrap();
+    db.bind(1, link).unwrap();
+
+    let mut vec: Vec<Links> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let other_simhash = u64::from_str_radix(&db.read::<String>(2).unwrap(), 16).unwrap_or_default();
+
+        if fingerprint::hamming_distance(simhash, other_simhash) <= max_hamming_distance as u32 {
+            vec.push(Links {
+                user_id: db.read::<f64>(0).unwrap(),
+                link: db.read::<String>(1).unwrap(),
+            });
+        }
+    }
+
+    vec
+}
+
+/// Returns the links that are stored by at least `min_copies` rows, together with the number of rows.
+///
+/// This allows previewing what [`deduplicate_global_stats`] is going to consolidate.
+///
+/// # Arguments
+///
+/// * `min_copies` - The minimum number of rows a link must have to be returned.
+///
+/// # Returns
+///
+/// A vector of `(link, copies)` tuples, sorted by the number of copies in descending order.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_deduplication_candidates(min_copies: u32) -> Vec<(String, u64)> {
+    let connection = open_connection();
+    let mut db = connection.prepare("SELECT link, COUNT(*) FROM links GROUP BY link HAVING COUNT(*) >= ? ORDER BY COUNT(*) DESC").unwrap();
+
+    db.bind(1, min_copies as i64).unwrap();
+
+    let mut vec: Vec<(String, u64)> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push((db.read::<String>(0).unwrap(), db.read::<i64>(1).unwrap() as u64));
+    }
+
+    vec
+}
+
+/// Consolidates the global link statistics and removes duplicate rows from the `links` table.
+///
+/// Rows with the same user ID and link are collapsed into one, after which the submission and
+/// user counts in `link_global_stats` are merged with the aggregated contents of `links`.
+/// Everything is done in a single transaction.
+///
+/// # Returns
+///
+/// A `DeduplicationReport` describing the changes, or the database error that aborted the operation.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn deduplicate_global_stats() -> Result<DeduplicationReport, DatabaseError> {
+    let connection = open_connection();
+
+    connection.execute("BEGIN").map_err(DatabaseError::Query)?;
+
+    let result = (|| {
+        connection.execute("DELETE FROM links WHERE rowid NOT IN (SELECT MIN(rowid) FROM links GROUP BY user_id, link)")?;
+        let duplicate_rows_removed = read_changes(&connection)?;
+
+        connection.execute("INSERT INTO link_global_stats (link, submission_count, user_count) \
+            SELECT link, COUNT(*), COUNT(DISTINCT user_id) FROM links WHERE true GROUP BY link \
+            ON CONFLICT(link) DO UPDATE SET submission_count = MAX(submission_count, excluded.submission_count), \
+            user_count = excluded.user_count")?;
+        let links_consolidated = read_changes(&connection)?;
+
+        Ok(DeduplicationReport {
+            duplicate_rows_removed,
+            links_consolidated,
+        })
+    })();
+
+    match result {
+        Ok(_) => connection.execute("COMMIT").map_err(DatabaseError::Query)?,
+        Err(_) => connection.execute("ROLLBACK").map_err(DatabaseError::Query)?,
+    }
+
+    result.map_err(DatabaseError::Query)
+}
+
+/// Compacts the database file and refreshes the statistics used by the query planner.
+///
+/// # Returns
+///
+/// * `Some` containing the duration of the operation and the size of the file before and after it.
+/// * `None` if the database is already being compacted.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn vacuum_database() -> Option<Result<VacuumReport, sqlite3::Error>> {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+
+    vacuum_database_file(&database_url)
+}
+
+/// Compacts the database file at the path and refreshes the statistics used by the query planner.
+fn vacuum_database_file(database_url: &str) -> Option<Result<VacuumReport, sqlite3::Error>> {
+    let _lock = match VACUUM_LOCK.try_lock() {
+        Ok(lock) => lock,
+        // A compaction that panicked has finished, so the lock is taken over instead of reporting it as running
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        Err(TryLockError::WouldBlock) => return None
+    };
+
+    let get_file_size = || fs::metadata(database_url).map(|metadata| metadata.len()).unwrap_or(0);
+
+    let size_before_bytes = get_file_size();
+    let time_now = Instant::now();
+
+    let connection = sqlite3::open(database_url).expect("Failed to connect to the database");
+
+    let result = connection.execute("VACUUM").and_then(|_| connection.execute("ANALYZE"));
+
+    Some(result.map(|_| VacuumReport {
+        duration_ms: time_now.elapsed().as_millis(),
+        size_before_bytes,
+        size_after_bytes: get_file_size(),
+    }))
+}
+
+/// Returns the number of rows changed by the last statement executed on the connection.
+fn read_changes(connection: &Connection) -> Result<u64, sqlite3::Error> {
+    let mut db = connection.prepare("SELECT changes()")?;
+    db.next()?;
+
+    Ok(db.read::<i64>(0)? as u64)
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod database_test {
     use super::*;
 
@@ -235,6 +846,55 @@ mod database_test {
         assert!(true)
     }
 
+    #[test]
+    fn test_get_links_by_family() {
+        add_link(77001, "https://shop.family-test.co.uk/cart");
+        add_link(77001, "https://family-test.co.uk/");
+        add_link(77001, "https://other-family-test.co.uk/");
+
+        let links = get_links_by_family(77001, "family-test.co.uk");
+
+        clear_all_links(77001);
+
+        assert_eq!(links.len(), 2);
+    }
+
+    #[test]
+    fn test_vacuum_database() {
+        // VACUUM needs exclusive access, so it runs on a separate file instead of the database the other tests use
+        let database_url = env::temp_dir().join(format!("sparkle_vacuum_test_{}.db", std::process::id()));
+        let database_url = database_url.to_str().unwrap();
+
+        sqlite3::open(database_url).unwrap().execute("CREATE TABLE IF NOT EXISTS links (user_id INTEGER, link TEXT); \
+            INSERT INTO links VALUES (1, 'https://vacuum.example.com/')").unwrap();
+
+        // A panic during a previous compaction must not block the next ones
+        let _ = std::thread::spawn(|| {
+            let _lock = VACUUM_LOCK.lock().unwrap();
+            panic!("The compaction failed");
+        }).join();
+
+        let report = vacuum_database_file(database_url).unwrap().unwrap();
+
+        fs::remove_file(database_url).unwrap();
+
+        assert!(report.size_after_bytes > 0);
+    }
+
+    #[test]
+    fn test_privacy_mode_stores_hash() {
+        user_preferences::set_privacy_mode(88001, true);
+        add_link(88001, "https://private.example.com/");
+
+        let links = get_all_links_from_user(88001, None);
+
+        assert_eq!(links[0].link, "9b08ff652557a7fe8226ffdcf1ca41e9b4aadc59b80d0f593990f75915884db7");
+        assert!(is_link_exists(88001, "https://private.example.com/"));
+
+        clear_all_links(88001);
+        user_preferences::set_privacy_mode(88001, false);
+    }
+
     #[test]
     fn test_is_link_exists() {
         let bool1 = is_link_exists(654352, "Ggg");
@@ -246,6 +906,35 @@ mod database_test {
         assert!(true)
     }
 
+    #[test]
+    fn test_find_canonical_duplicate() {
+        add_link(88102, "https://tracked.example.com/page?id=1&utm_source=mail");
+
+        assert_eq!(
+            find_canonical_duplicate(88102, "https://tracked.example.com/page?id=1&fbclid=abc").as_deref(),
+            Some("https://tracked.example.com/page?id=1&utm_source=mail")
+        );
+        assert!(find_canonical_duplicate(88102, "https://tracked.example.com/page?id=2").is_none());
+        assert!(find_canonical_duplicate(88102, "https://tracked.example.com/page?id=1&utm_source=mail").is_none());
+
+        clear_all_links(88102);
+        open_connection().execute("DELETE FROM link_deletions WHERE user_id = 88102").unwrap();
+    }
+
+    #[test]
+    fn test_get_recent_deletion() {
+        add_link(88101, "https://deleted-recently.example.com/");
+
+        assert!(get_recent_deletion(88101, "https://deleted-recently.example.com/", 24).is_none());
+
+        delete_some_links(88101, vec!["https://deleted-recently.example.com/"]);
+
+        assert!(get_recent_deletion(88101, "https://deleted-recently.example.com/", 24).is_some());
+        assert!(get_recent_deletion(88101, "https://deleted-recently.example.com/", 0).is_none());
+
+        open_connection().execute("DELETE FROM link_deletions WHERE user_id = 88101").unwrap();
+    }
+
     #[test]
     fn test_get_histories() {
         let vec = get_all_links_from_user(654352, None);
@@ -267,4 +956,21 @@ mod database_test {
 
         assert!(true)
     }
+
+    #[test]
+    fn test_deduplicate_global_stats() {
+        add_link(78121, "https://duplicate.example");
+        add_link(78121, "https://duplicate.example");
+        add_link(78122, "https://duplicate.example");
+
+        let candidates = get_deduplication_candidates(3);
+        assert!(candidates.iter().any(|(link, copies)| link == "https://duplicate.example" && *copies >= 3));
+
+        deduplicate_global_stats().unwrap();
+
+        assert_eq!(get_all_links_from_user(78121, Some("https://duplicate.example")).len(), 1);
+
+        clear_all_links(78121);
+        clear_all_links(78122);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlite3::State;
+use teloxide::dispatching::dialogue::Storage;
+use teloxide::types::ChatId;
+
+use crate::database;
+
+/// The future returned by the storage methods.
+type StorageFuture<T> = Pin<Box<dyn Future<Output = Result<T, serde_json::Error>> + Send>>;
+
+/// Stores the states of dialogues in the `dialogues` table, so multi-step commands
+/// (for example, the import wizard) survive restarts of the bot.
+///
+/// States are serialized to JSON.
+pub struct DialogueStorage<D> {
+    _state: PhantomData<fn() -> D>,
+}
+
+impl<D> DialogueStorage<D> {
+    /// Creates the storage.
+    pub fn new() -> Arc<DialogueStorage<D>> {
+        Arc::new(DialogueStorage { _state: PhantomData })
+    }
+}
+
+impl<D> Storage<D> for DialogueStorage<D>
+where
+    D: Serialize + DeserializeOwned + Send + 'static
+{
+    type Error = serde_json::Error;
+
+    fn remove_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<()> {
+        Box::pin(async move {
+            let connection = database::open_connection();
+            let mut db = connection.prepare("DELETE FROM dialogues WHERE chat_id = ?").unwrap();
+
+            db.bind(1, chat_id.0).unwrap();
+            db.next().unwrap();
+
+            Ok(())
+        })
+    }
+
+    fn update_dialogue(self: Arc<Self>, chat_id: ChatId, dialogue: D) -> StorageFuture<()> {
+        Box::pin(async move {
+            let state_json = serde_json::to_string(&dialogue)?;
+
+            let connection = database::open_connection();
+            let mut db = connection.prepare("INSERT OR REPLACE INTO dialogues (chat_id, state_json, updated_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+            db.bind(1, chat_id.0).unwrap();
+            db.bind(2, state_json.as_str()).unwrap();
+            db.next().unwrap();
+
+            Ok(())
+        })
+    }
+
+    fn get_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<Option<D>> {
+        Box::pin(async move {
+            let connection = database::open_connection();
+            let mut db = connection.prepare("SELECT state_json FROM dialogues WHERE chat_id = ?").unwrap();
+
+            db.bind(1, chat_id.0).unwrap();
+
+            match db.next().unwrap() {
+                State::Row => Ok(Some(serde_json::from_str(&db.read::<String>(0).unwrap())?)),
+                State::Done => Ok(None)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod dialogue_storage_tests {
+    use teloxide::dispatching::dialogue::Storage;
+    use teloxide::types::ChatId;
+
+    use crate::dialogue_storage::DialogueStorage;
+
+    #[tokio::test]
+    async fn test_dialogue_is_persisted() {
+        let storage = DialogueStorage::<Vec<String>>::new();
+
+        storage.clone().update_dialogue(ChatId(-1005), vec!["step".to_string()]).await.unwrap();
+        assert_eq!(storage.clone().get_dialogue(ChatId(-1005)).await.unwrap(), Some(vec!["step".to_string()]));
+
+        storage.clone().remove_dialogue(ChatId(-1005)).await.unwrap();
+        assert_eq!(storage.get_dialogue(ChatId(-1005)).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use http::Uri;
+use lazy_static::lazy_static;
+use publicsuffix::{List, Psl};
+
+lazy_static! {
+    /// The public suffix list from https://publicsuffix.org/list/public_suffix_list.dat.
+    /// Update the file from time to time, as new suffixes are added to the list.
+    static ref PUBLIC_SUFFIX_LIST: List = {
+        include_str!("../public_suffix_list.dat").parse().expect("The public suffix list is invalid")
+    };
+}
+
+/// Represents the reasons the domain family of a URL cannot be determined.
+#[derive(PartialEq, Debug)]
+pub enum DomainError {
+    /// The URL cannot be parsed or has no host.
+    InvalidUrl,
+    /// The host is a public suffix itself (for example, `co.uk`) or an IP address.
+    NoRegistrableDomain
+}
+
+impl fmt::Display for DomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainError::InvalidUrl => write!(f, "the URL has no host"),
+            DomainError::NoRegistrableDomain => write!(f, "the host has no registrable domain")
+        }
+    }
+}
+
+impl std::error::Error for DomainError {}
+
+/// Returns the registrable domain (eTLD+1) of the URL, which links of the same site share.
+///
+/// # Arguments
+///
+/// * `url` - The URL.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the registrable domain, for example `example.co.uk` for `https://sub.example.co.uk/`.
+/// * An `Err` variant containing a `DomainError` if the URL has no registrable domain.
+pub fn get_domain_family(url: &str) -> Result<String, DomainError> {
+    let uri = url.parse::<Uri>().map_err(|_| DomainError::InvalidUrl)?;
+    let host = uri.host().ok_or(DomainError::InvalidUrl)?.to_lowercase();
+
+    let domain = PUBLIC_SUFFIX_LIST.domain(host.as_bytes()).ok_or(DomainError::NoRegistrableDomain)?;
+
+    // IP addresses are not domains, but the list treats their last number as an unknown suffix
+    if !domain.suffix().is_known() {
+        return Err(DomainError::NoRegistrableDomain);
+    }
+
+    Ok(String::from_utf8_lossy(domain.as_bytes()).to_string())
+}
+
+#[cfg(test)]
+mod domain_family_tests {
+    use crate::domain_family::*;
+
+    #[test]
+    fn test_get_domain_family() {
+        assert_eq!(get_domain_family("https://sub.example.co.uk/page"), Ok("example.co.uk".to_string()));
+        assert_eq!(get_domain_family("https://WWW.Example.com"), Ok("example.com".to_string()));
+        assert_eq!(get_domain_family("https://co.uk/"), Err(DomainError::NoRegistrableDomain));
+        assert_eq!(get_domain_family("https://192.168.0.1/"), Err(DomainError::NoRegistrableDomain));
+        assert_eq!(get_domain_family("not a url"), Err(DomainError::InvalidUrl));
+    }
+}
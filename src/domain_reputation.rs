@@ -0,0 +1,265 @@
+use chrono::NaiveDate;
+use http::Uri;
+use sqlite3::{Connection, State};
+
+use crate::{database, reverse_dns};
+use crate::csp::CspAnalysis;
+
+/// How long a computed reputation is considered fresh.
+const DOMAIN_REPUTATION_CACHE_TTL_HOURS: u32 = 6;
+/// How many days the history of the reputation is kept for.
+pub const DOMAIN_REPUTATION_HISTORY_DAYS: u32 = 90;
+
+/// Weight of the share of the domain's IP addresses whose PTR record does not match the domain.
+const PTR_MISMATCH_WEIGHT: f32 = -0.3;
+/// Weight of the warnings attached to the domain's links.
+const LINK_FLAGS_WEIGHT: f32 = -0.4;
+/// Weight of the share of failed health checks of the domain's links in the last week.
+const FAILED_HEALTH_CHECKS_WEIGHT: f32 = -0.2;
+/// Weight of a weak Content-Security-Policy on the domain's pages.
+const WEAK_CSP_WEIGHT: f32 = -0.1;
+
+/// The bars of a sparkline from the lowest to the highest score.
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Represents how trustworthy a domain is.
+pub struct DomainReputation {
+    /// The score from 0 (untrustworthy) to 1 (no negative signals).
+    pub score: f32,
+    /// The names of the signals that affected the score together with their contributions.
+    pub contributing_signals: Vec<(String, f32)>,
+}
+
+/// Returns the reputation of the domain.
+///
+/// The reputation is taken from the `domain_reputation_cache` table if it was computed less than
+/// `DOMAIN_REPUTATION_CACHE_TTL_HOURS` hours ago, otherwise it is computed again and cached.
+/// The previous scores are kept in the cache, so the history of the reputation can be shown.
+///
+/// # Arguments
+///
+/// * `domain` - The domain.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_domain_reputation(domain: &str) -> DomainReputation {
+    if let Some(reputation) = get_cached_reputation(domain) {
+        return reputation;
+    }
+
+    let reputation = compute_domain_reputation(domain);
+    cache_reputation(domain, &reputation);
+
+    reputation
+}
+
+/// Computes the reputation of the domain from the signals collected by the bot.
+///
+/// Every signal is weighted and added to the perfect score of 1, the result is clamped to the range from 0 to 1.
+/// Signals without data are not taken into account.
+///
+/// # Arguments
+///
+/// * `domain` - The domain.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn compute_domain_reputation(domain: &str) -> DomainReputation {
+    let connection = database::open_connection();
+    let mut contributing_signals: Vec<(String, f32)> = Vec::new();
+
+    let ptr_records = read_domain_rows(&connection, "SELECT ptr_record FROM link_ips WHERE domain = ? AND ptr_record IS NOT NULL", domain);
+
+    if !ptr_records.is_empty() {
+        let mismatches = ptr_records.iter().filter(|ptr_record| reverse_dns::is_ptr_suspicious(domain, ptr_record)).count();
+        push_signal(&mut contributing_signals, "ptr_mismatch", PTR_MISMATCH_WEIGHT * mismatches as f32 / ptr_records.len() as f32);
+    }
+
+    if !read_domain_rows(&connection, "SELECT flag FROM link_flags WHERE domain = ? LIMIT 1", domain).is_empty() {
+        push_signal(&mut contributing_signals, "link_flags", LINK_FLAGS_WEIGHT);
+    }
+
+    // Only the numbers of checks are needed, so they are counted by the database
+    let mut db = connection.prepare("SELECT COUNT(*), COALESCE(SUM(error IS NOT NULL OR status_code >= 400), 0) FROM link_health_checks \
+        WHERE domain = ? AND checked_at > datetime('now', '-7 days')").unwrap();
+    db.bind(1, domain).unwrap();
+    db.next().unwrap();
+
+    let (checks, failures) = (db.read::<i64>(0).unwrap(), db.read::<i64>(1).unwrap());
+
+    if checks > 0 {
+        push_signal(&mut contributing_signals, "failed_health_checks", FAILED_HEALTH_CHECKS_WEIGHT * failures as f32 / checks as f32);
+    }
+
+    let has_weak_csp = read_domain_rows(&connection, "SELECT csp_analysis_json FROM links WHERE domain = ? AND csp_analysis_json IS NOT NULL", domain).iter()
+        .filter_map(|analysis| serde_json::from_str::<CspAnalysis>(analysis).ok())
+        .any(|analysis| analysis.has_weaknesses());
+
+    if has_weak_csp {
+        push_signal(&mut contributing_signals, "weak_csp", WEAK_CSP_WEIGHT);
+    }
+
+    let score = (1.0 + contributing_signals.iter().map(|(_, contribution)| contribution).sum::<f32>()).clamp(0.0, 1.0);
+
+    DomainReputation { score, contributing_signals }
+}
+
+/// Adds the signal to the list if it affects the score.
+fn push_signal(signals: &mut Vec<(String, f32)>, name: &str, contribution: f32) {
+    if contribution != 0.0 {
+        signals.push((name.to_string(), contribution));
+    }
+}
+
+/// Runs a query filtering the rows by the domain of their links, and returns the values of the first column.
+///
+/// The domain is stored alongside the links when they are saved, so the rows are found by its index.
+fn read_domain_rows(connection: &Connection, query: &str, domain: &str) -> Vec<String> {
+    let mut db = connection.prepare(query).unwrap();
+    db.bind(1, domain).unwrap();
+
+    let mut vec: Vec<String> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<String>(0).unwrap());
+    }
+
+    vec
+}
+
+/// Returns the average reputation score of the domain for each day it was computed in the last days, the oldest first.
+///
+/// # Arguments
+///
+/// * `domain` - The domain.
+/// * `days` - The number of days to return the history for.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_domain_reputation_history(domain: &str, days: u32) -> Vec<(NaiveDate, f32)> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT date(computed_at), AVG(score) FROM domain_reputation_cache \
+        WHERE domain = ? AND computed_at > datetime('now', ?) GROUP BY date(computed_at) ORDER BY date(computed_at)").unwrap();
+
+    db.bind(1, domain).unwrap();
+    db.bind(2, format!("-{} days", days).as_str()).unwrap();
+
+    let mut vec: Vec<(NaiveDate, f32)> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        if let Ok(date) = NaiveDate::parse_from_str(&db.read::<String>(0).unwrap(), "%Y-%m-%d") {
+            vec.push((date, db.read::<f64>(1).unwrap() as f32));
+        }
+    }
+
+    vec
+}
+
+/// Renders the scores as a textual sparkline, one bar per score.
+///
+/// # Arguments
+///
+/// * `scores` - The scores from 0 to 1.
+pub fn render_sparkline(scores: &[f32]) -> String {
+    scores.iter()
+        .map(|score| SPARKLINE_BARS[(score.clamp(0.0, 1.0) * (SPARKLINE_BARS.len() - 1) as f32).round() as usize])
+        .collect()
+}
+
+/// Returns the domain of the link without the `www.` prefix.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+pub fn get_domain(link: &str) -> Option<String> {
+    let uri = link.parse::<Uri>().ok()?;
+
+    uri.host().map(|host| host.trim_start_matches("www.").to_lowercase())
+}
+
+/// Returns the cached reputation of the domain if it was computed recently.
+fn get_cached_reputation(domain: &str) -> Option<DomainReputation> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT score, signals_json FROM domain_reputation_cache WHERE domain = ? AND computed_at > datetime('now', ?) \
+        ORDER BY computed_at DESC, rowid DESC LIMIT 1").unwrap();
+
+    db.bind(1, domain).unwrap();
+    db.bind(2, format!("-{} hours", DOMAIN_REPUTATION_CACHE_TTL_HOURS).as_str()).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => Some(DomainReputation {
+            score: db.read::<f64>(0).unwrap() as f32,
+            contributing_signals: serde_json::from_str(&db.read::<String>(1).unwrap()).unwrap_or_default(),
+        }),
+        State::Done => None
+    }
+}
+
+/// Saves the reputation of the domain to the cache. The previous entries are kept as the history of the domain
+/// for `DOMAIN_REPUTATION_HISTORY_DAYS` days, older entries of all domains are deleted.
+fn cache_reputation(domain: &str, reputation: &DomainReputation) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO domain_reputation_cache (domain, score, signals_json, computed_at) VALUES (?, ?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, domain).unwrap();
+    db.bind(2, reputation.score as f64).unwrap();
+    db.bind(3, serde_json::to_string(&reputation.contributing_signals).unwrap().as_str()).unwrap();
+
+    db.next().unwrap();
+
+    let mut db = connection.prepare("DELETE FROM domain_reputation_cache WHERE computed_at < datetime('now', ?)").unwrap();
+    db.bind(1, format!("-{} days", DOMAIN_REPUTATION_HISTORY_DAYS).as_str()).unwrap();
+
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod domain_reputation_tests {
+    use crate::domain_reputation::*;
+    use crate::{database, link_flags};
+    use crate::reverse_dns::IpRecord;
+
+    #[test]
+    fn test_compute_domain_reputation() {
+        database::open_connection().execute("DELETE FROM link_flags WHERE link = 'https://www.reputation-test.example/login'; \
+            DELETE FROM link_ips WHERE link = 'https://reputation-test.example/'").unwrap();
+
+        assert_eq!(compute_domain_reputation("reputation-test.example").score, 1.0);
+
+        link_flags::flag_link("https://www.reputation-test.example/login", "custom_rule", "Phishing");
+        reverse_dns::store_ip_records("https://reputation-test.example/", &[
+            IpRecord { ip: "192.0.2.1".parse().unwrap(), ptr_record: Some("mail.reputation-test.example".to_string()) },
+            IpRecord { ip: "192.0.2.2".parse().unwrap(), ptr_record: Some("vps-42.cheap-hosting.example".to_string()) },
+        ]);
+
+        let reputation = compute_domain_reputation("reputation-test.example");
+
+        assert!((reputation.score - 0.45).abs() < 0.001);
+        assert_eq!(reputation.contributing_signals.len(), 2);
+    }
+
+    #[test]
+    fn test_get_domain_reputation_history() {
+        database::open_connection().execute("INSERT INTO domain_reputation_cache (domain, score, signals_json, computed_at) \
+            VALUES ('trend-test.example', 0.0, '[]', '2000-01-01 00:00:00')").unwrap();
+
+        cache_reputation("trend-test.example", &DomainReputation { score: 1.0, contributing_signals: Vec::new() });
+        cache_reputation("trend-test.example", &DomainReputation { score: 0.5, contributing_signals: Vec::new() });
+
+        let history = get_domain_reputation_history("trend-test.example", 90);
+
+        // The entry from 2000 is deleted when the new ones are saved
+        assert_eq!(history.len(), 1);
+        assert_eq!(get_domain_reputation_history("trend-test.example", 100 * 365).len(), 1);
+        assert!((history[0].1 - 0.75).abs() < 0.001);
+        assert_eq!(get_cached_reputation("trend-test.example").unwrap().score, 0.5);
+
+        assert_eq!(render_sparkline(&[0.0, 0.5, 1.0]), "▁▅█");
+    }
+}
@@ -0,0 +1,185 @@
+use std::env;
+use std::error::Error;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use dptree::Cont;
+use dptree::di::{DependencyMap, DependencySupplier};
+use teloxide::dispatching::UpdateHandler;
+use teloxide::prelude::*;
+use teloxide::types::UpdateKind;
+
+use crate::HandlerResult;
+use crate::message_text;
+
+/// The maximum number of characters of the error description and of each of its causes in a report.
+const MAX_ERROR_LENGTH: usize = 1000;
+/// The maximum number of causes of the error listed in a report.
+const MAX_ERROR_CAUSES: usize = 5;
+
+/// Sends reports about errors in the handlers to the administrator, who would not see them in the logs otherwise.
+pub struct ErrorReporter {
+    pub admin_chat_id: i64,
+    pub bot: Bot,
+}
+
+impl ErrorReporter {
+    /// Returns the reporter that sends reports to the user specified in the `ADMIN_ID` environment variable,
+    /// or `None` if it is not set.
+    pub fn from_env(bot: Bot) -> Option<ErrorReporter> {
+        let admin_chat_id = env::var("ADMIN_ID").ok()?.parse::<i64>().ok()?;
+
+        Some(ErrorReporter { admin_chat_id, bot })
+    }
+
+    /// Sends the administrator a report about the error that occurred while handling the update.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error returned by the handler.
+    /// * `update` - The update the handler was called for.
+    pub async fn report(&self, error: &(dyn Error + Send + Sync), update: &Update) {
+        let user_id = update.user().map(|user| user.id.0);
+        let text = format_error_report(error, user_id, describe_update(update).as_deref(), Utc::now());
+
+        if let Err(err) = self.bot.send_message(ChatId(self.admin_chat_id), text).await {
+            warn!("Failed to send the error report to the administrator. Description: {}", err);
+        }
+    }
+}
+
+/// Creates a handler that calls the rest of the handlers and reports the errors they return to the administrator.
+///
+/// The error is passed on unchanged, so it is still logged by the dispatcher.
+pub fn report_handler_errors() -> UpdateHandler<Box<dyn Error + Send + Sync>> {
+    dptree::from_fn(|deps: DependencyMap, cont: Cont<'static, DependencyMap, HandlerResult>| async move {
+        let bot: Arc<Bot> = deps.get();
+        let update: Arc<Update> = deps.get();
+
+        let result = cont(deps).await;
+
+        if let ControlFlow::Break(Err(err)) = &result {
+            if let Some(reporter) = ErrorReporter::from_env(bot.as_ref().clone()) {
+                reporter.report(err.as_ref(), &update).await;
+            }
+        }
+
+        result
+    })
+}
+
+/// Returns the command or the button that caused the update, or `None` for other updates.
+fn describe_update(update: &Update) -> Option<String> {
+    match &update.kind {
+        UpdateKind::Message(msg) => match msg.text() {
+            Some(text) if text.starts_with('/') => text.split_whitespace().next().map(|command| command.to_string()),
+            Some(_) => Some("сообщение".to_string()),
+            None => None
+        },
+        UpdateKind::CallbackQuery(q) => q.data.as_ref().map(|data| format!("кнопка {data}")),
+        _ => None
+    }
+}
+
+/// Formats the report about an error for the administrator.
+///
+/// The causes of the error are listed after it, as the error type has no stack trace.
+/// Long descriptions are truncated, and so is the whole report, so it fits into a Telegram message.
+///
+/// # Arguments
+///
+/// * `error` - The error.
+/// * `user_id` - The ID of the user whose update caused the error.
+/// * `command` - The command or the button that caused the error.
+/// * `timestamp` - The time the error occurred.
+pub fn format_error_report(error: &dyn Error, user_id: Option<u64>, command: Option<&str>, timestamp: DateTime<Utc>) -> String {
+    let mut text = "❗ Ошибка в обработчике\n\n".to_string();
+
+    text = format!("{text}🕔 Время: {}\n", timestamp.format("%d.%m.%Y %H:%M:%S UTC"));
+    text = format!("{text}👤 Пользователь: {}\n", user_id.map(|user_id| user_id.to_string()).unwrap_or("неизвестен".to_string()));
+    text = format!("{text}⌨️ Команда: {}\n", command.unwrap_or("неизвестна"));
+    text = format!("{text}\n{}\n", truncate(&error.to_string()));
+
+    let mut source = error.source();
+    let mut causes = 0;
+
+    while let Some(cause) = source {
+        if causes == MAX_ERROR_CAUSES {
+            text = format!("{text}…\n");
+            break;
+        }
+
+        text = format!("{text}↳ {}\n", truncate(&cause.to_string()));
+
+        source = cause.source();
+        causes += 1;
+    }
+
+    message_text::truncate_message(&text)
+}
+
+/// Truncates the text to `MAX_ERROR_LENGTH` characters.
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_ERROR_LENGTH {
+        return text.to_string();
+    }
+
+    format!("{}…", text.chars().take(MAX_ERROR_LENGTH).collect::<String>())
+}
+
+#[cfg(test)]
+mod error_reporter_tests {
+    use std::fmt;
+    use chrono::TimeZone;
+    use crate::error_reporter::*;
+
+    #[derive(Debug)]
+    struct TestError {
+        description: String,
+        source: Option<Box<TestError>>,
+    }
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.description)
+        }
+    }
+
+    impl Error for TestError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.source.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static))
+        }
+    }
+
+    #[test]
+    fn test_format_error_report() {
+        let error = TestError {
+            description: "Failed to save the link".to_string(),
+            source: Some(Box::new(TestError { description: "x".repeat(MAX_ERROR_LENGTH + 10), source: None })),
+        };
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+
+        let report = format_error_report(&error, Some(42), Some("/addlink"), timestamp);
+
+        assert!(report.contains("02.01.2024 03:04:05 UTC"));
+        assert!(report.contains("Пользователь: 42"));
+        assert!(report.contains("Команда: /addlink"));
+        assert!(report.contains("Failed to save the link"));
+        assert!(report.contains(&format!("↳ {}…", "x".repeat(MAX_ERROR_LENGTH))));
+        assert!(!report.contains(&"x".repeat(MAX_ERROR_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_long_error_report_fits_into_message() {
+        let mut error = TestError { description: "x".repeat(MAX_ERROR_LENGTH), source: None };
+
+        for _ in 0..MAX_ERROR_CAUSES {
+            error = TestError { description: "x".repeat(MAX_ERROR_LENGTH), source: Some(Box::new(error)) };
+        }
+
+        let report = format_error_report(&error, Some(42), Some("/addlink"), Utc::now());
+
+        assert!(report.encode_utf16().count() <= message_text::MAX_MESSAGE_LENGTH);
+        assert!(report.ends_with('…'));
+    }
+}
@@ -0,0 +1,102 @@
+use lazy_static::lazy_static;
+use tokio::sync::broadcast;
+
+/// How many events can wait in the channel before slow subscribers start missing them.
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// Represents an event that happened in the bot.
+#[derive(Clone, Debug)]
+pub enum BotEvent {
+    LinkAdded {
+        user_id: u64,
+        link: String
+    },
+    LinksDeleted {
+        user_id: u64,
+        links: Vec<String>
+    },
+    LinksCleared {
+        user_id: u64
+    },
+    ScanCompleted {
+        user_id: u64,
+        link: String,
+        status_code: Option<u16>
+    }
+}
+
+/// Delivers bot events to every subscriber.
+///
+/// The database layer and the handlers only emit events, while side effects
+/// (notifications, statistics, alerts) subscribe to the events they care about.
+pub struct EventBus {
+    sender: broadcast::Sender<BotEvent>,
+}
+
+impl EventBus {
+    /// Creates an event bus that can hold `capacity` undelivered events.
+    pub fn new(capacity: usize) -> EventBus {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus { sender }
+    }
+
+    /// Sends the event to all current subscribers. The event is dropped if nobody is subscribed.
+    pub fn emit(&self, event: BotEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver for all events emitted after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<BotEvent> {
+        self.sender.subscribe()
+    }
+}
+
+lazy_static! {
+    pub static ref EVENT_BUS: EventBus = EventBus::new(EVENT_BUS_CAPACITY);
+}
+
+/// Launches a subscriber that writes every event to the log.
+pub fn launch_event_logger() {
+    let mut receiver = EVENT_BUS.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(BotEvent::LinkAdded { user_id, link }) => {
+                    info!("Event: the user {} added the link {}", user_id, link);
+                }
+                Ok(BotEvent::LinksDeleted { user_id, links }) => {
+                    info!("Event: the user {} deleted {} links", user_id, links.len());
+                }
+                Ok(BotEvent::LinksCleared { user_id }) => {
+                    info!("Event: the user {} cleared all links", user_id);
+                }
+                Ok(BotEvent::ScanCompleted { user_id, link, status_code }) => {
+                    info!("Event: the site {} was analyzed for the user {} (status code: {:?})", link, user_id, status_code);
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("The event logger missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod event_bus_tests {
+    use crate::event_bus::{BotEvent, EventBus};
+
+    #[test]
+    fn test_subscribers_receive_events() {
+        let event_bus = EventBus::new(4);
+        let mut receiver = event_bus.subscribe();
+
+        event_bus.emit(BotEvent::LinksCleared { user_id: 42 });
+
+        match receiver.try_recv() {
+            Ok(BotEvent::LinksCleared { user_id }) => assert_eq!(user_id, 42),
+            _ => panic!("The event was not received")
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use teloxide::Bot;
+use teloxide::net::Download;
+use teloxide::prelude::Requester;
+use teloxide::RequestError;
+
+/// Represents the reasons a file sent by a user was not downloaded.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The file is larger than allowed.
+    TooLarge {
+        size: u32,
+        max_size: u32
+    },
+    /// The type of the file is not allowed, or Telegram does not know it.
+    UnsupportedType(Option<String>),
+    /// Telegram did not return the information about the file.
+    Request(RequestError),
+    /// The file could not be downloaded from Telegram.
+    Download(teloxide::DownloadError)
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::TooLarge { size, max_size } => write!(f, "the file is {size} bytes, while at most {max_size} bytes are allowed"),
+            DownloadError::UnsupportedType(Some(mime_type)) => write!(f, "files of the type {mime_type} are not allowed"),
+            DownloadError::UnsupportedType(None) => write!(f, "the type of the file is unknown"),
+            DownloadError::Request(err) => write!(f, "failed to get the file from Telegram: {err}"),
+            DownloadError::Download(err) => write!(f, "failed to download the file from Telegram: {err}")
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+/// Downloads files sent by users, such as the files with links to import.
+///
+/// The size and the type of a file are checked before it is downloaded.
+pub struct TelegramFileDownloader {
+    pub max_size_bytes: u32,
+    /// The MIME types of the files that may be downloaded, for example `text/plain`.
+    pub allowed_mime_types: Vec<String>,
+}
+
+impl TelegramFileDownloader {
+    /// Downloads the file sent by a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `bot` - Bot instance.
+    /// * `file_id` - The ID of the file in Telegram.
+    /// * `mime_type` - The MIME type of the file reported by Telegram, if any.
+    ///
+    /// # Returns
+    ///
+    /// * An `Ok` variant containing the content of the file.
+    /// * An `Err` variant containing a `DownloadError` if the file is not allowed or could not be downloaded.
+    pub async fn download(&self, bot: &Bot, file_id: &str, mime_type: Option<&str>) -> Result<Vec<u8>, DownloadError> {
+        self.check_mime_type(mime_type)?;
+
+        let file = bot.get_file(file_id).await.map_err(DownloadError::Request)?;
+
+        // Only the information about the file is requested, so a large file is rejected before it is downloaded
+        self.check_size(file.size)?;
+
+        let mut content: Vec<u8> = Vec::new();
+        bot.download_file(&file.path, &mut content).await.map_err(DownloadError::Download)?;
+
+        Ok(content)
+    }
+
+    /// Checks whether a file of this size may be downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of the file in bytes.
+    fn check_size(&self, size: u32) -> Result<(), DownloadError> {
+        if size > self.max_size_bytes {
+            return Err(DownloadError::TooLarge { size, max_size: self.max_size_bytes });
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether a file of this type may be downloaded. Parameters of the type, such as the charset, are ignored.
+    fn check_mime_type(&self, mime_type: Option<&str>) -> Result<(), DownloadError> {
+        let essence = mime_type.map(|mime_type| mime_type.split(';').next().unwrap_or_default().trim().to_lowercase());
+
+        match &essence {
+            Some(essence) if self.allowed_mime_types.iter().any(|allowed| allowed == essence) => Ok(()),
+            _ => Err(DownloadError::UnsupportedType(essence))
+        }
+    }
+}
+
+#[cfg(test)]
+mod file_downloader_tests {
+    use crate::file_downloader::*;
+
+    #[test]
+    fn test_file_checks() {
+        let downloader = TelegramFileDownloader {
+            max_size_bytes: 1024,
+            allowed_mime_types: vec!["text/plain".to_string(), "text/csv".to_string()],
+        };
+
+        assert!(downloader.check_mime_type(Some("text/csv")).is_ok());
+        assert!(downloader.check_mime_type(Some("TEXT/PLAIN; charset=utf-8")).is_ok());
+        assert!(matches!(downloader.check_mime_type(Some("application/x-msdownload")), Err(DownloadError::UnsupportedType(Some(_)))));
+        assert!(matches!(downloader.check_mime_type(None), Err(DownloadError::UnsupportedType(None))));
+
+        assert!(downloader.check_size(1024).is_ok());
+        assert!(matches!(downloader.check_size(1025), Err(DownloadError::TooLarge { size: 1025, max_size: 1024 })));
+    }
+}
@@ -0,0 +1,100 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+lazy_static! {
+    static ref RE_SCRIPTS_AND_STYLES: Regex = {
+        Regex::new(r"(?is)<script.*?</script>|<style.*?</style>").unwrap()
+    };
+    static ref RE_TAGS: Regex = {
+        Regex::new(r"(?s)<[^>]*>").unwrap()
+    };
+    static ref RE_WORDS: Regex = {
+        Regex::new(r"\w{2,}").unwrap()
+    };
+}
+
+/// Extracts the words of the visible text from an HTML page.
+///
+/// # Arguments
+///
+/// * `html` - The HTML code of the page.
+///
+/// # Returns
+///
+/// The lowercase words of the page in the order they appear.
+pub fn extract_text_tokens(html: &str) -> Vec<String> {
+    let without_scripts = RE_SCRIPTS_AND_STYLES.replace_all(html, " ");
+    let text = RE_TAGS.replace_all(&without_scripts, " ");
+
+    RE_WORDS.find_iter(&text).map(|word| word.as_str().to_lowercase()).collect()
+}
+
+/// Computes the 64-bit SimHash of the tokens.
+///
+/// Similar sets of tokens produce hashes that differ only in a few bits,
+/// so the similarity of pages can be measured by the Hamming distance between their hashes.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens of the document.
+pub fn compute_simhash(tokens: &[String]) -> u64 {
+    let mut weights = [0i32; 64];
+
+    for token in tokens {
+        let hash = fnv1a(token.as_bytes());
+
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            }
+            else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights.iter().enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0u64, |simhash, (bit, _)| simhash | (1 << bit))
+}
+
+/// Returns the number of bits that differ between two hashes.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Computes the 64-bit FNV-1a hash of the bytes.
+///
+/// Unlike the standard library hasher, the result is guaranteed to be the same in every build,
+/// which is required for hashes stored in the database.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[cfg(test)]
+mod fingerprint_tests {
+    use crate::fingerprint::*;
+
+    #[test]
+    fn test_extract_text_tokens() {
+        let tokens = extract_text_tokens("<html><style>body { color: red }</style><body><h1>Hello, World!</h1><script>alert(1)</script></body></html>");
+
+        assert_eq!(tokens, vec!["hello", "world"]);
+    }
+
+    #[test]
+    fn test_similar_pages_have_close_hashes() {
+        let page_a = extract_text_tokens("<p>Sign in to your bank account to confirm the payment of your last order</p>");
+        let page_b = extract_text_tokens("<p>Sign in to your bank account to confirm the payment of your recent order</p>");
+        let page_c = extract_text_tokens("<p>Rust is a language empowering everyone to build reliable and efficient software</p>");
+
+        let hash_a = compute_simhash(&page_a);
+        let hash_b = compute_simhash(&page_b);
+        let hash_c = compute_simhash(&page_c);
+
+        assert!(hamming_distance(hash_a, hash_b) < hamming_distance(hash_a, hash_c));
+    }
+}
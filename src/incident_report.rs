@@ -0,0 +1,137 @@
+use chrono::Utc;
+use serde::Serialize;
+use sqlite3::State;
+
+use crate::{database, iocs, link_flags};
+use crate::iocs::IocRecord;
+use crate::link_flags::LinkFlag;
+
+/// Represents everything the bot knows about a malicious link, collected across all users.
+#[derive(Serialize)]
+pub struct IncidentReport {
+    pub link: String,
+    /// The time the report was generated, in RFC 3339 format.
+    pub generated_at: String,
+    /// The IDs of the users who added the link.
+    pub submitters: Vec<u64>,
+    /// The results of the hourly checks of the link, the oldest first.
+    pub health_checks: Vec<HealthCheckEntry>,
+    /// The IP addresses of the link's domain.
+    pub ips: Vec<IpEntry>,
+    pub iocs: Vec<IocRecord>,
+    /// The URLs from the short link to the link, if the link was added as a short link.
+    pub redirect_chain: Vec<String>,
+    pub flags: Vec<LinkFlag>,
+}
+
+/// Represents a stored result of checking a link.
+#[derive(Serialize)]
+pub struct HealthCheckEntry {
+    pub checked_at: String,
+    pub status_code: Option<u16>,
+    pub redirect_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Represents a stored IP address of a link.
+#[derive(Serialize)]
+pub struct IpEntry {
+    pub ip: String,
+    pub ptr_record: Option<String>,
+    pub resolved_at: String,
+}
+
+/// Collects all stored data about the link into a report for filing an incident.
+///
+/// WHOIS data, screenshots and third-party scan results are not collected by the bot, so they are not included.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn generate_incident_report(link: &str) -> IncidentReport {
+    let connection = database::open_connection();
+
+    let mut db = connection.prepare("SELECT DISTINCT user_id FROM links WHERE link = ? ORDER BY user_id").unwrap();
+    db.bind(1, link).unwrap();
+
+    let mut submitters: Vec<u64> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        submitters.push(db.read::<i64>(0).unwrap() as u64);
+    }
+
+    let mut db = connection.prepare("SELECT checked_at, status_code, redirect_url, error FROM link_health_checks WHERE link = ? ORDER BY id").unwrap();
+    db.bind(1, link).unwrap();
+
+    let mut health_checks: Vec<HealthCheckEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        health_checks.push(HealthCheckEntry {
+            checked_at: db.read::<String>(0).unwrap(),
+            status_code: db.read::<String>(1).ok().and_then(|status_code| status_code.parse().ok()),
+            redirect_url: db.read::<String>(2).ok(),
+            error: db.read::<String>(3).ok(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT ip, ptr_record, resolved_at FROM link_ips WHERE link = ? ORDER BY ip").unwrap();
+    db.bind(1, link).unwrap();
+
+    let mut ips: Vec<IpEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        ips.push(IpEntry {
+            ip: db.read::<String>(0).unwrap(),
+            ptr_record: db.read::<String>(1).ok(),
+            resolved_at: db.read::<String>(2).unwrap(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT redirect_chain_json FROM links WHERE link = ? AND redirect_chain_json IS NOT NULL LIMIT 1").unwrap();
+    db.bind(1, link).unwrap();
+
+    let redirect_chain: Vec<String> = match db.next().unwrap() {
+        State::Row => serde_json::from_str(&db.read::<String>(0).unwrap()).unwrap_or_default(),
+        State::Done => Vec::new()
+    };
+
+    IncidentReport {
+        link: link.to_string(),
+        generated_at: Utc::now().to_rfc3339(),
+        submitters,
+        health_checks,
+        ips,
+        iocs: iocs::get_iocs(link),
+        redirect_chain,
+        flags: link_flags::get_link_flags(link),
+    }
+}
+
+#[cfg(test)]
+mod incident_report_tests {
+    use crate::{database, link_flags};
+    use crate::incident_report::generate_incident_report;
+
+    #[test]
+    fn test_generate_incident_report() {
+        database::add_link(91002, "https://incident.example.com/");
+        database::add_link(91001, "https://incident.example.com/");
+        link_flags::flag_link("https://incident.example.com/", "custom_rule", "Phishing");
+
+        let report = generate_incident_report("https://incident.example.com/");
+
+        assert_eq!(report.submitters, vec![91001, 91002]);
+        assert_eq!(report.flags.len(), 1);
+        assert!(report.redirect_chain.is_empty());
+
+        database::clear_all_links(91001);
+        database::clear_all_links(91002);
+        database::open_connection().execute("DELETE FROM link_deletions WHERE user_id IN (91001, 91002); \
+            DELETE FROM link_flags WHERE link = 'https://incident.example.com/'").unwrap();
+    }
+}
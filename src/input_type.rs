@@ -0,0 +1,95 @@
+use std::net::IpAddr;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::website;
+
+lazy_static! {
+    static ref RE_MD5: Regex = {
+        Regex::new(r"^[0-9a-fA-F]{32}$").unwrap()
+    };
+    static ref RE_SHA256: Regex = {
+        Regex::new(r"^[0-9a-fA-F]{64}$").unwrap()
+    };
+    static ref RE_EMAIL: Regex = {
+        Regex::new(r"^[^@\s/:]+@[^@\s/:]+\.\p{L}{2,}$").unwrap()
+    };
+    static ref RE_DOMAIN: Regex = {
+        Regex::new(r"^(?:[\p{L}\d](?:[\p{L}\d-]*[\p{L}\d])?\.)+\p{L}{2,}\.?$").unwrap()
+    };
+    static ref RE_DOMAIN_WITH_PATH: Regex = {
+        Regex::new(r"^(?:[\p{L}\d](?:[\p{L}\d-]*[\p{L}\d])?\.)+\p{L}{2,}(?::\d+)?[/?#]\S*$").unwrap()
+    };
+}
+
+/// Represents the kinds of indicators users send to the bot.
+#[derive(PartialEq, Debug)]
+pub enum InputType {
+    /// A URL with or without the scheme, for example `https://example.com/login` or `example.com/login`.
+    Url(String),
+    IpAddress(IpAddr),
+    /// A domain without a path, for example `example.com`.
+    DomainOnly(String),
+    Email(String),
+    FileHashMd5(String),
+    FileHashSha256(String),
+    Unknown
+}
+
+/// Determines what kind of indicator the user has sent.
+///
+/// # Arguments
+///
+/// * `input` - The text sent by the user.
+pub fn detect_input_type(input: &str) -> InputType {
+    let input = input.trim();
+
+    if website::has_http_or_https(input) {
+        return InputType::Url(input.to_string());
+    }
+
+    if let Ok(ip) = input.parse::<IpAddr>() {
+        return InputType::IpAddress(ip);
+    }
+
+    // Hashes are checked before domains, as a hash is never a valid domain, but is easy to take for a word
+    if RE_MD5.is_match(input) {
+        return InputType::FileHashMd5(input.to_lowercase());
+    }
+    if RE_SHA256.is_match(input) {
+        return InputType::FileHashSha256(input.to_lowercase());
+    }
+    if RE_EMAIL.is_match(input) {
+        return InputType::Email(input.to_string());
+    }
+    if RE_DOMAIN.is_match(input) {
+        return InputType::DomainOnly(input.trim_end_matches('.').to_lowercase());
+    }
+    if RE_DOMAIN_WITH_PATH.is_match(input) {
+        return InputType::Url(input.to_string());
+    }
+
+    InputType::Unknown
+}
+
+#[cfg(test)]
+mod input_type_tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use crate::input_type::*;
+
+    #[test]
+    fn test_detect_input_type() {
+        assert_eq!(detect_input_type("https://example.com/login"), InputType::Url("https://example.com/login".to_string()));
+        assert_eq!(detect_input_type("example.com/login"), InputType::Url("example.com/login".to_string()));
+        assert_eq!(detect_input_type(" Example.COM "), InputType::DomainOnly("example.com".to_string()));
+        assert_eq!(detect_input_type("пример.рф"), InputType::DomainOnly("пример.рф".to_string()));
+        assert_eq!(detect_input_type("192.168.0.1"), InputType::IpAddress(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))));
+        assert_eq!(detect_input_type("user@example.com"), InputType::Email("user@example.com".to_string()));
+        assert_eq!(detect_input_type("D41D8CD98F00B204E9800998ECF8427E"), InputType::FileHashMd5("d41d8cd98f00b204e9800998ecf8427e".to_string()));
+        assert_eq!(
+            detect_input_type("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            InputType::FileHashSha256("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string())
+        );
+        assert_eq!(detect_input_type("hello world"), InputType::Unknown);
+    }
+}
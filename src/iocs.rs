@@ -0,0 +1,165 @@
+use std::net::IpAddr;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use sqlite3::State;
+
+use crate::database;
+
+lazy_static! {
+    static ref RE_IPV4: Regex = {
+        Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap()
+    };
+    static ref RE_EMAIL: Regex = {
+        Regex::new(r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.[a-zA-Z]{2,}\b").unwrap()
+    };
+    static ref RE_HASH: Regex = {
+        // MD5, SHA-1 and SHA-256 hashes
+        Regex::new(r"\b(?:[a-fA-F0-9]{64}|[a-fA-F0-9]{40}|[a-fA-F0-9]{32})\b").unwrap()
+    };
+    static ref RE_URL: Regex = {
+        Regex::new(r#"https?://[^\s"'<>()]+"#).unwrap()
+    };
+}
+
+/// Represents the indicators of compromise found on a page.
+#[derive(Default)]
+pub struct IocSet {
+    pub ips: Vec<IpAddr>,
+    pub emails: Vec<String>,
+    pub hashes: Vec<String>,
+    pub other_urls: Vec<String>,
+}
+
+/// Represents a stored indicator of compromise.
+#[derive(Serialize)]
+pub struct IocRecord {
+    pub ioc_type: String,
+    pub ioc_value: String,
+}
+
+impl IocSet {
+    /// Returns the total number of indicators.
+    pub fn len(&self) -> usize {
+        self.ips.len() + self.emails.len() + self.hashes.len() + self.other_urls.len()
+    }
+}
+
+/// Extracts indicators of compromise from the HTML code of a page.
+///
+/// Every indicator is returned only once, in the order of its first appearance.
+///
+/// # Arguments
+///
+/// * `html` - The HTML code of the page.
+///
+/// # Returns
+///
+/// An `IocSet` with IP addresses, e-mail addresses, file hashes and URLs found on the page.
+pub fn extract_iocs(html: &str) -> IocSet {
+    let mut iocs = IocSet::default();
+
+    for ip in RE_IPV4.find_iter(html).filter_map(|ip| ip.as_str().parse::<IpAddr>().ok()) {
+        if !iocs.ips.contains(&ip) {
+            iocs.ips.push(ip);
+        }
+    }
+
+    push_unique(&mut iocs.emails, RE_EMAIL.find_iter(html).map(|email| email.as_str().to_lowercase()));
+    push_unique(&mut iocs.hashes, RE_HASH.find_iter(html).map(|hash| hash.as_str().to_lowercase()));
+    push_unique(&mut iocs.other_urls, RE_URL.find_iter(html).map(|url| url.as_str().to_string()));
+
+    iocs
+}
+
+/// Adds the values to the vector, skipping the values it already contains.
+fn push_unique(vec: &mut Vec<String>, values: impl Iterator<Item = String>) {
+    for value in values {
+        if !vec.contains(&value) {
+            vec.push(value);
+        }
+    }
+}
+
+/// Replaces the stored indicators of compromise of the link.
+///
+/// # Arguments
+///
+/// * `link` - The link of the page the indicators were extracted from.
+/// * `iocs` - The indicators.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn store_iocs(link: &str, iocs: &IocSet) {
+    let connection = database::open_connection();
+
+    let mut db = connection.prepare("DELETE FROM ioc_records WHERE link = ?").unwrap();
+    db.bind(1, link).unwrap();
+    db.next().unwrap();
+
+    let records = iocs.ips.iter().map(|ip| ("ip", ip.to_string()))
+        .chain(iocs.emails.iter().map(|email| ("email", email.clone())))
+        .chain(iocs.hashes.iter().map(|hash| ("hash", hash.clone())))
+        .chain(iocs.other_urls.iter().map(|url| ("url", url.clone())));
+
+    for (ioc_type, ioc_value) in records {
+        let mut db = connection.prepare("INSERT INTO ioc_records (link, ioc_type, ioc_value, extracted_at) VALUES (?, ?, ?, datetime('now'))").unwrap();
+
+        db.bind(1, link).unwrap();
+        db.bind(2, ioc_type).unwrap();
+        db.bind(3, ioc_value.as_str()).unwrap();
+
+        db.next().unwrap();
+    }
+}
+
+/// Returns the stored indicators of compromise of the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_iocs(link: &str) -> Vec<IocRecord> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT ioc_type, ioc_value FROM ioc_records WHERE link = ? ORDER BY ioc_type, ioc_value").unwrap();
+
+    db.bind(1, link).unwrap();
+
+    let mut vec: Vec<IocRecord> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(IocRecord {
+            ioc_type: db.read::<String>(0).unwrap(),
+            ioc_value: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod iocs_tests {
+    use crate::iocs::extract_iocs;
+
+    #[test]
+    fn test_extract_iocs() {
+        let html = r#"<a href="http://185.12.4.9/payload.exe">Download</a>
+            <p>Contact: Support@Evil.example or support@evil.example</p>
+            <p>MD5: d41d8cd98f00b204e9800998ecf8427e, version 1.2.3.4567</p>
+            <script src="https://cdn.evil.example/loader.js"></script>"#;
+
+        let iocs = extract_iocs(html);
+
+        assert_eq!(iocs.ips, vec!["185.12.4.9".parse::<std::net::IpAddr>().unwrap()]);
+        assert_eq!(iocs.emails, vec!["support@evil.example"]);
+        assert_eq!(iocs.hashes, vec!["d41d8cd98f00b204e9800998ecf8427e"]);
+        assert_eq!(iocs.other_urls, vec!["http://185.12.4.9/payload.exe", "https://cdn.evil.example/loader.js"]);
+        assert_eq!(iocs.len(), 5);
+    }
+}
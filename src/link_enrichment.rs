@@ -0,0 +1,418 @@
+use std::env;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{brand_favicons, csp, ct_logs, database, domain_family, fingerprint, link_flags, robots_txt_cache, tls_verification, website};
+
+/// MIME types of executables, installers, archives and scripts, which a link to a web page should not lead to.
+const SUSPICIOUS_CONTENT_TYPES: &[&str] = &[
+    "application/octet-stream",
+    "application/x-msdownload",
+    "application/x-msdos-program",
+    "application/x-msi",
+    "application/vnd.microsoft.portable-executable",
+    "application/x-executable",
+    "application/x-sh",
+    "application/x-bat",
+    "application/java-archive",
+    "application/vnd.android.package-archive",
+    "application/zip",
+    "application/x-zip-compressed",
+    "application/vnd.rar",
+    "application/x-rar-compressed",
+    "application/x-7z-compressed",
+];
+
+lazy_static! {
+    static ref RE_TITLE: Regex = {
+        Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap()
+    };
+    static ref RE_WHITESPACE: Regex = {
+        Regex::new(r"\s+").unwrap()
+    };
+    static ref RE_META_TAG: Regex = {
+        Regex::new(r"(?is)<meta\s[^>]*>").unwrap()
+    };
+    static ref RE_ATTRIBUTE: Regex = {
+        Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap()
+    };
+    static ref RE_JSON_LD: Regex = {
+        Regex::new(r#"(?is)<script[^>]*type\s*=\s*["']application/ld\+json["'][^>]*>(.*?)</script>"#).unwrap()
+    };
+}
+
+/// Represents the structured data a page describes itself with.
+#[derive(Default, Serialize, PartialEq, Debug)]
+pub struct PageMetadata {
+    pub og_title: Option<String>,
+    pub og_description: Option<String>,
+    pub og_image: Option<String>,
+    /// The `@type` of the first schema.org JSON-LD object, for example `Organization` or `Product`.
+    pub schema_type: Option<String>,
+}
+
+/// Represents which enrichment steps are run for new links.
+pub struct EnrichmentConfig {
+    /// Save the title of the page.
+    pub title: bool,
+    /// Save the hash of the site's favicon.
+    pub favicon: bool,
+    /// Save the fingerprint of the page content.
+    pub content: bool,
+    /// Save the Open Graph and schema.org metadata of the page.
+    pub metadata: bool,
+    /// Save whether robots.txt allows the bot to download the page.
+    pub robots: bool,
+    /// Save the analysis of the Content-Security-Policy header of HTTPS pages.
+    pub csp: bool,
+    /// Save the MIME type of the resource and flag executables and archives.
+    pub content_type: bool,
+    /// Save the subdomains of the site found in the Certificate Transparency logs.
+    pub ct_logs: bool,
+    /// Verify the certificate chain of HTTPS sites and flag invalid or expiring certificates.
+    pub tls: bool,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        EnrichmentConfig {
+            title: true,
+            favicon: true,
+            content: true,
+            metadata: true,
+            robots: true,
+            csp: true,
+            content_type: true,
+            ct_logs: true,
+            tls: true,
+        }
+    }
+}
+
+impl EnrichmentConfig {
+    /// Returns the configuration from the `LINK_ENRICHMENT_STEPS` environment variable,
+    /// a comma-separated list of the steps to run (`title,favicon,content,metadata,robots,csp,content_type,ct_logs,tls`).
+    /// If it is not set, all steps are run.
+    pub fn from_env() -> EnrichmentConfig {
+        let steps = match env::var("LINK_ENRICHMENT_STEPS") {
+            Ok(steps) => steps,
+            Err(_) => return EnrichmentConfig::default()
+        };
+
+        let steps: Vec<&str> = steps.split(',').map(|step| step.trim()).collect();
+
+        EnrichmentConfig {
+            title: steps.contains(&"title"),
+            favicon: steps.contains(&"favicon"),
+            content: steps.contains(&"content"),
+            metadata: steps.contains(&"metadata"),
+            robots: steps.contains(&"robots"),
+            csp: steps.contains(&"csp"),
+            content_type: steps.contains(&"content_type"),
+            ct_logs: steps.contains(&"ct_logs"),
+            tls: steps.contains(&"tls"),
+        }
+    }
+}
+
+/// Collects additional information about a new link and saves it to the database.
+///
+/// The steps run concurrently and save their results independently,
+/// so a failed step does not prevent the others from saving theirs.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user who added the link.
+/// * `link` - The link.
+/// * `config` - The steps to run.
+pub async fn enrich_link(user_id: u64, link: &str, config: &EnrichmentConfig) {
+    tokio::join!(
+        enrich_from_page(link, config),
+        enrich_favicon(link, config),
+        enrich_robots(link, config),
+        enrich_csp(link, config),
+        enrich_content_type(link, config),
+        enrich_ct_logs(link, config),
+        enrich_tls(link, config)
+    );
+
+    info!("Enriched the link of the user {}: {}", user_id, link);
+}
+
+/// Downloads the page once and saves its title, metadata and the fingerprint of its content.
+///
+/// If the page has no `<title>` tag, its Open Graph title is saved as the title.
+async fn enrich_from_page(link: &str, config: &EnrichmentConfig) {
+    if !config.title && !config.content && !config.metadata {
+        return;
+    }
+
+    let html = match website::fetch_page_html(link).await {
+        Ok(Some(html)) => html,
+        Ok(None) => return,
+        Err(err) => {
+            warn!("Failed to download the page: {}. Description: {}", link, err);
+            return;
+        }
+    };
+
+    let metadata = extract_page_metadata(&html);
+
+    if config.title {
+        if let Some(title) = extract_page_title(&html).or(metadata.og_title.clone()) {
+            database::set_page_title(link, &title);
+        }
+    }
+
+    if config.metadata && metadata != PageMetadata::default() {
+        database::set_page_metadata(link, &serde_json::to_string(&metadata).unwrap());
+    }
+
+    if config.content {
+        let tokens = fingerprint::extract_text_tokens(&html);
+        database::set_page_simhash(link, &format!("{:016x}", fingerprint::compute_simhash(&tokens)));
+    }
+}
+
+/// Downloads the favicon of the site and saves its hash.
+///
+/// If the site uses the favicon of a known brand without belonging to it, the link is flagged.
+async fn enrich_favicon(link: &str, config: &EnrichmentConfig) {
+    if !config.favicon {
+        return;
+    }
+
+    match website::fetch_favicon(link).await {
+        Ok(Some(favicon)) => {
+            let favicon_hash = format!("{:016x}", fingerprint::fnv1a(&favicon));
+            database::set_favicon_hash(link, &favicon_hash);
+
+            if let Some(brand) = brand_favicons::find_impersonated_brand(link, &favicon_hash) {
+                info!("The link {} uses the favicon of the brand {}", link, brand);
+                link_flags::flag_link(link, "brand_impersonation", &format!("Подозрительно: сайт использует значок {brand}, но не принадлежит ему"));
+            }
+        }
+        Ok(None) => (),
+        Err(err) => warn!("Failed to download the favicon: {}. Description: {}", link, err)
+    }
+}
+
+/// Saves whether the robots.txt file of the site allows the bot to download the page.
+async fn enrich_robots(link: &str, config: &EnrichmentConfig) {
+    if !config.robots {
+        return;
+    }
+
+    let crawl_allowed = robots_txt_cache::is_url_crawl_allowed(link).await;
+    database::set_crawl_allowed(link, crawl_allowed);
+}
+
+/// Analyzes the Content-Security-Policy header of an HTTPS page and saves the result.
+async fn enrich_csp(link: &str, config: &EnrichmentConfig) {
+    if !config.csp || !link.starts_with("https://") {
+        return;
+    }
+
+    match website::fetch_csp_header(link).await {
+        Ok(Some(header)) => {
+            let analysis = csp::analyze_csp(&header);
+            database::set_csp_analysis(link, &serde_json::to_string(&analysis).unwrap());
+        }
+        Ok(None) => (),
+        Err(err) => warn!("Failed to get the Content-Security-Policy header: {}. Description: {}", link, err)
+    }
+}
+
+/// Saves the MIME type of the resource and flags the link if it leads to an executable or an archive instead of a page.
+async fn enrich_content_type(link: &str, config: &EnrichmentConfig) {
+    if !config.content_type {
+        return;
+    }
+
+    match website::fetch_content_type(link).await {
+        Ok(Some(content_type)) => {
+            database::set_content_type(link, &content_type);
+
+            if is_suspicious_content_type(&content_type) {
+                info!("The link {} leads to a resource of the type {}", link, content_type);
+                link_flags::flag_link(link, "content_type_suspicious", &format!("Ссылка ведет не на страницу, а на файл типа {content_type}"));
+            }
+        }
+        Ok(None) => (),
+        Err(err) => warn!("Failed to get the Content-Type header: {}. Description: {}", link, err)
+    }
+}
+
+/// Saves the subdomains of the site found in the Certificate Transparency logs.
+///
+/// Phishing sites often register many subdomains of one domain, which the logs reveal.
+async fn enrich_ct_logs(link: &str, config: &EnrichmentConfig) {
+    if !config.ct_logs {
+        return;
+    }
+
+    let Ok(domain) = domain_family::get_domain_family(link) else {
+        return;
+    };
+
+    match ct_logs::query_ct_logs(&domain).await {
+        Ok(subdomains) => ct_logs::store_ct_subdomains(&domain, &subdomains),
+        Err(err) => warn!("Failed to query the Certificate Transparency logs: {}. Description: {}", domain, err)
+    }
+}
+
+/// Verifies the certificate chain of an HTTPS site, saves the result and flags the link if the certificate should not be trusted.
+async fn enrich_tls(link: &str, config: &EnrichmentConfig) {
+    if !config.tls || !link.starts_with("https://") {
+        return;
+    }
+
+    match tls_verification::verify_tls_chain(link).await {
+        Ok(verification) => {
+            database::set_tls_verification(link, &serde_json::to_string(&verification).unwrap());
+
+            if tls_verification::is_certificate_invalid(&verification) {
+                let reason = if !verification.chain_valid || verification.cert_expired {
+                    "Сертификат сайта недействителен".to_string()
+                }
+                else {
+                    format!("Сертификат сайта истекает через {} дн.", verification.days_until_expiry)
+                };
+
+                info!("The link {} has an invalid certificate", link);
+                link_flags::flag_link(link, "certificate_invalid", &reason);
+            }
+        }
+        Err(err) => warn!("Failed to verify the certificate: {}. Description: {}", link, err)
+    }
+}
+
+/// Checks whether the MIME type is one of an executable, an installer, an archive or a script.
+///
+/// # Arguments
+///
+/// * `content_type` - The lowercase MIME type without parameters.
+pub fn is_suspicious_content_type(content_type: &str) -> bool {
+    SUSPICIOUS_CONTENT_TYPES.contains(&content_type)
+}
+
+/// Extracts the title of an HTML page.
+///
+/// # Arguments
+///
+/// * `html` - The HTML code of the page.
+///
+/// # Returns
+///
+/// The text of the `<title>` tag with the whitespace collapsed, or `None` if the page has no title.
+pub fn extract_page_title(html: &str) -> Option<String> {
+    let title = RE_TITLE.captures(html)?.get(1)?.as_str();
+    let title = RE_WHITESPACE.replace_all(title, " ").trim().to_string();
+
+    if title.is_empty() {
+        None
+    }
+    else {
+        Some(title)
+    }
+}
+
+/// Extracts the Open Graph tags and the schema.org type of an HTML page.
+///
+/// # Arguments
+///
+/// * `html` - The HTML code of the page.
+///
+/// # Returns
+///
+/// The metadata of the page. Values the page does not have are `None`.
+pub fn extract_page_metadata(html: &str) -> PageMetadata {
+    let mut metadata = PageMetadata::default();
+
+    for tag in RE_META_TAG.find_iter(html) {
+        let mut property: Option<String> = None;
+        let mut content: Option<String> = None;
+
+        for attribute in RE_ATTRIBUTE.captures_iter(tag.as_str()) {
+            let value = attribute.get(2).or(attribute.get(3)).map(|value| value.as_str().trim().to_string());
+
+            match attribute[1].to_lowercase().as_str() {
+                // Some sites use `name` instead of `property` for Open Graph tags
+                "property" | "name" => property = value.map(|value| value.to_lowercase()),
+                "content" => content = value.filter(|value| !value.is_empty()),
+                _ => ()
+            }
+        }
+
+        let field = match property.as_deref() {
+            Some("og:title") => &mut metadata.og_title,
+            Some("og:description") => &mut metadata.og_description,
+            Some("og:image") => &mut metadata.og_image,
+            _ => continue
+        };
+
+        if field.is_none() {
+            *field = content;
+        }
+    }
+
+    metadata.schema_type = RE_JSON_LD.captures_iter(html)
+        .filter_map(|script| serde_json::from_str::<serde_json::Value>(&script[1]).ok())
+        .find_map(|json| extract_schema_type(&json));
+
+    metadata
+}
+
+/// Returns the `@type` of the first object in a JSON-LD document.
+///
+/// Documents can be a single object, an array of objects or an object with the `@graph` array.
+fn extract_schema_type(json: &serde_json::Value) -> Option<String> {
+    match json {
+        serde_json::Value::Array(objects) => objects.iter().find_map(extract_schema_type),
+        serde_json::Value::Object(object) => match object.get("@type") {
+            Some(serde_json::Value::String(schema_type)) => Some(schema_type.clone()),
+            Some(serde_json::Value::Array(schema_types)) => schema_types.first().and_then(|schema_type| schema_type.as_str()).map(|schema_type| schema_type.to_string()),
+            _ => object.get("@graph").and_then(extract_schema_type)
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod link_enrichment_tests {
+    use crate::link_enrichment::{extract_page_metadata, extract_page_title, is_suspicious_content_type};
+
+    #[test]
+    fn test_extract_page_title() {
+        assert_eq!(extract_page_title("<html><head><TITLE lang=\"en\">\n  Sign in to\n your account </TITLE></head></html>").as_deref(), Some("Sign in to your account"));
+        assert_eq!(extract_page_title("<title>   </title>"), None);
+        assert_eq!(extract_page_title("<p>No title</p>"), None);
+    }
+
+    #[test]
+    fn test_extract_page_metadata() {
+        let html = r#"<head>
+            <meta property="og:title" content="Secure Login">
+            <meta name='og:description' content='Verify your account' />
+            <meta content="https://evil.example/logo.png" property="og:image">
+            <script type="application/ld+json">{"@context": "https://schema.org", "@graph": [{"@type": "Organization"}]}</script>
+        </head>"#;
+
+        let metadata = extract_page_metadata(html);
+
+        assert_eq!(metadata.og_title.as_deref(), Some("Secure Login"));
+        assert_eq!(metadata.og_description.as_deref(), Some("Verify your account"));
+        assert_eq!(metadata.og_image.as_deref(), Some("https://evil.example/logo.png"));
+        assert_eq!(metadata.schema_type.as_deref(), Some("Organization"));
+        assert_eq!(extract_page_metadata("<p>Nothing</p>"), Default::default());
+    }
+
+    #[test]
+    fn test_is_suspicious_content_type() {
+        assert!(is_suspicious_content_type("application/x-msdownload"));
+        assert!(is_suspicious_content_type("application/zip"));
+        assert!(!is_suspicious_content_type("text/html"));
+        assert!(!is_suspicious_content_type("image/png"));
+    }
+}
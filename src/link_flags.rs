@@ -0,0 +1,123 @@
+use serde::Serialize;
+use sqlite3::State;
+
+use crate::{database, domain_reputation};
+
+/// Represents a warning attached to a link by one of the bot's checks.
+#[derive(Serialize)]
+pub struct LinkFlag {
+    pub flag: String,
+    pub reason: String,
+}
+
+/// Attaches a warning to the link. If the link already has a warning of this kind, its reason is updated.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `flag` - The kind of the warning, for example `custom_rule`.
+/// * `reason` - The description of the warning for the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn flag_link(link: &str, flag: &str, reason: &str) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR REPLACE INTO link_flags (link, flag, reason, domain, flagged_at) VALUES (?, ?, ?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, link).unwrap();
+    db.bind(2, flag).unwrap();
+    db.bind(3, reason).unwrap();
+
+    match domain_reputation::get_domain(link) {
+        Some(domain) => db.bind(4, domain.as_str()).unwrap(),
+        None => db.bind(4, ()).unwrap()
+    }
+
+    db.next().unwrap();
+}
+
+/// Returns all warnings attached to the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_link_flags(link: &str) -> Vec<LinkFlag> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT flag, reason FROM link_flags WHERE link = ? ORDER BY flagged_at").unwrap();
+
+    db.bind(1, link).unwrap();
+
+    let mut vec: Vec<LinkFlag> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(LinkFlag {
+            flag: db.read::<String>(0).unwrap(),
+            reason: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    vec
+}
+
+/// Returns the users who have added the most links with warnings.
+///
+/// # Arguments
+///
+/// * `limit` - The maximum number of users.
+///
+/// # Returns
+///
+/// The IDs of the users together with the number of their links with warnings, the most first.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_top_threat_hunters(limit: u32) -> Vec<(u64, u64)> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT links.user_id, COUNT(DISTINCT links.link) AS flagged_links FROM links \
+        JOIN link_flags ON link_flags.link = links.link \
+        GROUP BY links.user_id \
+        ORDER BY flagged_links DESC, links.user_id \
+        LIMIT ?").unwrap();
+
+    db.bind(1, limit as i64).unwrap();
+
+    let mut vec: Vec<(u64, u64)> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push((db.read::<i64>(0).unwrap() as u64, db.read::<i64>(1).unwrap() as u64));
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod link_flags_tests {
+    use crate::database;
+    use crate::link_flags::*;
+
+    #[test]
+    fn test_get_top_threat_hunters() {
+        database::add_link(92001, "https://hunted-one.example.com/");
+        database::add_link(92001, "https://hunted-two.example.com/");
+        flag_link("https://hunted-one.example.com/", "custom_rule", "Phishing");
+        flag_link("https://hunted-one.example.com/", "malicious_redirector", "Redirect");
+        flag_link("https://hunted-two.example.com/", "custom_rule", "Phishing");
+
+        let hunters = get_top_threat_hunters(100);
+
+        assert!(hunters.contains(&(92001, 2)));
+
+        database::clear_all_links(92001);
+        database::open_connection().execute("DELETE FROM link_deletions WHERE user_id = 92001; \
+            DELETE FROM link_flags WHERE link IN ('https://hunted-one.example.com/', 'https://hunted-two.example.com/')").unwrap();
+    }
+}
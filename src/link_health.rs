@@ -0,0 +1,268 @@
+use std::env;
+use sqlite3::State;
+
+use crate::{database, domain_reputation};
+
+/// Returns the number of links checked per hour.
+///
+/// The value is taken from the `LINK_HEALTH_BATCH_SIZE` environment variable.
+/// If it is not set, all links are checked every hour.
+pub fn get_health_batch_size() -> Option<u32> {
+    env::var("LINK_HEALTH_BATCH_SIZE").ok().and_then(|size| size.parse::<u32>().ok())
+}
+
+/// Returns the links that should be checked next.
+///
+/// Links that have never been checked come first, followed by the links that were checked the longest time ago.
+/// Links checked at the same time are ordered by the number of times users have viewed them, the most viewed first.
+/// Links with their own rescan interval are skipped until that many hours have passed since their latest check.
+///
+/// # Arguments
+///
+/// * `batch_size` - The maximum number of links, or `None` to return all links.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_links_for_health_check(batch_size: Option<u32>) -> Vec<String> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT links.link FROM links \
+        LEFT JOIN (SELECT link, MAX(checked_at) AS last_checked_at FROM link_health_checks GROUP BY link) AS checks \
+        ON checks.link = links.link \
+        LEFT JOIN (SELECT link, SUM(view_count) AS views FROM link_view_counts GROUP BY link) AS link_views \
+        ON link_views.link = links.link \
+        WHERE links.is_hashed = 0 \
+        GROUP BY links.link \
+        HAVING MAX(links.rescan_interval_hours) IS NULL OR MAX(checks.last_checked_at) IS NULL \
+            OR ROUND((julianday('now') - julianday(MAX(checks.last_checked_at))) * 24) >= MAX(links.rescan_interval_hours) \
+        ORDER BY MAX(checks.last_checked_at) IS NOT NULL, MAX(checks.last_checked_at), COALESCE(MAX(link_views.views), 0) DESC \
+        LIMIT ?").unwrap();
+
+    // A negative limit means that there is no limit
+    db.bind(1, batch_size.map(|size| size as i64).unwrap_or(-1)).unwrap();
+
+    let mut vec: Vec<String> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<String>(0).unwrap());
+    }
+
+    vec
+}
+
+/// Sets how often the link is checked, overriding the hourly checks for all users who added it.
+///
+/// Links are checked once an hour at most, so the interval is rounded to whole hours.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `hours` - The number of hours between the checks, or `None` to check the link every hour.
+///
+/// # Returns
+///
+/// The number of saved copies of the link that were updated, 0 if nobody has added the link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_rescan_interval(link: &str, hours: Option<u32>) -> u64 {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE links SET rescan_interval_hours = ? WHERE link = ? AND is_hashed = 0").unwrap();
+
+    match hours {
+        Some(hours) => db.bind(1, hours as i64).unwrap(),
+        None => db.bind(1, ()).unwrap()
+    }
+    db.bind(2, link).unwrap();
+
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT changes()").unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap() as u64
+}
+
+/// Represents the result of checking a link.
+pub struct HealthCheckRecord {
+    pub link: String,
+    /// The status code of the response, if a response was received.
+    pub status_code: Option<u16>,
+    /// The URL the link was redirected to, if any.
+    pub redirect_url: Option<String>,
+    /// The description of the error, if the link could not be checked.
+    pub error: Option<String>,
+}
+
+/// Saves the results of checking links in a single transaction.
+///
+/// If one of the results cannot be saved, none of them are saved.
+///
+/// # Arguments
+///
+/// * `records` - The results of the checks.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the number of saved results.
+/// * An `Err` variant containing a `sqlite3::Error` if the results could not be saved.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn batch_record_health_checks(records: &[HealthCheckRecord]) -> Result<u64, sqlite3::Error> {
+    let connection = database::open_connection();
+
+    connection.execute("BEGIN")?;
+
+    let result = (|| {
+        let mut db = connection.prepare("INSERT INTO link_health_checks (link, checked_at, status_code, redirect_url, error, domain) VALUES (?, datetime('now'), ?, ?, ?, ?)")?;
+
+        for record in records {
+            db.reset()?;
+            db.bind(1, record.link.as_str())?;
+
+            match record.status_code {
+                Some(status_code) => db.bind(2, status_code as i64)?,
+                None => db.bind(2, ())?
+            }
+            match &record.redirect_url {
+                Some(redirect_url) => db.bind(3, redirect_url.as_str())?,
+                None => db.bind(3, ())?
+            }
+            match &record.error {
+                Some(error) => db.bind(4, error.as_str())?,
+                None => db.bind(4, ())?
+            }
+            match domain_reputation::get_domain(&record.link) {
+                Some(domain) => db.bind(5, domain.as_str())?,
+                None => db.bind(5, ())?
+            }
+
+            db.next()?;
+        }
+
+        Ok(records.len() as u64)
+    })();
+
+    match result {
+        Ok(_) => connection.execute("COMMIT")?,
+        Err(_) => connection.execute("ROLLBACK")?,
+    }
+
+    result
+}
+
+/// Returns the URL the link was redirected to during the latest check.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+///
+/// # Returns
+///
+/// `None` if the link has never been checked or was not redirected during the latest check.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_last_redirect_url(link: &str) -> Option<String> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT redirect_url FROM link_health_checks WHERE link = ? ORDER BY id DESC LIMIT 1").unwrap();
+
+    db.bind(1, link).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => db.read::<String>(0).ok(),
+        State::Done => None
+    }
+}
+
+/// Returns the latest check of the link.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+///
+/// # Returns
+///
+/// `None` if the link has never been checked.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_last_health_check(link: &str) -> Option<HealthCheckRecord> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT COALESCE(status_code, 0), redirect_url, error FROM link_health_checks WHERE link = ? ORDER BY id DESC LIMIT 1").unwrap();
+
+    db.bind(1, link).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => Some(HealthCheckRecord {
+            link: link.to_string(),
+            // Checks without a response are saved without a status code
+            status_code: Some(db.read::<i64>(0).unwrap() as u16).filter(|status_code| *status_code != 0),
+            redirect_url: db.read::<String>(1).ok(),
+            error: db.read::<String>(2).ok(),
+        }),
+        State::Done => None
+    }
+}
+
+#[cfg(test)]
+mod link_health_tests {
+    use crate::database;
+    use crate::link_health::*;
+
+    #[test]
+    fn test_least_recently_checked_links_come_first() {
+        database::add_link(40501, "https://checked.example");
+        database::add_link(40501, "https://unchecked.example");
+
+        let saved = batch_record_health_checks(&[HealthCheckRecord {
+            link: "https://checked.example".to_string(),
+            status_code: Some(301),
+            redirect_url: Some("https://moved.example/".to_string()),
+            error: None,
+        }]).unwrap();
+
+        assert_eq!(saved, 1);
+
+        let links = get_links_for_health_check(None);
+        let checked = links.iter().position(|link| link == "https://checked.example").unwrap();
+        let unchecked = links.iter().position(|link| link == "https://unchecked.example").unwrap();
+
+        assert!(unchecked < checked);
+        assert_eq!(get_last_redirect_url("https://checked.example").as_deref(), Some("https://moved.example/"));
+
+        database::clear_all_links(40501);
+    }
+
+    #[test]
+    fn test_rescan_interval_postpones_checks() {
+        database::add_link(40502, "https://rarely-checked.example");
+
+        batch_record_health_checks(&[HealthCheckRecord {
+            link: "https://rarely-checked.example".to_string(),
+            status_code: Some(200),
+            redirect_url: None,
+            error: None,
+        }]).unwrap();
+
+        assert_eq!(set_rescan_interval("https://rarely-checked.example", Some(6)), 1);
+        assert!(!get_links_for_health_check(None).contains(&"https://rarely-checked.example".to_string()));
+
+        set_rescan_interval("https://rarely-checked.example", None);
+        assert!(get_links_for_health_check(None).contains(&"https://rarely-checked.example".to_string()));
+
+        assert_eq!(set_rescan_interval("https://never-added.example", Some(6)), 0);
+
+        database::clear_all_links(40502);
+    }
+}
@@ -0,0 +1,140 @@
+use is_url::is_url;
+use serde::{Deserialize, Serialize};
+
+use crate::{custom_scan_rules, database, url_normalizer, user_preferences};
+use crate::custom_scan_rules::RuleAction;
+use crate::link_enrichment::{self, EnrichmentConfig};
+
+/// The maximum number of links imported from one file.
+pub const MAX_IMPORTED_LINKS: usize = 100;
+
+/// Represents the format of a file with links.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ImportFormat {
+    /// One link per line.
+    Text,
+    /// Links in any cells of a table separated by commas or semicolons.
+    Csv
+}
+
+/// Represents the result of importing links.
+pub struct ImportReport {
+    pub added: usize,
+    pub skipped: usize,
+}
+
+impl ImportFormat {
+    /// Parses the format from the data of the button that chose it.
+    pub fn parse(format: &str) -> Option<ImportFormat> {
+        match format {
+            "txt" => Some(ImportFormat::Text),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None
+        }
+    }
+}
+
+/// Finds the links in the content of a file.
+///
+/// Values are normalized with `normalize_url`, values that are not links are skipped.
+///
+/// # Arguments
+///
+/// * `content` - The content of the file.
+/// * `format` - The format of the file.
+///
+/// # Returns
+///
+/// Unique links in the order they appear in the file, not more than `MAX_IMPORTED_LINKS`.
+pub fn parse_links(content: &str, format: ImportFormat) -> Vec<String> {
+    let values: Vec<&str> = match format {
+        ImportFormat::Text => content.lines().collect(),
+        ImportFormat::Csv => content.lines().flat_map(|line| line.split([',', ';'])).collect()
+    };
+
+    let mut links: Vec<String> = Vec::new();
+
+    for value in values {
+        let value = value.trim().trim_matches('"').trim();
+
+        // Headers and names in tables are not links, and every link to a public site has a dot in it
+        if !value.contains('.') {
+            continue;
+        }
+
+        let link = url_normalizer::normalize_url(value);
+
+        if is_url(&link) && !links.contains(&link) {
+            links.push(link);
+        }
+
+        if links.len() == MAX_IMPORTED_LINKS {
+            break;
+        }
+    }
+
+    links
+}
+
+/// Adds the links to the user's links.
+///
+/// Links the user has already added and links blocked by custom rules are skipped.
+/// Information about the added links is collected in the background, unless the user has turned on the privacy mode.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `links` - The links to add.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn import_links(user_id: u64, links: &[String]) -> ImportReport {
+    let mut report = ImportReport { added: 0, skipped: 0 };
+
+    for link in links {
+        let is_blocked = custom_scan_rules::apply_custom_rules(link)
+            .map(|rule_result| rule_result.action == RuleAction::Block)
+            .unwrap_or(false);
+
+        if is_blocked || database::is_link_exists(user_id, link) {
+            report.skipped += 1;
+            continue;
+        }
+
+        database::add_link(user_id, link);
+        report.added += 1;
+
+        // Hashed links cannot be opened
+        if user_preferences::get_user_preferences(user_id).privacy_mode {
+            continue;
+        }
+
+        let link = link.clone();
+        tokio::spawn(async move {
+            link_enrichment::enrich_link(user_id, &link, &EnrichmentConfig::from_env()).await;
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod link_import_tests {
+    use crate::link_import::*;
+
+    #[test]
+    fn test_parse_text_links() {
+        let links = parse_links("https://example.com\n\nexample.org/page\nnot a link\nhttps://example.com\n", ImportFormat::Text);
+
+        assert_eq!(links, vec!["https://example.com", "https://example.org/page"]);
+    }
+
+    #[test]
+    fn test_parse_csv_links() {
+        let links = parse_links("name;link\nShop;\"https://shop.example.com\"\nBlog,http://blog.example.com/feed\n", ImportFormat::Csv);
+
+        assert_eq!(links, vec!["https://shop.example.com", "http://blog.example.com/feed"]);
+    }
+}
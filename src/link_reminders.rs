@@ -0,0 +1,214 @@
+use sqlite3::State;
+
+use crate::database;
+use crate::link_health::HealthCheckRecord;
+
+/// Represents the change of a link's check result a reminder waits for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReminderTrigger {
+    /// The status code or the error differs from the previous check.
+    AnyChange,
+    /// The site responds without an error after it was unavailable.
+    Available,
+    /// The site responds with an error or does not respond after it was available.
+    Unavailable
+}
+
+/// Represents a reminder whose trigger has fired.
+pub struct LinkReminder {
+    pub id: i64,
+    pub user_id: u64,
+    pub link: String,
+}
+
+impl ReminderTrigger {
+    /// Parses the trigger as it is stored in the database.
+    pub fn parse(trigger: &str) -> Option<ReminderTrigger> {
+        match trigger {
+            "any_change" => Some(ReminderTrigger::AnyChange),
+            "available" => Some(ReminderTrigger::Available),
+            "unavailable" => Some(ReminderTrigger::Unavailable),
+            _ => None
+        }
+    }
+
+    /// Returns the trigger as it is stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderTrigger::AnyChange => "any_change",
+            ReminderTrigger::Available => "available",
+            ReminderTrigger::Unavailable => "unavailable"
+        }
+    }
+
+    /// Checks whether the change from the previous check to the current one fires the trigger.
+    ///
+    /// Nothing fires on the first check of a link, as there is nothing to compare it with.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The previous check of the link.
+    /// * `current` - The current check of the link.
+    pub fn is_fired(&self, previous: &HealthCheckRecord, current: &HealthCheckRecord) -> bool {
+        match self {
+            ReminderTrigger::AnyChange => previous.status_code != current.status_code || previous.error != current.error,
+            ReminderTrigger::Available => !is_available(previous) && is_available(current),
+            ReminderTrigger::Unavailable => is_available(previous) && !is_available(current)
+        }
+    }
+}
+
+/// Checks whether the site responded without an error during the check.
+fn is_available(check: &HealthCheckRecord) -> bool {
+    matches!(check.status_code, Some(200..=399))
+}
+
+/// Adds a reminder that is sent once, when the check result of the link changes.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `link` - The link.
+/// * `trigger` - The change to wait for.
+///
+/// # Returns
+///
+/// The ID of the new reminder.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_reminder(user_id: u64, link: &str, trigger: ReminderTrigger) -> i64 {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO link_reminders (user_id, link, trigger_on) VALUES (?, ?, ?)").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, link).unwrap();
+    db.bind(3, trigger.as_str()).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT last_insert_rowid()").unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap()
+}
+
+/// Returns the reminders of the link that have not been sent yet and are fired by the change of its check result.
+///
+/// # Arguments
+///
+/// * `previous` - The previous check of the link, or `None` if the link has not been checked before.
+/// * `current` - The current check of the link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_fired_reminders(previous: Option<&HealthCheckRecord>, current: &HealthCheckRecord) -> Vec<LinkReminder> {
+    let Some(previous) = previous else {
+        return Vec::new();
+    };
+
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT id, user_id, trigger_on FROM link_reminders \
+        WHERE link = ? AND reminded_at IS NULL \
+        AND EXISTS (SELECT 1 FROM links WHERE links.user_id = link_reminders.user_id AND links.link = link_reminders.link) \
+        ORDER BY id").unwrap();
+
+    db.bind(1, current.link.as_str()).unwrap();
+
+    let mut vec: Vec<LinkReminder> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let Some(trigger) = ReminderTrigger::parse(&db.read::<String>(2).unwrap()) else {
+            continue;
+        };
+
+        if trigger.is_fired(previous, current) {
+            vec.push(LinkReminder {
+                id: db.read::<i64>(0).unwrap(),
+                user_id: db.read::<i64>(1).unwrap() as u64,
+                link: current.link.clone(),
+            });
+        }
+    }
+
+    vec
+}
+
+/// Marks the reminder as sent, so it is not sent again.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the reminder.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn mark_reminded(id: i64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE link_reminders SET reminded_at = datetime('now') WHERE id = ?").unwrap();
+
+    db.bind(1, id).unwrap();
+
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod link_reminders_tests {
+    use crate::database;
+    use crate::link_health::HealthCheckRecord;
+    use crate::link_reminders::*;
+
+    fn check(status_code: Option<u16>, error: Option<&str>) -> HealthCheckRecord {
+        HealthCheckRecord {
+            link: "https://reminder.example.com/".to_string(),
+            status_code,
+            redirect_url: None,
+            error: error.map(|error| error.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_reminder_triggers() {
+        assert!(ReminderTrigger::AnyChange.is_fired(&check(Some(200), None), &check(Some(301), None)));
+        assert!(!ReminderTrigger::AnyChange.is_fired(&check(Some(200), None), &check(Some(200), None)));
+        assert!(ReminderTrigger::Unavailable.is_fired(&check(Some(200), None), &check(None, Some("timeout"))));
+        assert!(!ReminderTrigger::Unavailable.is_fired(&check(Some(200), None), &check(Some(301), None)));
+        assert!(ReminderTrigger::Available.is_fired(&check(Some(503), None), &check(Some(200), None)));
+    }
+
+    #[test]
+    fn test_reminder_is_sent_once() {
+        database::add_link(97001, "https://reminder.example.com/");
+        let id = add_reminder(97001, "https://reminder.example.com/", ReminderTrigger::Unavailable);
+
+        assert!(get_fired_reminders(None, &check(Some(500), None)).is_empty());
+
+        let reminders = get_fired_reminders(Some(&check(Some(200), None)), &check(Some(500), None));
+
+        assert!(reminders.iter().any(|reminder| reminder.id == id && reminder.user_id == 97001));
+
+        mark_reminded(id);
+
+        let reminders = get_fired_reminders(Some(&check(Some(200), None)), &check(Some(500), None));
+
+        assert!(reminders.iter().all(|reminder| reminder.id != id));
+
+        database::clear_all_links(97001);
+    }
+
+    #[test]
+    fn test_reminder_is_not_sent_for_deleted_link() {
+        database::add_link(97002, "https://reminder.example.com/");
+        let id = add_reminder(97002, "https://reminder.example.com/", ReminderTrigger::Unavailable);
+
+        database::delete_some_links(97002, vec!["https://reminder.example.com/"]);
+
+        let reminders = get_fired_reminders(Some(&check(Some(200), None)), &check(Some(500), None));
+
+        assert!(reminders.iter().all(|reminder| reminder.id != id && reminder.user_id != 97002));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::database;
+
+/// Counts a view of each of the links shown to the user, for example in the list of their links.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `links` - The links as they are stored.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn record_link_views(user_id: u64, links: &[&str]) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO link_view_counts (link, user_id, view_count, last_viewed_at) VALUES (?, ?, 1, datetime('now')) \
+        ON CONFLICT(link, user_id) DO UPDATE SET view_count = view_count + 1, last_viewed_at = excluded.last_viewed_at").unwrap();
+
+    for link in links {
+        db.reset().unwrap();
+        db.bind(1, *link).unwrap();
+        db.bind(2, user_id as i64).unwrap();
+
+        db.next().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod link_views_tests {
+    use crate::{database, link_health, user_data_export};
+    use crate::link_views::record_link_views;
+
+    #[test]
+    fn test_viewed_links_are_checked_first() {
+        database::add_link(40601, "https://rarely-viewed.example");
+        database::add_link(40601, "https://often-viewed.example");
+
+        record_link_views(40601, &["https://often-viewed.example", "https://rarely-viewed.example"]);
+        record_link_views(40601, &["https://often-viewed.example"]);
+
+        let links = link_health::get_links_for_health_check(None);
+        let rarely_viewed = links.iter().position(|link| link == "https://rarely-viewed.example").unwrap();
+        let often_viewed = links.iter().position(|link| link == "https://often-viewed.example").unwrap();
+
+        assert!(often_viewed < rarely_viewed);
+
+        database::clear_all_links(40601);
+    }
+
+    #[test]
+    fn test_views_are_deleted_with_links() {
+        database::add_link(40602, "https://viewed-and-deleted.example");
+        database::add_link(40602, "https://viewed-and-cleared.example");
+
+        record_link_views(40602, &["https://viewed-and-deleted.example", "https://viewed-and-cleared.example"]);
+
+        database::delete_some_links(40602, vec!["https://viewed-and-deleted.example"]);
+
+        assert_eq!(user_data_export::export_all_user_data(40602).link_views.len(), 1);
+
+        database::clear_all_links(40602);
+
+        assert!(user_data_export::export_all_user_data(40602).link_views.is_empty());
+    }
+}
@@ -1,31 +1,118 @@
+use std::env;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Semaphore;
+use chrono::Datelike;
 use dptree::{case, deps};
 use is_url::is_url;
 use log::LevelFilter;
 use teloxide::{
-    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
     prelude::*,
     Bot,
     utils::command::BotCommands,
-    dispatching::{dialogue, dialogue::InMemStorage, UpdateHandler},
-    types::{InputFile}
+    dispatching::{dialogue, UpdateHandler},
+    update_listeners::webhooks,
+    types::{InputFile},
+    ApiError,
+    RequestError
 };
+use serde::{Deserialize, Serialize};
 use teloxide::dispatching::dialogue::GetChatId;
 
+use crate::callback_router::CallbackRouter;
+use crate::custom_scan_rules::{PatternType, RuleAction};
 use crate::database::Links;
+use crate::event_bus::{BotEvent, EVENT_BUS};
+use crate::file_downloader::{DownloadError, TelegramFileDownloader};
+use crate::input_type::InputType;
+use crate::link_enrichment::EnrichmentConfig;
+use crate::dialogue_storage::DialogueStorage;
+use crate::link_health::HealthCheckRecord;
+use crate::link_reminders::ReminderTrigger;
+use crate::link_import::ImportFormat;
+use crate::scan_sessions::SessionLinkStatus;
+use crate::user_activity::UserActivity;
+use crate::user_timeline::TimelineEvent;
 use crate::website::SiteInformation;
 
 extern crate pretty_env_logger;
 #[macro_use] extern crate log;
 
+mod brand_favicons;
+mod callback_router;
+mod campaign_alerts;
+mod csp;
+mod ct_logs;
+mod ct_monitor;
+mod custom_scan_rules;
 mod database;
+mod dialogue_storage;
+mod domain_family;
+mod domain_reputation;
+mod error_reporter;
+mod event_bus;
+mod file_downloader;
+mod fingerprint;
+mod incident_report;
+mod input_type;
+mod iocs;
+mod link_enrichment;
+mod link_flags;
+mod link_health;
+mod link_import;
+mod link_reminders;
+mod link_views;
+mod message_queue;
+mod message_text;
+mod privacy_audit;
+mod report_sharing;
+mod reverse_dns;
+mod robots_txt_cache;
+mod scan_sessions;
+mod shortener_cache;
+mod site_analysis_cooldown;
+mod tls_verification;
+mod url_normalizer;
+mod user_activity;
+mod user_data_export;
+mod user_preferences;
+mod user_statistics;
+mod user_timeline;
 mod website;
 
 type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
-type SparkleDialogue = Dialogue<BotState, InMemStorage<BotState>>;
+type SparkleDialogue = Dialogue<BotState, DialogueStorage<BotState>>;
 
 const HOUR_IN_SECONDS: u64 = 3600;
 
+/// The maximum number of differing bits in the fingerprints of pages considered similar
+const SIMILAR_PAGE_MAX_DISTANCE: u8 = 3;
+
+/// The maximum size of a file with links for import, in bytes.
+const MAX_IMPORT_FILE_SIZE: u32 = 1024 * 1024;
+/// The MIME types of files with links for import. Windows reports CSV files as Excel spreadsheets.
+const IMPORT_MIME_TYPES: [&str; 5] = ["text/plain", "text/csv", "text/comma-separated-values", "application/csv", "application/vnd.ms-excel"];
+/// The number of links shown in the preview of the import.
+const IMPORT_PREVIEW_SIZE: usize = 10;
+
+/// The maximum length of the data of an inline keyboard button, in bytes.
+const MAX_CALLBACK_DATA_LENGTH: usize = 64;
+
+/// The number of users shown in the leaderboard.
+const LEADERBOARD_SIZE: u32 = 10;
+/// The number of links of a scan session that are checked at the same time.
+const SESSION_CHECK_CONCURRENCY: usize = 5;
+/// The number of days the trend of a domain's reputation is shown for.
+const DOMAIN_TREND_DAYS: u32 = domain_reputation::DOMAIN_REPUTATION_HISTORY_DAYS;
+/// The number of days the activity of a user is shown for.
+const ACTIVITY_DAYS: u32 = 7;
+/// The short names of the days of the week, starting with Monday.
+const SHORT_WEEKDAYS: [&str; 7] = ["Пн", "Вт", "Ср", "Чт", "Пт", "Сб", "Вс"];
+
+/// The port the webhook server listens on if `WEBHOOK_PORT` is not set.
+const DEFAULT_WEBHOOK_PORT: u16 = 8443;
+
 const STICKER_WELCOME_ID: &str = "CAACAgIAAxkBAAEne6RlSyQM7sJfMXWBN3u-dfEgIlxzoAACBQADwDZPE_lqX5qCa011MwQ";
 const STICKER_ERROR_ID: &str = "CAACAgIAAxkBAAEne6JlSyP9VdH3N8Mk2imfp7BgFRu9NwACEAADwDZPE-qBiinxHwLoMwQ";
 
@@ -47,13 +134,172 @@ enum SparkleCommand {
     CheckSite {
         link: String
     },
+    #[command(description = "Показывает ваши ссылки на сайт и все его поддомены")]
+    Family {
+        link: String
+    },
+    #[command(description = "Показывает, как менялась репутация домена за последние 90 дней")]
+    DomainTrend {
+        link: String
+    },
+    #[command(description = "Импортирует ссылки из файла")]
+    Import,
+    #[command(description = "Показывает пользователей, нашедших больше всего опасных ссылок")]
+    Leaderboard,
+    #[command(description = "Показывает историю ваших ссылок: добавления, проверки и удаления")]
+    Timeline,
+    #[command(description = "Отправляет все ваши данные, которые хранит бот, в формате JSON")]
+    MyData,
+    #[command(description = "Показывает статистику ваших ссылок")]
+    MyStats,
+    #[command(description = "Показывает, сколько ссылок вы добавили за каждый из последних 7 дней")]
+    Activity,
+    #[command(description = "Напоминает, когда изменится результат проверки ссылки: /remind <ссылка> [any_change|available|unavailable]")]
+    Remind {
+        reminder: String
+    },
+    #[command(rename = "share_with", description = "Предлагает пользователю обмениваться отчетами о проверке: /share_with @username")]
+    ShareWith {
+        username: String
+    },
+    #[command(description = "Показывает опасные ссылки ваши и пользователей, с которыми вы обмениваетесь отчетами")]
+    Shared,
+    #[command(description = "Начинает совместную проверку ссылок в группе")]
+    StartSession,
+    #[command(description = "Добавляет ссылку в совместную проверку группы")]
+    Submit {
+        link: String
+    },
+    #[command(description = "Завершает совместную проверку и публикует ее итоги")]
+    EndSession,
+    #[command(description = "Включает (on) или выключает (off) хранение ссылок в виде хэшей")]
+    Privacy {
+        mode: String
+    },
+    #[command(description = "Задает, сколько записей показывать на одной странице списков (от 1 до 20)")]
+    SetPageSize {
+        size: String
+    },
 
     #[command(description = "Показывает команды бота")]
     Help
 }
 
+/// Represents commands that are only available to the bot administrator
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase", description = "Команды администратора:")]
+enum AdminCommand {
+    #[command(description = "Показывает ссылки, которые сохранены несколько раз")]
+    DedupCandidates {
+        min_copies: u32
+    },
+    #[command(description = "Объединяет повторяющиеся ссылки в базе данных")]
+    Dedup,
+    #[command(description = "Находит ссылки на страницы, похожие на страницу по ссылке")]
+    Similar {
+        link: String
+    },
+    #[command(description = "Добавляет правило проверки: /addrule <regex|glob> <block|allow|flag> <шаблон> <причина>")]
+    AddRule {
+        rule: String
+    },
+    #[command(description = "Показывает правила проверки")]
+    Rules,
+    #[command(description = "Удаляет правило проверки")]
+    DeleteRule {
+        id: i64
+    },
+    #[command(description = "Показывает индикаторы компрометации, найденные на странице по ссылке")]
+    Iocs {
+        link: String
+    },
+    #[command(description = "Собирает отчет об инциденте со всеми данными о ссылке в формате JSON: /incident <ссылка> <причина>")]
+    Incident {
+        request: String
+    },
+    #[command(description = "Показывает пользователей, которые не пользовались ботом указанное число дней: /inactive <дни> <причина>")]
+    Inactive {
+        request: String
+    },
+    #[command(description = "Отправляет все данные пользователя в формате JSON: /userdata <ID пользователя> <причина>")]
+    UserData {
+        request: String
+    },
+    #[command(description = "Задает, как часто проверять ссылку: /setrescaninterval <ссылка> <часы>, 0 — каждый час")]
+    SetRescanInterval {
+        request: String
+    },
+    #[command(description = "Сжимает базу данных")]
+    Vacuum
+}
+
+/// Represents the lists of links that are shown page by page.
+enum LinkList {
+    /// The links saved by the user.
+    Saved,
+    /// The links saved by the user, numbered to choose the ones to delete.
+    Deleting,
+    /// The user's links to the sites of the registrable domain.
+    Family(String),
+    /// The links with warnings of the user and of the users they share the reports with.
+    Shared
+}
+
+impl LinkList {
+    /// Returns the callback data of the button that shows the page of the list.
+    fn callback_data(&self, page: u32) -> String {
+        match self {
+            LinkList::Saved => format!("links_page:{page}"),
+            LinkList::Deleting => format!("delete_page:{page}"),
+            LinkList::Family(family) => format!("family_page:{page}:{family}"),
+            LinkList::Shared => format!("shared_page:{page}")
+        }
+    }
+
+    /// Parses the callback data of a page button into the list and the number of the page.
+    fn parse_callback_data(data: &str) -> Option<(LinkList, u32)> {
+        let (prefix, rest) = data.split_once(':')?;
+        let (page, family) = match rest.split_once(':') {
+            Some((page, family)) => (page, Some(family)),
+            None => (rest, None)
+        };
+
+        let list = match (prefix, family) {
+            ("links_page", None) => LinkList::Saved,
+            ("delete_page", None) => LinkList::Deleting,
+            ("family_page", Some(family)) => LinkList::Family(family.to_string()),
+            ("shared_page", None) => LinkList::Shared,
+            _ => return None
+        };
+
+        Some((list, page.parse::<u32>().ok()?))
+    }
+
+    /// Returns the text shown above the links.
+    fn title(&self) -> String {
+        match self {
+            LinkList::Saved => "Вот ваши сохраненные ссылки:\n".to_string(),
+            LinkList::Deleting => "Выберите, какие элементы требуется удалить. Напишите номера элемента через пробел. \
+                Вы можете отменить удаление, введя команду /cancel.\n\nИстория запросов:\n".to_string(),
+            LinkList::Family(family) => format!("Ваши ссылки на {family}:\n"),
+            LinkList::Shared => "⚠️ Опасные ссылки ваши и ваших партнеров:\n".to_string()
+        }
+    }
+
+    /// Returns all links of the list.
+    fn load(&self, user_id: u64) -> Vec<Links> {
+        match self {
+            LinkList::Saved | LinkList::Deleting => database::get_all_links_from_user(user_id, None),
+            LinkList::Family(family) => database::get_links_by_family(user_id, family),
+            LinkList::Shared => report_sharing::get_shared_links(user_id)
+        }
+    }
+}
+
 /// Represents the state of a bot.
-#[derive(Clone, Default)]
+///
+/// States are stored in the `dialogues` table, so multi-step commands survive restarts of the bot.
+#[derive(Clone, Default, Serialize, Deserialize)]
 enum BotState {
     #[default]
     Default,
@@ -61,7 +307,21 @@ enum BotState {
     ReceiveLink,
     ReceiveLinkForChecking,
     ReceiveConfirmRemoveLinks,
-    DeletingSomeLinks
+    DeletingSomeLinks,
+
+    // Steps of the import wizard
+    ImportChooseFormat,
+    ImportReceiveFile {
+        format: ImportFormat
+    },
+    ImportConfirm {
+        links: Vec<String>
+    },
+
+    // Confirmation of adding a link similar to a recently deleted or an already added link
+    ConfirmAddLink {
+        link: String
+    }
 }
 
 #[tokio::main]
@@ -72,21 +332,57 @@ async fn main() -> HandlerResult {
 
     info!("The bot is up and running and ready to go!");
 
+    event_bus::launch_event_logger();
+    campaign_alerts::launch_campaign_alerts(bot.clone());
+    ct_monitor::launch_ct_monitor(bot.clone());
+    tokio::spawn(brand_favicons::seed_known_brand_favicons());
     launch_checkers(bot.clone());
+    message_queue::launch_message_retries(bot.clone());
 
-    Dispatcher::builder(bot, schema())
-        .dependencies(deps![InMemStorage::<BotState>::new()])
+    let mut dispatcher = Dispatcher::builder(bot.clone(), schema())
+        .dependencies(deps![DialogueStorage::<BotState>::new(), Arc::new(create_callback_router())])
         .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+
+    match get_webhook_options() {
+        Some(options) => {
+            info!("Receiving updates through the webhook: {}", options.url);
+
+            // Telegram sends the secret token in every request, and the listener rejects requests without it
+            let listener = webhooks::axum(bot, options).await?;
+
+            dispatcher.dispatch_with_listener(listener, LoggingErrorHandler::with_custom_text("An error from the webhook listener")).await;
+        }
+        None => dispatcher.dispatch().await
+    }
 
     Ok(())
 }
 
+/// Returns the webhook settings if the bot should receive updates through a webhook instead of long polling
+///
+/// The webhook mode is turned on by the `TELEGRAM_WEBHOOK_URL` environment variable. The server listens
+/// on the `WEBHOOK_PORT` port and checks the `TELEGRAM_WEBHOOK_SECRET` token (a random token if it is not set).
+///
+/// returns: Webhook settings, or `None` if long polling should be used
+fn get_webhook_options() -> Option<webhooks::Options> {
+    let url = env::var("TELEGRAM_WEBHOOK_URL").ok()?.parse().expect("TELEGRAM_WEBHOOK_URL is not a valid URL");
+    let port = env::var("WEBHOOK_PORT").ok().and_then(|port| port.parse::<u16>().ok()).unwrap_or(DEFAULT_WEBHOOK_PORT);
+
+    let options = webhooks::Options::new(([0, 0, 0, 0], port).into(), url);
+
+    match env::var("TELEGRAM_WEBHOOK_SECRET") {
+        Ok(secret) => Some(options.secret_token(secret)),
+        Err(_) => Some(options)
+    }
+}
+
 /// Creates a separate standalone thread in which it checks the availability of sites in the
 /// database every hour and if it is unavailable, informs the user
 ///
+/// Each check is saved to the link health history. If the `LINK_HEALTH_BATCH_SIZE` environment
+/// variable is set, only the specified number of the least recently checked links is checked every hour.
+///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
@@ -102,21 +398,72 @@ fn launch_checkers(bot: Bot) {
             info!("Runs a site checker");
 
             let all_links = database::get_all_links();
-
-            for one_link in all_links {
-                let user_id: UserId = UserId(one_link.user_id as u64);
-                let status_code = website::get_request_code(&one_link.link).await;
-
-                match status_code {
-                    Ok(status_code_unwrapped) => {
-                        handle_status_code(&bot, one_link.user_id as u64, one_link.link, status_code_unwrapped).await;
+            let links = link_health::get_links_for_health_check(link_health::get_health_batch_size());
+
+            let mut records: Vec<HealthCheckRecord> = Vec::new();
+
+            for link in links {
+                let user_ids: Vec<u64> = all_links.iter()
+                    .filter(|one_link| one_link.link == link)
+                    .map(|one_link| one_link.user_id as u64)
+                    .collect();
+                let previous_check = link_health::get_last_health_check(&link);
+                let health = website::check_link_health(&link).await;
+
+                match health {
+                    Ok(health) => {
+                        let previous_redirect_url = link_health::get_last_redirect_url(&link);
+
+                        for user_id in user_ids {
+                            let _ = handle_status_code(&bot, user_id, link.clone(), health.status_code).await;
+
+                            if let Some(redirect_url) = &health.redirect_url {
+                                if website::is_unexpected_redirect(&link, redirect_url) && previous_redirect_url.as_ref() != Some(redirect_url) {
+                                    message_queue::send_message_with_persistence(&bot, user_id, &format!("⚠️ Ссылка {link} теперь перенаправляет на другой сайт: {redirect_url}")).await;
+                                }
+                            }
+                        }
+
+                        records.push(HealthCheckRecord {
+                            link,
+                            status_code: Some(health.status_code),
+                            redirect_url: health.redirect_url,
+                            error: None,
+                        });
                     }
                     Err(err) => {
-                        error!("Failed to verify the site for the user: {}. Description: {}", one_link.user_id, err.to_string());
-                        let _ = bot.send_message(user_id, format!("Не удалось проверить сайт по ссылке: {}", one_link.link)).await;
+                        error!("Failed to verify the site: {}. Description: {}", link, err);
+
+                        for user_id in user_ids {
+                            message_queue::send_message_with_persistence(&bot, user_id, &format!("Не удалось проверить сайт по ссылке: {}", link)).await;
+                        }
+
+                        records.push(HealthCheckRecord {
+                            link,
+                            status_code: None,
+                            redirect_url: None,
+                            error: Some(err.to_string()),
+                        });
+                    }
+                }
+
+                if let Some(current_check) = records.last() {
+                    for reminder in link_reminders::get_fired_reminders(previous_check.as_ref(), current_check) {
+                        let status = match current_check.status_code {
+                            Some(status_code) => format!("код ответа {status_code}"),
+                            None => "сайт не отвечает".to_string()
+                        };
+
+                        message_queue::send_message_with_persistence(&bot, reminder.user_id, &format!("🔔 Напоминание: изменился результат проверки ссылки {} ({status})", reminder.link)).await;
+                        link_reminders::mark_reminded(reminder.id);
                     }
                 }
             }
+
+            match link_health::batch_record_health_checks(&records) {
+                Ok(saved) => info!("Saved the results of {} site checks", saved),
+                Err(err) => error!("Failed to save the results of the site checks: {}", err)
+            }
         }
     });
 }
@@ -137,7 +484,7 @@ async fn handle_status_code(bot: &Bot, user_id: u64, link: String, status_code:
             text = format!("{text}\n\nКод ошибки: {status_code}\nБот не может получить доступ");
         }
         404 => {
-            text = format!("{text}\n\nКод ошибки: {status_code}\nЭтой страницы не существует");
+            text = format!("{text}\n\nКод ошибки: {status_code}\nЭтой страницы не существует. Если страница была удалена навсегда, вы можете удалить ссылку через меню");
         }
         500 => {
             text = format!("{text}\n\nКод ошибки: {status_code}\nВнутренняя ошибка сервера");
@@ -146,14 +493,15 @@ async fn handle_status_code(bot: &Bot, user_id: u64, link: String, status_code:
             text = format!("{text}\n\nКод ошибки: {status_code}\nСервис недоступен");
         }
 
-        200..=299 | 300..=399 => { return Ok(()); }
+        200..=399 => { return Ok(()); }
         _ => {
             text = format!("{text}\n\nКод ошибки: {status_code}");
         }
     }
 
-    bot.send_sticker(UserId(user_id), InputFile::file_id(STICKER_ERROR_ID)).await?;
-    bot.send_message(UserId(user_id), text).await?;
+    // The sticker is only decoration, so only the text of the message is guaranteed to be delivered
+    let _ = bot.send_sticker(UserId(user_id), InputFile::file_id(STICKER_ERROR_ID)).await;
+    message_queue::send_message_with_persistence(bot, user_id, &text).await;
 
     Ok(())
 }
@@ -188,33 +536,106 @@ async fn handle_status_code(bot: &Bot, user_id: u64, link: String, status_code:
 ///
 /// where `Dispatcher` and `bot` are previously defined according to your program needs.
 fn schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let admin_command_handler = teloxide::filter_command::<AdminCommand, _>()
+        .filter(|msg: Message| msg.from().map(|user| is_admin(user.id)).unwrap_or(false))
+        .branch(case![AdminCommand::DedupCandidates { min_copies }].endpoint(show_deduplication_candidates))
+        .branch(case![AdminCommand::Dedup].endpoint(deduplicate_links))
+        .branch(case![AdminCommand::Similar { link }].endpoint(show_similar_links))
+        .branch(case![AdminCommand::AddRule { rule }].endpoint(add_custom_rule))
+        .branch(case![AdminCommand::Rules].endpoint(show_custom_rules))
+        .branch(case![AdminCommand::DeleteRule { id }].endpoint(delete_custom_rule))
+        .branch(case![AdminCommand::Iocs { link }].endpoint(show_iocs))
+        .branch(case![AdminCommand::Incident { request }].endpoint(send_incident_report))
+        .branch(case![AdminCommand::Inactive { request }].endpoint(show_inactive_users))
+        .branch(case![AdminCommand::UserData { request }].endpoint(send_user_data_to_admin))
+        .branch(case![AdminCommand::SetRescanInterval { request }].endpoint(set_rescan_interval))
+        .branch(case![AdminCommand::Vacuum].endpoint(vacuum_database));
+
     let command_handler = teloxide::filter_command::<SparkleCommand, _>()
         .branch(case![BotState::Default]
             .branch(case![SparkleCommand::Start].endpoint(start))
             .branch(case![SparkleCommand::Menu].endpoint(show_actions))
             .branch(case![SparkleCommand::Help].endpoint(help))
             .branch(case![SparkleCommand::AddLink { link }].endpoint(add_link))
-            .branch(case![SparkleCommand::CheckSite { link }].endpoint(check_site_command)))
+            .branch(case![SparkleCommand::CheckSite { link }].endpoint(check_site_command))
+            .branch(case![SparkleCommand::Family { link }].endpoint(show_domain_family))
+            .branch(case![SparkleCommand::DomainTrend { link }].endpoint(show_domain_trend))
+            .branch(case![SparkleCommand::Import].endpoint(start_import))
+            .branch(case![SparkleCommand::Privacy { mode }].endpoint(set_privacy_mode))
+            .branch(case![SparkleCommand::SetPageSize { size }].endpoint(set_page_size))
+            .branch(case![SparkleCommand::Leaderboard].endpoint(show_leaderboard))
+            .branch(case![SparkleCommand::Timeline].endpoint(show_timeline))
+            .branch(case![SparkleCommand::MyData].endpoint(send_user_data))
+            .branch(case![SparkleCommand::MyStats].endpoint(show_user_statistics))
+            .branch(case![SparkleCommand::Activity].endpoint(show_user_activity_trend))
+            .branch(case![SparkleCommand::Remind { reminder }].endpoint(add_link_reminder))
+            .branch(case![SparkleCommand::ShareWith { username }].endpoint(request_report_sharing))
+            .branch(case![SparkleCommand::Shared].endpoint(show_shared_links))
+            .branch(case![SparkleCommand::StartSession].endpoint(start_scan_session))
+            .branch(case![SparkleCommand::Submit { link }].endpoint(submit_session_link))
+            .branch(case![SparkleCommand::EndSession].endpoint(end_scan_session)))
         .branch(case![BotState::ReceiveLink]
             .branch(case![SparkleCommand::Cancel].endpoint(cancel_receive_link)))
         .branch(case![BotState::DeletingSomeLinks]
             .branch(case![SparkleCommand::Cancel].endpoint(cancel_deleting_some_links)))
         .branch(case![BotState::ReceiveLinkForChecking]
+            .branch(case![SparkleCommand::Cancel].endpoint(cancel_receive_link)))
+        .branch(case![BotState::ImportChooseFormat]
+            .branch(case![SparkleCommand::Cancel].endpoint(cancel_import)))
+        .branch(case![BotState::ImportReceiveFile { format }]
+            .branch(case![SparkleCommand::Cancel].endpoint(cancel_import)))
+        .branch(case![BotState::ImportConfirm { links }]
+            .branch(case![SparkleCommand::Cancel].endpoint(cancel_import)))
+        .branch(case![BotState::ConfirmAddLink { link }]
             .branch(case![SparkleCommand::Cancel].endpoint(cancel_receive_link)));
 
     let message_handler = Update::filter_message()
+        .inspect(track_user_activity)
+        .branch(admin_command_handler)
         .branch(command_handler)
         .branch(case![BotState::ReceiveLink].endpoint(receive_link))
         .branch(case![BotState::DeletingSomeLinks].endpoint(delete_some_links))
-        .branch(case![BotState::ReceiveLinkForChecking].endpoint(check_site));
+        .branch(case![BotState::ReceiveLinkForChecking].endpoint(check_site))
+        .branch(case![BotState::ImportReceiveFile { format }].endpoint(receive_import_file));
 
+    // Requests to share the reports come from other users, so they are answered in any state of the dialogue
     let callback_query_handler = Update::filter_callback_query()
+        .branch(dptree::filter(|q: CallbackQuery| is_share_request_answer(&q)).endpoint(share_request_callback_handler))
         .branch(case![BotState::Default].endpoint(menu_choice_callback_handler))
-        .branch(case![BotState::ReceiveConfirmRemoveLinks].endpoint(menu_confirm_remove_links_callback_handler));
+        .branch(case![BotState::ReceiveConfirmRemoveLinks].endpoint(menu_confirm_remove_links_callback_handler))
+        .branch(case![BotState::DeletingSomeLinks].endpoint(deleting_links_page_callback_handler))
+        .branch(case![BotState::ImportChooseFormat].endpoint(receive_import_format))
+        .branch(case![BotState::ImportConfirm { links }].endpoint(import_confirm_callback_handler))
+        .branch(case![BotState::ConfirmAddLink { link }].endpoint(add_link_confirm_callback_handler));
+
+    // Errors of all handlers are reported to the administrator
+    error_reporter::report_handler_errors()
+        .chain(dialogue::enter::<Update, DialogueStorage<BotState>, BotState, _>()
+            .branch(message_handler)
+            .branch(callback_query_handler))
+}
 
-    dialogue::enter::<Update, InMemStorage<BotState>, BotState, _>()
-        .branch(message_handler)
-        .branch(callback_query_handler)
+/// Saves the time of the user's message and the command it contains, so inactive users can be found
+///
+/// # Arguments
+///
+/// * `msg`: Message sent by the user
+fn track_user_activity(msg: Message) {
+    let Some(user) = msg.from() else {
+        return;
+    };
+
+    let last_command = match msg.text() {
+        Some(text) if text.starts_with('/') => text.split_whitespace().next().unwrap_or(text).to_string(),
+        _ => "message".to_string()
+    };
+
+    user_activity::record_user_activity(user.id.0, UserActivity {
+        user_id: user.id.0,
+        last_seen: chrono::Utc::now(),
+        last_command,
+    });
+    user_activity::record_username(user.id.0, user.username.as_deref());
 }
 
 /// Displays a welcome message to the user
@@ -254,37 +675,204 @@ async fn help(bot: Bot, msg: Message) -> HandlerResult {
 ///
 /// # Arguments:
 /// - `bot`: The Telegram bot instance.
+/// - `dialogue`: A handle for controlling dialogue state.
 /// - `msg`: The received message.
 /// - `link`: The link to be added.
 ///
 /// Returns:
 /// The result of the operation.
-async fn add_link(bot: Bot, msg: Message, link: String) -> HandlerResult {
+async fn add_link(bot: Bot, dialogue: SparkleDialogue, msg: Message, link: String) -> HandlerResult {
     let user_id = msg.from().expect("Unable to determine user ID").id;
+
     let mut url = link;
 
-    if !website::has_http_or_https(&url) {
-        url = format!("https://{}", url);
+    if let Some(reply) = get_unsupported_input_reply(&url) {
+        bot.send_message(msg.chat.id, reply).await?;
+        return Ok(());
     }
 
+    url = url_normalizer::normalize_url(&url);
+
     if is_url(&url) {
-        if database::is_link_exists(user_id.0, &url) {
-            bot.send_message(msg.chat.id, "Данная ссылка уже была добавлена. Пожалуйста, введите другую").await?;
+        save_link(&bot, &dialogue, msg.chat.id, user_id, url, false).await?;
+    }
+    else {
+        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+    }
 
-            return Ok(());
+    Ok(())
+}
+
+/// Returns the reply for text that is an indicator the bot cannot check instead of a link
+///
+/// URLs, domains and IP addresses are checked as links, while emails and file hashes would be
+/// saved as links to non-existent sites otherwise.
+///
+/// # Arguments
+///
+/// * `input`: Text sent by the user
+///
+/// returns: The reply for the user, or `None` if the text may be a link
+fn get_unsupported_input_reply(input: &str) -> Option<&'static str> {
+    match input_type::detect_input_type(input) {
+        InputType::Email(_) => Some("Это адрес электронной почты, а не ссылка. Бот проверяет только сайты"),
+        InputType::FileHashMd5(_) | InputType::FileHashSha256(_) => Some("Это хэш файла, а не ссылка. Бот проверяет только сайты"),
+        _ => None
+    }
+}
+
+/// Saves the link sent by the user to the database and informs the user about the result
+///
+/// Short links are resolved first, then the link is checked against the custom rules:
+/// blocked links are not saved, and flagged links are saved with a warning.
+///
+/// If the user already has the same link with other tracking parameters, or has deleted the link within
+/// the last `DEDUP_WINDOW_HOURS` hours, the link is not saved until the user confirms adding it.
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `chat_id`: Chat with the user
+/// * `user_id`: User ID in Telegram
+/// * `url`: Valid URL to save
+/// * `is_confirmed`: The user has confirmed adding the link
+///
+/// returns: `true` if the link was saved, `false` if it was rejected or has to be confirmed
+async fn save_link(bot: &Bot, dialogue: &SparkleDialogue, chat_id: ChatId, user_id: UserId, mut url: String, is_confirmed: bool) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let mut redirect_chain: Vec<String> = Vec::new();
+
+    if website::is_shortened_url(&url) {
+        if let Ok(resolved_url) = website::resolve_url(&url, website::MAX_REDIRECT_HOPS).await {
+            info!("The short link {} leads to {} through {} redirects", resolved_url.original, resolved_url.final_url, resolved_url.hop_count);
+
+            url = resolved_url.final_url;
+            redirect_chain = resolved_url.chain;
         }
+    }
 
-        database::add_link(user_id.0, &url);
+    let rule_result = custom_scan_rules::apply_custom_rules(&url);
 
-        info!("Added a new link to the database from the user: {}", user_id);
+    if let Some(rule_result) = &rule_result {
+        if rule_result.action == RuleAction::Block {
+            info!("The link from the user {} was blocked by the custom rule {}", user_id, rule_result.rule_id);
+            bot.send_message(chat_id, format!("⛔ Эту ссылку нельзя добавить: {}", rule_result.reason)).await?;
 
-        bot.send_message(msg.chat.id, "Спасибо за ссылку! Теперь я буду проверять эту ссылку каждый час").await?;
+            return Ok(false);
+        }
     }
-    else {
-        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+
+    if database::is_link_exists(user_id.0, &url) {
+        bot.send_message(chat_id, "Данная ссылка уже была добавлена. Пожалуйста, введите другую").await?;
+
+        return Ok(false);
     }
 
-    Ok(())
+    if !is_confirmed {
+        let question = if let Some(duplicate) = database::find_canonical_duplicate(user_id.0, &url) {
+            Some(format!("У вас уже есть похожая ссылка: {duplicate}\n\nОни отличаются только параметрами отслеживания и ведут на {}\n\nВсе равно добавить {url}?",
+                url_normalizer::compute_canonical_url(&url)))
+        }
+        else {
+            database::get_recent_deletion(user_id.0, &url, database::get_dedup_window_hours())
+                .map(|deleted_at| format!("Вы удалили эту ссылку {}. Добавить ее снова?", deleted_at.format("%d.%m.%Y %H:%M")))
+        };
+
+        if let Some(question) = question {
+            let keyboard = create_add_link_confirmation_keyboard().await;
+
+            bot.send_message(chat_id, question).reply_markup(keyboard).await?;
+            dialogue.update(BotState::ConfirmAddLink { link: url }).await?;
+
+            return Ok(false);
+        }
+    }
+
+    database::add_link(user_id.0, &url);
+
+    if user_preferences::get_user_preferences(user_id.0).privacy_mode {
+        // A hashed link cannot be opened, so there is nothing to check or collect about it
+        info!("Added a new hashed link to the database from the user: {}", user_id);
+        bot.send_message(chat_id, "Спасибо за ссылку! Она сохранена в виде хэша SHA-256, так как у вас включен режим приватности. Такие ссылки не проверяются").await?;
+
+        return Ok(true);
+    }
+
+    let mut redirector_warning: Option<String> = None;
+
+    if !redirect_chain.is_empty() {
+        database::set_redirect_chain(user_id.0, &url, &serde_json::to_string(&redirect_chain).unwrap());
+        redirector_warning = flag_malicious_redirectors(&url, &redirect_chain);
+    }
+
+    let link = url.clone();
+    tokio::spawn(async move {
+        link_enrichment::enrich_link(user_id.0, &link, &EnrichmentConfig::from_env()).await;
+    });
+
+    info!("Added a new link to the database from the user: {}", user_id);
+
+    bot.send_message(chat_id, "Спасибо за ссылку! Теперь я буду проверять эту ссылку каждый час").await?;
+
+    if let Some(rule_result) = rule_result {
+        if rule_result.action == RuleAction::Flag {
+            link_flags::flag_link(&url, "custom_rule", &rule_result.reason);
+            tokio::spawn(update_iocs(url.clone()));
+            bot.send_message(chat_id, format!("⚠️ Будьте осторожны с этой ссылкой: {}", rule_result.reason)).await?;
+        }
+    }
+
+    if let Some(redirector_warning) = redirector_warning {
+        tokio::spawn(update_iocs(url.clone()));
+        bot.send_message(chat_id, format!("⚠️ {redirector_warning}")).await?;
+    }
+
+    Ok(true)
+}
+
+/// Flags the link if its redirect chain passes through a redirector blocked or flagged by a custom rule
+///
+/// # Arguments
+///
+/// * `link`: Final URL of the chain
+/// * `redirect_chain`: All URLs from the short link to the final URL
+///
+/// returns: The warning for the user if the link was flagged
+fn flag_malicious_redirectors(link: &str, redirect_chain: &[String]) -> Option<String> {
+    // The final URL is checked by the custom rules on its own
+    for redirector in &redirect_chain[..redirect_chain.len() - 1] {
+        let rule_result = custom_scan_rules::apply_custom_rules(redirector);
+
+        if let Some(rule_result) = rule_result.filter(|rule_result| rule_result.action != RuleAction::Allow) {
+            let reason = format!("Ссылка проходит через подозрительный редирект {redirector}: {}", rule_result.reason);
+
+            info!("The link {} passes through the malicious redirector {}", link, redirector);
+            link_flags::flag_link(link, "malicious_redirector", &reason);
+
+            return Some(reason);
+        }
+    }
+
+    None
+}
+
+/// Downloads the page of a flagged link and saves the indicators of compromise found on it
+/// to the database
+///
+/// # Arguments
+///
+/// * `link`: Link to the page
+async fn update_iocs(link: String) {
+    match website::fetch_page_html(&link).await {
+        Ok(Some(html)) => {
+            let iocs = iocs::extract_iocs(&html);
+
+            info!("Extracted {} indicators of compromise from the page: {}", iocs.len(), link);
+            iocs::store_iocs(&link, &iocs);
+        }
+        Ok(None) => (),
+        Err(err) => warn!("Failed to extract indicators of compromise from the page: {}. Description: {}", link, err)
+    }
 }
 
 /// Asynchronously checks the given site link and sends site information to the user.
@@ -318,21 +906,32 @@ async fn add_link(bot: Bot, msg: Message, link: String) -> HandlerResult {
 async fn check_site_command(bot: Bot, msg: Message, link: String) -> HandlerResult {
     let mut url = link;
 
-    if !website::has_http_or_https(&url) {
-        url = format!("https://{}", url);
+    if let Some(reply) = get_unsupported_input_reply(&url) {
+        bot.send_message(msg.chat.id, reply).await?;
+        return Ok(());
     }
 
+    url = url_normalizer::normalize_url(&url);
+
     if is_url(&url) {
         let send_message = bot.send_message(msg.chat.id, "Пожалуйста, подождите...\nМаксимальное время ответа - 15 секунд").await?;
 
         info!("Site information for the user is requested: {}", msg.from().expect("Unable to determine user ID").id.0);
 
-        let site_information = website::get_site_information(&url).await;
+        let site_information = site_analysis_cooldown::get_site_information(&url).await;
+
+        EVENT_BUS.emit(BotEvent::ScanCompleted {
+            user_id: msg.from().expect("Unable to determine user ID").id.0,
+            link: url.clone(),
+            status_code: site_information.as_ref().ok().map(|information| information.status_code)
+        });
 
         match site_information {
             Ok(result) => {
-                let text = compile_site_information(result);
-                bot.edit_message_text(msg.chat.id, send_message.id, text).await?;
+                let text = compile_site_information(&url, &result);
+                let keyboard = create_scan_result_keyboard(&url).await;
+
+                bot.edit_message_text(msg.chat.id, send_message.id, text).reply_markup(keyboard).await?;
             }
             Err(e) => {
                 if e.is_timeout() {
@@ -351,408 +950,1267 @@ async fn check_site_command(bot: Bot, msg: Message, link: String) -> HandlerResu
     Ok(())
 }
 
-/// Handles the callback for menu choice.
+/// Creates the router of the inline keyboard buttons available in the default state
+///
+/// returns: Router that calls the handler of the pressed button
+fn create_callback_router() -> CallbackRouter {
+    CallbackRouter::new()
+        .route("begin", |bot, _, message, q| show_actions(bot, message, q))
+        .route("check_link", start_check_link)
+        .route("get_links", |bot, _, _, q| get_all_links_from_user(bot, q))
+        .route("clear_all_links", |bot, dialogue, _, q| ask_about_clear_links(bot, dialogue, q))
+        .route("delete_some_links", |bot, dialogue, _, q| start_deleting_some_links(bot, dialogue, q))
+        .route("enter_links", start_enter_links)
+        .route("pin_result", |bot, _, message, q| pin_scan_result_callback(bot, message, q))
+        .route("score_explanation:", |bot, _, message, q| explain_reputation_score(bot, message, q))
+        .route("timeline:", |bot, _, message, q| show_timeline_page(bot, message, q))
+        .route("links_page:", |bot, _, message, q| show_links_page(bot, message, q))
+        .route("family_page:", |bot, _, message, q| show_links_page(bot, message, q))
+        .route("shared_page:", |bot, _, message, q| show_links_page(bot, message, q))
+}
+
+/// Handles the callback for menu choice by passing it to the handler of the pressed button.
 ///
 /// # Arguments
 ///
 /// * `bot` - The bot instance.
 /// * `dialogue` - The dialogue instance.
 /// * `q` - The callback query.
+/// * `router` - The router of the buttons.
 ///
 /// # Returns
 ///
 /// A `HandlerResult` indicating the success of the operation.
+async fn menu_choice_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery, router: Arc<CallbackRouter>) -> HandlerResult {
+    router.dispatch(bot, dialogue, q).await
+}
+
+/// Sends the user a sparkline of how the reputation of the domain has changed over the last days
 ///
-/// # Examples
-///
-/// ```rust
-/// use tokio::sync::mpsc;
-///
-/// #[tokio::main]
-/// async fn main() {
-///     let bot = Bot::new();
-///     let dialogue = MyDialogue::new();
-///     let q = CallbackQuery::new();
+/// # Arguments
 ///
-///     let result = menu_choice_callback_handler(bot, dialogue, q).await;
-/// }
-/// ```
-async fn menu_choice_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
-    if let Some(data) = &q.data {
-        if let Some(message) = q.clone().message {
-            match data.as_str() {
-                "begin" => show_actions(bot, message, q).await?,
-                "check_link" => start_check_link(bot, dialogue, message, q).await?,
-                "get_links" => get_all_links_from_user(bot, q).await?,
-                "clear_all_links" => ask_about_clear_links(bot, dialogue, q).await?,
-                "delete_some_links" => start_deleting_some_links(bot, dialogue, q).await?,
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+/// * `link`: Link or domain of the site
+async fn show_domain_trend(bot: Bot, msg: Message, link: String) -> HandlerResult {
+    let Some(domain) = domain_reputation::get_domain(&url_normalizer::normalize_url(&link)) else {
+        bot.send_message(msg.chat.id, "Укажите домен сайта: /domaintrend example.com").await?;
+        return Ok(());
+    };
 
-                "enter_links" => start_enter_links(bot, dialogue, message, q).await?,
+    let history = domain_reputation::get_domain_reputation_history(&domain, DOMAIN_TREND_DAYS);
 
-                _ => (),
-            }
-        }
-    }
+    let (Some((first_date, _)), Some((last_date, last_score))) = (history.first(), history.last()) else {
+        bot.send_message(msg.chat.id, format!("Репутация домена {domain} еще не оценивалась. Проверьте сайт командой /checksite")).await?;
+        return Ok(());
+    };
+
+    let scores = history.iter().map(|(_, score)| *score).collect::<Vec<f32>>();
+
+    let text = format!("📈 Репутация домена {domain} за {DOMAIN_TREND_DAYS} дней:\n\n{}\n{} — {}\n\n⭐ Последняя оценка: {}/100",
+        domain_reputation::render_sparkline(&scores), first_date.format("%d.%m.%Y"), last_date.format("%d.%m.%Y"), (last_score * 100.0).round());
+
+    bot.send_message(msg.chat.id, text).await?;
 
     Ok(())
 }
 
-/// Event handler after the user clicks the button in the ReceiveConfirmRemoveHistories state,
-/// which determines whether to delete the history or not
+/// Sends the user a list of their links that share the registrable domain with the given link
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `dialogue`: A handle for controlling dialogue state
-/// * `q`: Response from the user after pressing the button
-async fn menu_confirm_remove_links_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
-    if let Some(data) = &q.data {
-        if let Some(message) = &q.message {
-            match data.as_str() {
-                "confirm" => clear_links(bot, dialogue, q).await?,
-                "cancel" => {
-                    bot.edit_message_text(message.chat.id, q.message.clone().unwrap().id, "Процесс очистки ссылок отменен.").await?;
-                    dialogue.update(BotState::Default).await?;
+/// * `msg`: Message sent by the user
+/// * `link`: Link or domain of the site
+async fn show_domain_family(bot: Bot, msg: Message, link: String) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
 
-                    show_main_menu(&bot, &message).await?;
+    let url = url_normalizer::normalize_url(&link);
 
-                    bot.answer_callback_query(q.id).await?;
-                },
+    let family = match domain_family::get_domain_family(&url) {
+        Ok(family) => family,
+        Err(err) => {
+            info!("Failed to determine the domain family of {}: {}", url, err);
+            bot.send_message(msg.chat.id, "Не удалось определить домен сайта").await?;
 
-                _ => (),
-            }
+            return Ok(());
         }
+    };
+
+    if database::get_links_by_family(user_id.0, &family).is_empty() {
+        bot.send_message(msg.chat.id, format!("У вас нет ссылок на {family}")).await?;
+        return Ok(());
     }
 
-    Ok(())
+    send_links_page(&bot, msg.chat.id, None, user_id.0, &LinkList::Family(family), 0).await
 }
 
-/// Sends a message to the user that displays the bot's action menu
+/// Turns the privacy mode of the user on or off
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
 /// * `msg`: Message sent by the user
-async fn show_actions(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
-    show_main_menu(&bot, &msg).await?;
-    bot.answer_callback_query(q.id).await?;
+/// * `mode`: `on` or `off`
+async fn set_privacy_mode(bot: Bot, msg: Message, mode: String) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    let text = match mode.trim() {
+        "on" => {
+            user_preferences::set_privacy_mode(user_id.0, true);
+
+            "🔒 Режим приватности включен.\n\n\
+            ⚠️ Новые ссылки будут сохраняться только в виде хэша SHA-256. Ни вы, ни администраторы бота \
+            не смогут прочитать такие ссылки, а бот не сможет их проверять. \
+            Уже добавленные ссылки останутся без изменений"
+        }
+        "off" => {
+            user_preferences::set_privacy_mode(user_id.0, false);
+
+            "🔓 Режим приватности выключен. Новые ссылки будут сохраняться как обычно"
+        }
+        _ => "Укажите режим: /privacy on или /privacy off"
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
 
     Ok(())
 }
 
-/// Sends a message to the user that displays the main menu
+/// Sets how many items are shown on a page of the lists the user is shown
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
 /// * `msg`: Message sent by the user
-async fn show_main_menu(bot: &Bot, msg: &Message) -> HandlerResult {
-    let text = format!("{} к вашим услугам!\nЧто вы хотите сделать?", bot.get_me().await?.first_name);
-    let keyboard = create_main_menu_keyboard().await;
+/// * `size`: Number of items on a page
+async fn set_page_size(bot: Bot, msg: Message, size: String) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
 
-    bot.send_message(msg.chat.id, text).reply_markup(keyboard).await?;
+    let Some(size) = size.trim().parse::<u8>().ok().filter(|size| (1..=user_preferences::MAX_LINKS_PER_PAGE).contains(size)) else {
+        bot.send_message(msg.chat.id, format!("Укажите число от 1 до {}: /setpagesize 10", user_preferences::MAX_LINKS_PER_PAGE)).await?;
+        return Ok(());
+    };
+
+    user_preferences::set_links_per_page(user_id.0, size);
+
+    bot.send_message(msg.chat.id, format!("Теперь на одной странице будет до {size} записей")).await?;
 
     Ok(())
 }
 
-/// Sends a message to the user asking for confirmation to clear the history
+/// Sends the user the leaderboard of the users who have added the most links with warnings
+///
+/// Users who have turned on the privacy mode are shown without their names.
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `dialogue`: A handle for controlling dialogue state
 /// * `msg`: Message sent by the user
-async fn ask_about_clear_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
-    let user_id = q.from.id;
-    let histories = database::get_all_links_from_user(user_id.0, None);
+async fn show_leaderboard(bot: Bot, msg: Message) -> HandlerResult {
+    let hunters = link_flags::get_top_threat_hunters(LEADERBOARD_SIZE);
 
-    if histories.iter().count() == 0 {
-        bot.send_message(user_id, "У вас нет ссылок для удаления").await?;
+    if hunters.is_empty() {
+        bot.send_message(msg.chat.id, "Пока никто не нашел опасных ссылок").await?;
         return Ok(());
     }
 
-    let text = "❓ Вы действительно хотите очистить все сохраненные вами ссылки? ❓";
-    let keyboard = create_confirmation_menu_keyboard().await;
+    let mut text = "🏆 Охотники за угрозами:\n".to_string();
 
-    bot.send_message(q.chat_id().unwrap(), text).reply_markup(keyboard).await?;
+    for (place, (user_id, flagged_links)) in hunters.into_iter().enumerate() {
+        let name = if user_preferences::get_user_preferences(user_id).privacy_mode {
+            None
+        }
+        else {
+            // The name is not stored by the bot, so it is requested from Telegram
+            bot.get_chat(ChatId(user_id as i64)).await.ok().and_then(|chat| chat.first_name().map(|name| name.to_string()))
+        };
 
-    dialogue.update(BotState::ReceiveConfirmRemoveLinks).await?;
+        text = format!("{text}\n{}. {} — {flagged_links}", place + 1, name.unwrap_or("Аноним".to_string()));
+    }
 
-    bot.answer_callback_query(q.id).await?;
+    bot.send_message(msg.chat.id, text).await?;
 
     Ok(())
 }
 
-/// Sends a message to the user telling them to enter a link and also goes to the ReceiveLink state
+/// Sends the user the first page of the history of their links
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `dialogue`: A handle for controlling dialogue state
 /// * `msg`: Message sent by the user
-async fn start_enter_links(bot: Bot, dialogue: SparkleDialogue, msg: Message, q: CallbackQuery) -> HandlerResult {
-    bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку. Для отмены ввода ссылки введите команду /cancel").await?;
-    dialogue.update(BotState::ReceiveLink).await?;
-
-    bot.answer_callback_query(q.id).await?;
+async fn show_timeline(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
 
-    Ok(())
+    send_timeline_page(&bot, msg.chat.id, None, user_id.0, 0).await
 }
 
-/// Sends a message to the user that a link needs to be entered, and enters the
-/// ReceiveLinkForChecking state
+/// Shows the user another page of the history of their links when the button is pressed
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `dialogue`: A handle for controlling dialogue state
-/// * `msg`: Message sent by the user
-async fn start_check_link(bot: Bot, dialogue: SparkleDialogue, msg: Message, q: CallbackQuery) -> HandlerResult {
-    bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку. Для отмены ввода ссылки введите команду /cancel").await?;
-    dialogue.update(BotState::ReceiveLinkForChecking).await?;
-
+/// * `msg`: Message with the current page
+/// * `q`: Callback query with the number of the page
+async fn show_timeline_page(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
     bot.answer_callback_query(q.id).await?;
 
-    Ok(())
+    let Some(page) = q.data.as_deref().and_then(|data| data.strip_prefix("timeline:")).and_then(|page| page.parse::<u32>().ok()) else {
+        return Ok(());
+    };
+
+    send_timeline_page(&bot, msg.chat.id, Some(msg.id), q.from.id.0, page).await
 }
 
-/// Receives a link from a user, validates it, and adds it to the database if it is a valid URL.
-///
-/// # Arguments
-///
-/// * `bot` - A `Bot` object representing the Telegram bot.
-/// * `dialogue` - A `MyDialogue` object for handling the conversation flow.
-/// * `msg` - A `Message` object representing the received message.
-///
-/// # Returns
+/// Sends a page of the history of the user's links with buttons for the other pages
 ///
-/// A `HandlerResult`, indicating the success or failure of the operation.
+/// The number of events on a page is chosen by the user with `/setpagesize`.
 ///
-/// # Examples
+/// # Arguments
 ///
-/// ```
-/// # use my_bot::{Bot, MyDialogue, Message, HandlerResult};
-/// # use my_bot::website;
-/// # use my_bot::database;
-/// # use log::info;
-/// # use std::error::Error;
-/// async fn receive_link(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
-///     // Code implementation
-///     Ok(())
-/// }
-/// ```
-async fn receive_link(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
-    let user_id = msg.from().expect("Unable to determine user ID").id;
+/// * `bot`: Bot instance
+/// * `chat_id`: Chat to send the page to
+/// * `message_id`: Message with the current page to replace, or `None` to send a new message
+/// * `user_id`: User whose history is shown
+/// * `page`: Number of the page, starting from 0
+async fn send_timeline_page(bot: &Bot, chat_id: ChatId, message_id: Option<MessageId>, user_id: u64, page: u32) -> HandlerResult {
+    let page_size = user_preferences::get_user_preferences(user_id).links_per_page as u32;
+    let total_pages = user_timeline::count_user_timeline_events(user_id).div_ceil(page_size);
+    let events = user_timeline::get_user_timeline(user_id, page, page_size);
+
+    if events.is_empty() {
+        bot.send_message(chat_id, "В истории больше нет событий").await?;
+        return Ok(());
+    }
 
-    let mut url: String;
+    let mut text = "🕓 История ваших ссылок:\n".to_string();
 
-    match msg.text() {
-        Some(text) => {
-            url = text.to_string();
+    for event in &events {
+        let line = match event {
+            TimelineEvent::LinkAdded { link, timestamp } => format!("{} ➕ Добавлена {link}", timestamp.format("%d.%m.%Y %H:%M")),
+            TimelineEvent::ScanCompleted { link, result, timestamp } => format!("{} 🔎 Проверена {link}: {result}", timestamp.format("%d.%m.%Y %H:%M")),
+            TimelineEvent::LinkDeleted { link, timestamp } => format!("{} ➖ Удалена {link}", timestamp.format("%d.%m.%Y %H:%M"))
+        };
+
+        text = format!("{text}\n{line}");
+    }
+
+    let keyboard = create_page_keyboard(page, total_pages.max(1), |page| format!("timeline:{page}"));
+
+    send_or_edit_page(bot, chat_id, message_id, text, keyboard).await
+}
+
+/// Sends a page of a list, or replaces the message with the previous page
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `chat_id`: Chat to send the page to
+/// * `message_id`: Message with the current page to replace, or `None` to send a new message
+/// * `text`: Text of the page
+/// * `keyboard`: Buttons for the other pages
+async fn send_or_edit_page(bot: &Bot, chat_id: ChatId, message_id: Option<MessageId>, text: String, keyboard: InlineKeyboardMarkup) -> HandlerResult {
+    match message_id {
+        Some(message_id) => {
+            let result = bot.edit_message_text(chat_id, message_id, text).reply_markup(keyboard).await;
+
+            // Pressing the button of the current page does not change the message
+            if let Err(err) = result {
+                if !matches!(err, RequestError::Api(ApiError::MessageNotModified)) {
+                    return Err(err.into());
+                }
+            }
         }
         None => {
-            bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку.").await?;
-            return Ok(());
+            bot.send_message(chat_id, text).reply_markup(keyboard).await?;
         }
     }
 
-    if !website::has_http_or_https(&url) {
-        url = format!("https://{}", url);
-    }
+    Ok(())
+}
 
-    if is_url(&url) {
-        if database::is_link_exists(user_id.0, &url) {
-            bot.send_message(msg.chat.id, "Данная ссылка уже была добавлена. Пожалуйста, введите другую").await?;
+/// Creates the keyboard for switching the pages of a list.
+///
+/// Buttons whose callback data would be longer than Telegram allows are left out.
+///
+/// # Arguments
+///
+/// * `page`: Number of the current page, starting from 0
+/// * `total_pages`: Number of pages
+/// * `callback_data`: Creates the callback data of the button that shows the page
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with the buttons for the previous and the next page, and the number of the current page.
+fn create_page_keyboard(page: u32, total_pages: u32, callback_data: impl Fn(u32) -> String) -> InlineKeyboardMarkup {
+    let mut buttons: Vec<(String, u32)> = vec![];
 
-            return Ok(());
-        }
+    if page > 0 {
+        buttons.push(("⬅️ Назад".to_string(), page - 1));
+    }
 
-        database::add_link(user_id.0, &url);
+    buttons.push((format!("Страница {}/{}", page + 1, total_pages), page));
 
-        info!("Added a new link to the database from the user: {}", user_id);
+    if page + 1 < total_pages {
+        buttons.push(("Вперед ➡️".to_string(), page + 1));
+    }
 
-        bot.send_message(msg.chat.id, "Спасибо за ссылку! Теперь я буду проверять эту ссылку каждый час").await?;
+    let row: Vec<InlineKeyboardButton> = buttons.into_iter()
+        .map(|(text, page)| (text, callback_data(page)))
+        .filter(|(_, data)| data.len() <= MAX_CALLBACK_DATA_LENGTH)
+        .map(|(text, data)| InlineKeyboardButton::callback(text, data))
+        .collect();
 
-        dialogue.update(BotState::Default).await?;
+    InlineKeyboardMarkup::new([row])
+}
 
-        show_main_menu(&bot, &msg).await?;
+/// Sends the user the statistics of their links
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn show_user_statistics(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+    let stats = user_statistics::get_user_stats_detailed(user_id.0);
+
+    if stats.total_links == 0 {
+        bot.send_message(msg.chat.id, "У вас пока нет ссылок").await?;
+        return Ok(());
     }
-    else {
-        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+
+    let mut text = "📊 Статистика ваших ссылок\n\n".to_string();
+
+    text = format!("{text}🔗 Всего ссылок: {}\n", stats.total_links);
+    text = format!("{text}⚠️ Опасных: {}\n", stats.malicious_found);
+    text = format!("{text}✅ Без предупреждений: {}\n", stats.clean_links);
+    text = format!("{text}📟 Проверок: {}\n", stats.total_scans_triggered);
+    text = format!("{text}⭐ Средняя репутация доменов: {}/100\n", (stats.average_safety_score * 100.0).round());
+    text = format!("{text}🗓 Самой старой ссылке: {} дн.\n", stats.oldest_link_age_days);
+
+    if !stats.most_active_day.is_empty() {
+        text = format!("{text}📅 Чаще всего вы добавляете ссылки в {}\n", stats.most_active_day);
     }
 
+    bot.send_message(msg.chat.id, text).await?;
+
     Ok(())
 }
 
-/// Checks the site received from the user and sends the information to the user
+/// Sends the user the number of links they added on each of the last days
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `dialogue`: A handle for controlling dialogue state
 /// * `msg`: Message sent by the user
-async fn check_site(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
-    let mut url: String;
+async fn show_user_activity_trend(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
 
-    match msg.text() {
-        Some(text) => {
-            url = text.to_string();
-        }
-        None => {
-            bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку.").await?;
-            return Ok(());
-        }
-    }
+    let mut text = format!("📅 Активность за последние {ACTIVITY_DAYS} дней:");
 
-    if !website::has_http_or_https(&url) {
-        url = format!("https://{}", url);
+    for (date, count) in user_statistics::get_user_submission_trend(user_id.0, ACTIVITY_DAYS) {
+        text = format!("{text} {}:{count}", SHORT_WEEKDAYS[date.weekday().num_days_from_monday() as usize]);
     }
 
-    if is_url(&url) {
-        let send_message = bot.send_message(msg.chat.id, "Пожалуйста, подождите...\nМаксимальное время ответа - 15 секунд").await?;
+    bot.send_message(msg.chat.id, text).await?;
 
-        info!("Site information for the user is requested: {}", msg.from().expect("Unable to determine user ID").id.0);
+    Ok(())
+}
 
-        let site_information = website::get_site_information(&url).await;
+/// Schedules a reminder that is sent once, when the check result of the user's link changes
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+/// * `reminder`: Link and the change to wait for, `any_change` by default
+async fn add_link_reminder(bot: Bot, msg: Message, reminder: String) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
 
-        match site_information {
-            Ok(result) => {
-                let text = compile_site_information(result);
-                bot.edit_message_text(msg.chat.id, send_message.id, text).await?;
-            }
-            Err(e) => {
-                if e.is_timeout() {
-                    bot.edit_message_text(msg.chat.id, send_message.id, "Сайт слишком долго отвечал").await?;
-                }
-                else {
-                    bot.edit_message_text(msg.chat.id, send_message.id, "Боту не удалось проверить сайт").await?;
-                }
-            }
-        }
+    let mut parts = reminder.split_whitespace();
 
-        dialogue.update(BotState::Default).await?;
+    let link = parts.next();
+    let trigger = parts.next().map_or(Some(ReminderTrigger::AnyChange), ReminderTrigger::parse);
 
-        show_main_menu(&bot, &msg).await?;
-    }
-    else {
-        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+    let (Some(link), Some(trigger)) = (link, trigger) else {
+        bot.send_message(msg.chat.id, "Формат команды: /remind <ссылка> [any_change|available|unavailable]").await?;
+        return Ok(());
+    };
+
+    let url = url_normalizer::normalize_url(link);
+
+    if !database::is_link_exists(user_id.0, &url) {
+        bot.send_message(msg.chat.id, "Этой ссылки нет в вашем списке. Сначала добавьте ее").await?;
+        return Ok(());
     }
 
+    link_reminders::add_reminder(user_id.0, &url, trigger);
+
+    let text = match trigger {
+        ReminderTrigger::AnyChange => format!("Хорошо! Я напомню, когда изменится результат проверки ссылки: {url}"),
+        ReminderTrigger::Available => format!("Хорошо! Я напомню, когда сайт снова станет доступен: {url}"),
+        ReminderTrigger::Unavailable => format!("Хорошо! Я напомню, когда сайт станет недоступен: {url}")
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
+
     Ok(())
 }
 
-/// Compiles the site information into a formatted string.
+/// Sends the user with the given username a request to share the reports of the link checks with each other
 ///
 /// # Arguments
 ///
-/// * `site_information` - The site information to compile.
-///
-/// # Returns
-///
-/// A string containing the compiled site information.
-///
-/// # Examples
-///
-/// ```
-/// use crate::SiteInformation;
-///
-/// let info = SiteInformation {
-///     status_code: 200,
-///     duration: 100,
-///     has_robots: 200,
-///     has_sitemap: 200,
-///     certificate: None,
-/// };
-///
-/// let result = compile_site_information(info);
-/// ```
-fn compile_site_information(site_information: SiteInformation) -> String {
-    let mut text = format!("❔ Информация о введеном вами сайте ❔\n\n");
-
-    text = format!("{text}📝 Код ответа: {}\n", site_information.status_code);
-    text = format!("{text}🕔 Время ответа: {} милисекунд\n", site_information.duration);
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+/// * `username`: Username of the other user
+async fn request_report_sharing(bot: Bot, msg: Message, username: String) -> HandlerResult {
+    let user = msg.from().expect("Unable to determine user ID");
 
-    match site_information.has_robots {
-        200 => {
-            text = format!("{text}🤖 Наличие robots.txt: есть\n")
-        }
-        _ => {
-            text = format!("{text}🤖 Наличие robots.txt: нет (код ответа: {})\n", site_information.has_robots);
-        }
+    if username.trim().is_empty() {
+        bot.send_message(msg.chat.id, "Укажите имя пользователя: /share_with @username").await?;
+        return Ok(());
     }
-    match site_information.has_sitemap {
-        200 => {
-            text = format!("{text}🗺 Наличие sitemap.xml: есть\n\n")
-        }
-        _ => {
-            text = format!("{text}🗺 Наличие sitemap.xml: нет (код ответа: {})\n\n", site_information.has_sitemap);
-        }
+
+    let Some(partner_id) = user_activity::find_user_by_username(&username) else {
+        bot.send_message(msg.chat.id, "Пользователь не найден. Бот знает только тех, кто уже писал ему").await?;
+        return Ok(());
+    };
+
+    if partner_id == user.id.0 {
+        bot.send_message(msg.chat.id, "Нельзя обмениваться отчетами с самим собой").await?;
+        return Ok(());
     }
 
-    match site_information.certificate {
-        Some(cert) => {
-            text = format!("{text}📄 Сертификат:\
-                \nОбщее название: {}\
-                \nОрганизация: {}\
-                \nСтрана: {}\
-                \nИздатель: {}\
-                \nВремя окончания: {}\n\n", cert.intermediate.common_name,
-                           cert.intermediate.organization,
-                           cert.intermediate.country,
-                           cert.intermediate.issuer,
-                           cert.intermediate.time_to_expiration);
-        }
-        None => {
-            text = format!("{text}📄 Сертификат: не найден\n\n")
+    let name = user.username.as_ref().map_or(user.full_name(), |username| format!("@{username}"));
+    let text = format!("🤝 {name} предлагает обмениваться отчетами о проверке ссылок. \
+        Вы будете видеть опасные ссылки друг друга в /shared");
+
+    // The request is stored before it is sent, so the answer can only come for a request that was actually made
+    report_sharing::add_share_request(user.id.0, partner_id);
+
+    let request = bot.send_message(ChatId(partner_id as i64), text)
+        .reply_markup(create_share_request_keyboard(user.id.0))
+        .await;
+
+    let text = match request {
+        Ok(_) => "Запрос отправлен. Я сообщу, когда на него ответят",
+        Err(_) => {
+            report_sharing::take_share_request(user.id.0, partner_id);
+            "Не удалось отправить запрос: пользователь остановил бота"
         }
-    }
+    };
 
-    text
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
 }
 
-/// Function to cancel receiving a link in a Telegram chat.
+/// Checks whether the button pressed by the user answers a request to share the reports
 ///
 /// # Arguments
 ///
-/// * `bot` - An instance of the `Bot` struct providing methods to interact with the Telegram Bot API.
-/// * `dialogue` - An instance of the `MyDialogue` struct representing the conversation state.
-/// * `msg` - The `Message` struct representing the incoming message in the chat.
-///
-/// # Returns
-///
-/// An `HandlerResult` which is an alias for `Result<(), Error>` indicating the success or failure of the operation.
-///
-/// # Examples
+/// * `q`: Response from the user after pressing the button
+fn is_share_request_answer(q: &CallbackQuery) -> bool {
+    q.data.as_deref().is_some_and(|data| data.starts_with("share_accept:") || data.starts_with("share_decline:"))
+}
+
+/// Answers the request to share the reports, whatever the state of the dialogue with the user is
 ///
-/// ```rust,no_run
-/// use your_crate::{Bot, MyDialogue, Message, HandlerResult};
-/// use tokio::runtime::Runtime;
+/// # Arguments
 ///
-/// let bot = Bot::new("your_bot_token");
-/// let dialogue = MyDialogue::create();
-/// let msg = Message::new("your_chat_id", "your_message");
+/// * `bot`: Bot instance
+/// * `q`: Response from the user after pressing the button
+async fn share_request_callback_handler(bot: Bot, q: CallbackQuery) -> HandlerResult {
+    let Some(message) = q.message.clone() else {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    };
+
+    answer_share_request(bot, message, q).await
+}
+
+/// Saves or declines the request to share the reports, and lets the user who sent it know the answer
 ///
-/// let mut rt = Runtime::new().unwrap();
+/// Only the requests stored by `/share_with` for this user are answered, each of them once.
 ///
-/// let result = rt.block_on(cancel_receive_link(bot, dialogue, msg));
+/// # Arguments
 ///
-/// assert!(result.is_ok());
-/// ```
-async fn cancel_receive_link(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
-    bot.send_message(msg.chat.id, "Вы отменили ввод ссылки").await?;
+/// * `bot`: Bot instance
+/// * `msg`: Message with the request
+/// * `q`: Response from the user after pressing the button
+async fn answer_share_request(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
+    bot.answer_callback_query(q.id).await?;
 
-    dialogue.update(BotState::Default).await?;
+    let Some((answer, requester_id)) = q.data.as_deref().and_then(|data| data.split_once(':')) else {
+        return Ok(());
+    };
+
+    let Ok(requester_id) = requester_id.parse::<u64>() else {
+        return Ok(());
+    };
+
+    // The callback data comes from the client, so the requester is only trusted if they really sent a request to this user
+    if !report_sharing::take_share_request(requester_id, q.from.id.0) {
+        bot.edit_message_text(msg.chat.id, msg.id, "Этот запрос уже обработан или не существует").await?;
+        return Ok(());
+    }
+
+    let name = q.from.username.as_ref().map_or(q.from.full_name(), |username| format!("@{username}"));
+
+    let (text, requester_text) = if answer == "share_accept" {
+        report_sharing::add_share_agreement(requester_id, q.from.id.0);
+
+        ("✅ Теперь вы обмениваетесь отчетами. Общие опасные ссылки: /shared".to_string(),
+            format!("✅ {name} принимает ваш запрос. Общие опасные ссылки: /shared"))
+    } else {
+        ("Вы отклонили запрос".to_string(), format!("{name} отклоняет ваш запрос на обмен отчетами"))
+    };
+
+    bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+
+    message_queue::send_message_with_persistence(&bot, requester_id, &requester_text).await;
 
     Ok(())
 }
 
-/// Cancels the process of deleting some links.
+/// Shows the links with warnings of the user and of the users they share the reports with
 ///
 /// # Arguments
 ///
-/// * `bot` - The `Bot` instance to send a message.
-/// * `dialogue` - The `MyDialogue` instance to update the state.
-/// * `msg` - The `Message` object that triggered the cancellation.
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn show_shared_links(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    if report_sharing::get_shared_links(user_id.0).is_empty() {
+        bot.send_message(msg.chat.id, "Опасных ссылок пока нет").await?;
+        return Ok(());
+    }
+
+    send_links_page(&bot, msg.chat.id, None, user_id.0, &LinkList::Shared, 0).await
+}
+
+/// Sends the user a JSON file with all data the bot stores about them
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn send_user_data(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    let export_json = serde_json::to_vec_pretty(&user_data_export::export_all_user_data(user_id.0))?;
+
+    bot.send_document(msg.chat.id, InputFile::memory(export_json).file_name("mydata.json"))
+        .caption("Все ваши данные, которые хранит бот")
+        .await?;
+
+    Ok(())
+}
+
+/// Starts a scan session in the group, during which members submit links to be checked together
+///
+/// Only administrators of the group can start a session.
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn start_scan_session(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        bot.send_message(msg.chat.id, "Совместная проверка доступна только в группах").await?;
+        return Ok(());
+    }
+
+    if !bot.get_chat_member(msg.chat.id, user_id).await?.is_privileged() {
+        bot.send_message(msg.chat.id, "Начать совместную проверку может только администратор группы").await?;
+        return Ok(());
+    }
+
+    let text = match scan_sessions::start_session(msg.chat.id.0, user_id.0) {
+        Some(session_id) => {
+            info!("The scan session {} was started in the group {}", session_id, msg.chat.id);
+            "🔎 Совместная проверка началась! Отправляйте ссылки командой /submit <ссылка>, а когда закончите, введите /endsession"
+        }
+        None => "В группе уже идет совместная проверка. Завершите ее командой /endsession"
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+/// Adds a link to the active scan session of the group
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+/// * `link`: Link to check
+async fn submit_session_link(bot: Bot, msg: Message, link: String) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    let Some(session_id) = scan_sessions::get_active_session(msg.chat.id.0) else {
+        bot.send_message(msg.chat.id, "Совместная проверка не начата. Администратор группы может начать ее командой /startsession").await?;
+        return Ok(());
+    };
+
+    let url = url_normalizer::normalize_url(&link);
+
+    let text = if !is_url(&url) {
+        "Данный текст не является ссылкой!"
+    }
+    else {
+        match scan_sessions::add_session_link(session_id, user_id.0, &url) {
+            SessionLinkStatus::Added => "✅ Ссылка добавлена в совместную проверку",
+            SessionLinkStatus::AlreadySubmitted => "Эта ссылка уже добавлена в совместную проверку",
+            SessionLinkStatus::SessionFull => "В совместную проверку уже добавлено максимальное количество ссылок"
+        }
+    };
+
+    bot.send_message(msg.chat.id, text).reply_to_message_id(msg.id).await?;
+
+    Ok(())
+}
+
+/// Ends the scan session of the group, checks the submitted links and posts the summary
+///
+/// The session can be ended by the administrators of the group. Up to `SESSION_CHECK_CONCURRENCY` links are checked
+/// at the same time, and a long summary is posted in several messages.
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn end_scan_session(bot: Bot, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id;
+
+    let Some(session_id) = scan_sessions::get_active_session(msg.chat.id.0) else {
+        bot.send_message(msg.chat.id, "Совместная проверка не начата").await?;
+        return Ok(());
+    };
+
+    if !bot.get_chat_member(msg.chat.id, user_id).await?.is_privileged() {
+        bot.send_message(msg.chat.id, "Завершить совместную проверку может только администратор группы").await?;
+        return Ok(());
+    }
+
+    let links = scan_sessions::complete_session(session_id);
+
+    info!("The scan session {} was completed with {} links", session_id, links.len());
+
+    if links.is_empty() {
+        bot.send_message(msg.chat.id, "Совместная проверка завершена. Ссылок не было отправлено").await?;
+        return Ok(());
+    }
+
+    bot.send_message(msg.chat.id, format!("Проверяю отправленные ссылки ({})...", links.len())).await?;
+
+    let members = links.iter().map(|session_link| session_link.user_id).collect::<std::collections::HashSet<u64>>().len();
+    let mut text = format!("📋 Итоги совместной проверки\n\nСсылок: {}, участников: {members}\n", links.len());
+
+    let semaphore = Arc::new(Semaphore::new(SESSION_CHECK_CONCURRENCY));
+
+    let checks = links.into_iter()
+        .map(|session_link| {
+            let semaphore = semaphore.clone();
+
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                check_session_link(session_link.link).await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // The results are collected in the order the links were submitted
+    for check in checks {
+        match check.await {
+            Ok(line) => text = format!("{text}\n{line}"),
+            Err(err) => warn!("Failed to check a link of the scan session {}: {}", session_id, err)
+        }
+    }
+
+    for part in message_text::split_message(&text) {
+        bot.send_message(msg.chat.id, part).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks a link submitted to a scan session and returns the line of the summary about it
+///
+/// # Arguments
+///
+/// * `link`: Link to check
+async fn check_session_link(link: String) -> String {
+    let warning = match custom_scan_rules::apply_custom_rules(&link) {
+        Some(rule_result) if rule_result.action != RuleAction::Allow => Some(rule_result.reason),
+        _ => link_flags::get_link_flags(&link).into_iter().next().map(|flag| flag.reason)
+    };
+
+    let result = match site_analysis_cooldown::get_site_information(&link).await {
+        Ok(information) => format!("код ответа {}", information.status_code),
+        Err(err) if err.is_timeout() => "сайт слишком долго отвечал".to_string(),
+        Err(_) => "не удалось проверить".to_string()
+    };
+
+    match warning {
+        Some(warning) => format!("⚠️ {link}: {result}. {warning}"),
+        None => format!("✅ {link}: {result}")
+    }
+}
+
+/// Pins the site analysis result after the user clicks the button under it
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message with the site analysis result
+/// * `q`: Response from the user after pressing the button
+async fn pin_scan_result_callback(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
+    let text = if pin_scan_result(&bot, msg.chat.id, msg.id).await? {
+        "📌 Результат закреплен"
+    }
+    else {
+        "У бота нет прав на закрепление сообщений в этом чате"
+    };
+
+    bot.answer_callback_query(q.id).text(text).await?;
+
+    Ok(())
+}
+
+/// Sends the user the breakdown of the domain reputation score after they click the button under the site analysis result
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message with the site analysis result
+/// * `q`: Response from the user after pressing the button
+async fn explain_reputation_score(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
+    bot.answer_callback_query(q.id).await?;
+
+    let Some(domain) = q.data.as_deref().and_then(|data| data.strip_prefix("score_explanation:")) else {
+        return Ok(());
+    };
+
+    let reputation = domain_reputation::get_domain_reputation(domain);
+
+    let mut text = format!("🔍 Репутация домена {domain}\n\nБазовая оценка: 100\n");
+
+    if reputation.contributing_signals.is_empty() {
+        text = format!("{text}Негативных сигналов не найдено\n");
+    }
+
+    for (signal, contribution) in &reputation.contributing_signals {
+        text = format!("{text}{}: {}\n", describe_reputation_signal(signal), (contribution * 100.0).round());
+    }
+
+    text = format!("{text}\n⭐ Итоговая оценка: {}/100", (reputation.score * 100.0).round());
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+/// Pins the message with the site analysis result in the chat
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `chat_id`: Chat with the message
+/// * `message_id`: Message with the site analysis result
+///
+/// returns: `true` if the message was pinned, `false` if the bot is not allowed to pin messages in the chat
+async fn pin_scan_result(bot: &Bot, chat_id: ChatId, message_id: MessageId) -> Result<bool, RequestError> {
+    match bot.pin_chat_message(chat_id, message_id).disable_notification(true).await {
+        Ok(_) => Ok(true),
+        Err(RequestError::Api(ApiError::NotEnoughRightsToPinMessage | ApiError::NotEnoughRightsToManagePins)) => {
+            info!("Not enough rights to pin a message in the chat: {}", chat_id);
+            Ok(false)
+        }
+        Err(err) => Err(err)
+    }
+}
+
+/// Event handler after the user clicks the button in the ReceiveConfirmRemoveHistories state,
+/// which determines whether to delete the history or not
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `q`: Response from the user after pressing the button
+async fn menu_confirm_remove_links_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+    if let Some(data) = &q.data {
+        if let Some(message) = &q.message {
+            match data.as_str() {
+                "confirm" => clear_links(bot, dialogue, q).await?,
+                "cancel" => {
+                    bot.edit_message_text(message.chat.id, q.message.clone().unwrap().id, "Процесс очистки ссылок отменен.").await?;
+                    dialogue.update(BotState::Default).await?;
+
+                    show_main_menu(&bot, message).await?;
+
+                    bot.answer_callback_query(q.id).await?;
+                },
+
+                _ => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a message to the user that displays the bot's action menu
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn show_actions(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
+    show_main_menu(&bot, &msg).await?;
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Sends a message to the user that displays the main menu
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the user
+async fn show_main_menu(bot: &Bot, msg: &Message) -> HandlerResult {
+    let text = format!("{} к вашим услугам!\nЧто вы хотите сделать?", bot.get_me().await?.first_name);
+    let keyboard = create_main_menu_keyboard().await;
+
+    bot.send_message(msg.chat.id, text).reply_markup(keyboard).await?;
+
+    Ok(())
+}
+
+/// Sends a message to the user asking for confirmation to clear the history
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn ask_about_clear_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+    let user_id = q.from.id;
+    let histories = database::get_all_links_from_user(user_id.0, None);
+
+    if histories.is_empty() {
+        bot.send_message(user_id, "У вас нет ссылок для удаления").await?;
+        return Ok(());
+    }
+
+    let text = "❓ Вы действительно хотите очистить все сохраненные вами ссылки? ❓";
+    let keyboard = create_confirmation_menu_keyboard().await;
+
+    bot.send_message(q.chat_id().unwrap(), text).reply_markup(keyboard).await?;
+
+    dialogue.update(BotState::ReceiveConfirmRemoveLinks).await?;
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Sends a message to the user telling them to enter a link and also goes to the ReceiveLink state
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn start_enter_links(bot: Bot, dialogue: SparkleDialogue, msg: Message, q: CallbackQuery) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку. Для отмены ввода ссылки введите команду /cancel").await?;
+    dialogue.update(BotState::ReceiveLink).await?;
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Sends a message to the user that a link needs to be entered, and enters the
+/// ReceiveLinkForChecking state
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn start_check_link(bot: Bot, dialogue: SparkleDialogue, msg: Message, q: CallbackQuery) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку. Для отмены ввода ссылки введите команду /cancel").await?;
+    dialogue.update(BotState::ReceiveLinkForChecking).await?;
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Receives a link from a user, validates it, and adds it to the database if it is a valid URL.
+///
+/// # Arguments
+///
+/// * `bot` - A `Bot` object representing the Telegram bot.
+/// * `dialogue` - A `MyDialogue` object for handling the conversation flow.
+/// * `msg` - A `Message` object representing the received message.
+///
+/// # Returns
+///
+/// A `HandlerResult`, indicating the success or failure of the operation.
+///
+/// # Examples
+///
+/// ```
+/// # use my_bot::{Bot, MyDialogue, Message, HandlerResult};
+/// # use my_bot::website;
+/// # use my_bot::database;
+/// # use log::info;
+/// # use std::error::Error;
+/// async fn receive_link(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+///     // Code implementation
+///     Ok(())
+/// }
+/// ```
+async fn receive_link(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    let mut url: String;
+
+    match msg.text() {
+        Some(text) => {
+            url = text.to_string();
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку.").await?;
+            return Ok(());
+        }
+    }
+
+    if let Some(reply) = get_unsupported_input_reply(&url) {
+        bot.send_message(msg.chat.id, reply).await?;
+        return Ok(());
+    }
+
+    url = url_normalizer::normalize_url(&url);
+
+    if is_url(&url) {
+        let user_id = msg.from().expect("Unable to determine user ID").id;
+
+        if save_link(&bot, &dialogue, msg.chat.id, user_id, url, false).await? {
+            dialogue.update(BotState::Default).await?;
+
+            show_main_menu(&bot, &msg).await?;
+        }
+    }
+    else {
+        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+    }
+
+    Ok(())
+}
+
+/// Checks the site received from the user and sends the information to the user
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn check_site(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    let mut url: String;
+
+    match msg.text() {
+        Some(text) => {
+            url = text.to_string();
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Пожалуйста, введите ссылку.").await?;
+            return Ok(());
+        }
+    }
+
+    if let Some(reply) = get_unsupported_input_reply(&url) {
+        bot.send_message(msg.chat.id, reply).await?;
+        return Ok(());
+    }
+
+    url = url_normalizer::normalize_url(&url);
+
+    if is_url(&url) {
+        let send_message = bot.send_message(msg.chat.id, "Пожалуйста, подождите...\nМаксимальное время ответа - 15 секунд").await?;
+
+        info!("Site information for the user is requested: {}", msg.from().expect("Unable to determine user ID").id.0);
+
+        let site_information = site_analysis_cooldown::get_site_information(&url).await;
+
+        EVENT_BUS.emit(BotEvent::ScanCompleted {
+            user_id: msg.from().expect("Unable to determine user ID").id.0,
+            link: url.clone(),
+            status_code: site_information.as_ref().ok().map(|information| information.status_code)
+        });
+
+        match site_information {
+            Ok(result) => {
+                let text = compile_site_information(&url, &result);
+                let keyboard = create_scan_result_keyboard(&url).await;
+
+                bot.edit_message_text(msg.chat.id, send_message.id, text).reply_markup(keyboard).await?;
+            }
+            Err(e) => {
+                if e.is_timeout() {
+                    bot.edit_message_text(msg.chat.id, send_message.id, "Сайт слишком долго отвечал").await?;
+                }
+                else {
+                    bot.edit_message_text(msg.chat.id, send_message.id, "Боту не удалось проверить сайт").await?;
+                }
+            }
+        }
+
+        dialogue.update(BotState::Default).await?;
+
+        show_main_menu(&bot, &msg).await?;
+    }
+    else {
+        bot.send_message(msg.chat.id, "Данный текст не является ссылкой!").await?;
+    }
+
+    Ok(())
+}
+
+/// Compiles the site information into a formatted string.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the site.
+/// * `site_information` - The site information to compile.
+///
+/// # Returns
+///
+/// A string containing the compiled site information.
+///
+/// # Examples
+///
+/// ```
+/// use crate::SiteInformation;
+///
+/// let info = SiteInformation {
+///     status_code: 200,
+///     duration: 100,
+///     has_robots: 200,
+///     has_sitemap: 200,
+///     crawl_allowed: true,
+///     ip_records: Vec::new(),
+///     csp: None,
+///     certificate: None,
+/// };
+///
+/// let result = compile_site_information("https://example.com", &info);
+/// ```
+fn compile_site_information(url: &str, site_information: &SiteInformation) -> String {
+    let mut text = "❔ Информация о введеном вами сайте ❔\n\n".to_string();
+
+    match custom_scan_rules::apply_custom_rules(url) {
+        Some(rule_result) if rule_result.action == RuleAction::Block => {
+            text = format!("{text}⛔ Ссылка заблокирована: {}\n\n", rule_result.reason);
+        }
+        Some(rule_result) if rule_result.action == RuleAction::Flag => {
+            text = format!("{text}⚠️ Будьте осторожны: {}\n\n", rule_result.reason);
+        }
+        Some(rule_result) => {
+            text = format!("{text}✅ Сайт в списке доверенных: {}\n\n", rule_result.reason);
+        }
+        None => ()
+    }
+
+    // Warnings of the custom rules are shown above, so only the warnings of other checks are listed
+    for flag in link_flags::get_link_flags(url).iter().filter(|flag| flag.flag != "custom_rule") {
+        text = format!("{text}⚠️ {}\n", flag.reason);
+    }
+
+    if let Ok(domain) = domain_family::get_domain_family(url) {
+        let subdomains = ct_logs::get_ct_subdomains(&domain);
+
+        if !subdomains.is_empty() {
+            text = format!("{text}🔗 Связанные поддомены из CT-логов: {}\n", subdomains.len());
+        }
+    }
+
+    if let Some(domain) = domain_reputation::get_domain(url) {
+        let reputation = domain_reputation::get_domain_reputation(&domain);
+
+        // The contributions of the signals are shown by the "Why this score?" button
+        text = format!("{text}⭐ Репутация домена: {}/100\n\n", (reputation.score * 100.0).round());
+    }
+
+    text = format!("{text}📝 Код ответа: {}\n", site_information.status_code);
+    text = format!("{text}🕔 Время ответа: {} милисекунд\n", site_information.duration);
+
+    match site_information.has_robots {
+        200 => {
+            text = format!("{text}🤖 Наличие robots.txt: есть\n")
+        }
+        _ => {
+            text = format!("{text}🤖 Наличие robots.txt: нет (код ответа: {})\n", site_information.has_robots);
+        }
+    }
+    match site_information.has_sitemap {
+        200 => {
+            text = format!("{text}🗺 Наличие sitemap.xml: есть\n\n")
+        }
+        _ => {
+            text = format!("{text}🗺 Наличие sitemap.xml: нет (код ответа: {})\n\n", site_information.has_sitemap);
+        }
+    }
+
+    if !site_information.ip_records.is_empty() {
+        let domain = url.parse::<http::Uri>().ok().and_then(|uri| uri.host().map(|host| host.to_string())).unwrap_or_default();
+
+        text = format!("{text}🌐 IP-адреса:");
+
+        for record in &site_information.ip_records {
+            match &record.ptr_record {
+                Some(ptr_record) if reverse_dns::is_ptr_suspicious(&domain, ptr_record) => {
+                    text = format!("{text}\n{} (PTR: {ptr_record}) ⚠️ PTR-запись не совпадает с доменом", record.ip);
+                }
+                Some(ptr_record) => {
+                    text = format!("{text}\n{} (PTR: {ptr_record})", record.ip);
+                }
+                None => {
+                    text = format!("{text}\n{} (PTR-запись отсутствует)", record.ip);
+                }
+            }
+        }
+
+        text = format!("{text}\n\n");
+    }
+
+    match &site_information.csp {
+        Some(analysis) if analysis.has_weaknesses() => {
+            text = format!("{text}🛡 Content-Security-Policy: есть слабые места");
+
+            if analysis.has_unsafe_inline {
+                text = format!("{text}\n⚠️ Разрешены встроенные скрипты ('unsafe-inline')");
+            }
+            if analysis.has_unsafe_eval {
+                text = format!("{text}\n⚠️ Разрешен eval ('unsafe-eval')");
+            }
+            if analysis.allows_data_uris {
+                text = format!("{text}\n⚠️ Разрешены скрипты из data: URI");
+            }
+            if analysis.allows_wildcard {
+                text = format!("{text}\n⚠️ Разрешены скрипты с любых сайтов");
+            }
+
+            text = format!("{text}\n\n");
+        }
+        Some(_) => {
+            text = format!("{text}🛡 Content-Security-Policy: слабых мест не найдено\n\n");
+        }
+        None => {
+            text = format!("{text}🛡 Content-Security-Policy: не задан\n\n");
+        }
+    }
+
+    let iocs = iocs::get_iocs(url);
+
+    if !iocs.is_empty() {
+        let count = |ioc_type: &str| iocs.iter().filter(|ioc| ioc.ioc_type == ioc_type).count();
+
+        text = format!("{text}🧬 Индикаторы компрометации на странице:\nIP-адреса: {}\nE-mail: {}\nХэши файлов: {}\nСсылки: {}\n\n",
+                       count("ip"), count("email"), count("hash"), count("url"));
+    }
+
+    if !site_information.crawl_allowed {
+        text = format!("{text}🚧 Файл robots.txt запрещает боту загружать эту страницу, поэтому она не была загружена\n\n");
+    }
+
+    match &site_information.certificate {
+        Some(cert) => {
+            text = format!("{text}📄 Сертификат:\
+                \nОбщее название: {}\
+                \nОрганизация: {}\
+                \nСтрана: {}\
+                \nИздатель: {}\
+                \nВремя окончания: {}\n\n", cert.intermediate.common_name,
+                           cert.intermediate.organization,
+                           cert.intermediate.country,
+                           cert.intermediate.issuer,
+                           cert.intermediate.time_to_expiration);
+        }
+        None => {
+            text = format!("{text}📄 Сертификат: не найден\n\n")
+        }
+    }
+
+    text
+}
+
+/// Returns the description of a domain reputation signal for the user
+///
+/// # Arguments
+///
+/// * `signal`: Name of the signal
+fn describe_reputation_signal(signal: &str) -> &str {
+    match signal {
+        "ptr_mismatch" => "🌐 PTR-записи IP-адресов не совпадают с доменом",
+        "link_flags" => "⚠️ На ссылки этого домена есть предупреждения",
+        "failed_health_checks" => "📟 Сайт недавно был недоступен",
+        "weak_csp" => "🛡 Слабый Content-Security-Policy",
+        _ => signal
+    }
+}
+
+/// Function to cancel receiving a link in a Telegram chat.
+///
+/// # Arguments
+///
+/// * `bot` - An instance of the `Bot` struct providing methods to interact with the Telegram Bot API.
+/// * `dialogue` - An instance of the `MyDialogue` struct representing the conversation state.
+/// * `msg` - The `Message` struct representing the incoming message in the chat.
+///
+/// # Returns
+///
+/// An `HandlerResult` which is an alias for `Result<(), Error>` indicating the success or failure of the operation.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use your_crate::{Bot, MyDialogue, Message, HandlerResult};
+/// use tokio::runtime::Runtime;
+///
+/// let bot = Bot::new("your_bot_token");
+/// let dialogue = MyDialogue::create();
+/// let msg = Message::new("your_chat_id", "your_message");
+///
+/// let mut rt = Runtime::new().unwrap();
+///
+/// let result = rt.block_on(cancel_receive_link(bot, dialogue, msg));
+///
+/// assert!(result.is_ok());
+/// ```
+async fn cancel_receive_link(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Вы отменили ввод ссылки").await?;
+
+    dialogue.update(BotState::Default).await?;
+
+    Ok(())
+}
+
+/// Cancels the process of deleting some links.
+///
+/// # Arguments
+///
+/// * `bot` - The `Bot` instance to send a message.
+/// * `dialogue` - The `MyDialogue` instance to update the state.
+/// * `msg` - The `Message` object that triggered the cancellation.
 async fn cancel_deleting_some_links(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
     bot.send_message(msg.chat.id, "Вы отменили удаление ссылок").await?;
 
@@ -761,265 +2219,968 @@ async fn cancel_deleting_some_links(bot: Bot, dialogue: SparkleDialogue, msg: Me
     Ok(())
 }
 
-// Creating bot menus
-
-/// Creates the beginning menu keyboard.
-///
-/// This async function creates an instance of `InlineKeyboardMarkup` that represents
-/// a menu keyboard with a single button labeled "Приступим!" and a callback value of "begin".
-///
-/// # Example
-/// ```rust
-/// use telegram_bot::InlineKeyboardMarkup;
-/// use telegram_bot::InlineKeyboardButton;
-///
-/// async fn create_beginning_menu_keyboard() -> InlineKeyboardMarkup {
-///     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+// Creating bot menus
+
+/// Creates the beginning menu keyboard.
+///
+/// This async function creates an instance of `InlineKeyboardMarkup` that represents
+/// a menu keyboard with a single button labeled "Приступим!" and a callback value of "begin".
+///
+/// # Example
+/// ```rust
+/// use telegram_bot::InlineKeyboardMarkup;
+/// use telegram_bot::InlineKeyboardButton;
+///
+/// async fn create_beginning_menu_keyboard() -> InlineKeyboardMarkup {
+///     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+///
+///     let begin = InlineKeyboardButton::callback("Приступим!", "begin");
+///
+///     keyboard.push(vec![begin]);
+///
+///     InlineKeyboardMarkup::new(keyboard)
+/// }
+/// ```
+async fn create_beginning_menu_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let begin = InlineKeyboardButton::callback("Приступим!", "begin");
+
+    keyboard.push(vec![begin]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the main menu keyboard with inline buttons.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object representing the main menu keyboard.
+async fn create_main_menu_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let check_link = InlineKeyboardButton::callback("🔭 Проанализировать сайт 🔭", "check_link");
+    let enter_links = InlineKeyboardButton::callback("✏️ Добавить ссылки ✏️", "enter_links");
+    let links = InlineKeyboardButton::callback("📒 Получить все ссылки 📒", "get_links");
+    let delete_some_histories = InlineKeyboardButton::callback("✂️ Удалить несколько ссылок ✂️", "delete_some_links");
+    let clear_all_links = InlineKeyboardButton::callback("❌ Очистить все ссылки ❌", "clear_all_links");
+
+    keyboard.push(vec![check_link]);
+    keyboard.push(vec![enter_links]);
+    keyboard.push(vec![links]);
+    keyboard.push(vec![delete_some_histories]);
+    keyboard.push(vec![clear_all_links]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the keyboard shown under the site analysis result.
+///
+/// # Arguments
+///
+/// * `url` - The analyzed URL.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with the buttons that explain the domain reputation and pin the result.
+async fn create_scan_result_keyboard(url: &str) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    // Telegram limits callback data to 64 bytes, so the button is not shown for very long domains
+    if let Some(data) = domain_reputation::get_domain(url)
+        .map(|domain| format!("score_explanation:{domain}"))
+        .filter(|data| data.len() <= MAX_CALLBACK_DATA_LENGTH) {
+        keyboard.push(vec![InlineKeyboardButton::callback("🔍 Почему такая оценка?", data)]);
+    }
+
+    let pin_result = InlineKeyboardButton::callback("📌 Закрепить результат", "pin_result");
+
+    keyboard.push(vec![pin_result]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the keyboard with the formats of files the links can be imported from.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with a button for every format.
+async fn create_import_format_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let text = InlineKeyboardButton::callback("📄 Текст (ссылка на каждой строке)", "import_format:txt");
+    let csv = InlineKeyboardButton::callback("📊 CSV-таблица", "import_format:csv");
+
+    keyboard.push(vec![text]);
+    keyboard.push(vec![csv]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the keyboard that confirms importing the links shown in the preview.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with the "Импортировать" (import) and "Отмена" (cancel) buttons.
+async fn create_import_confirmation_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let confirmation = InlineKeyboardButton::callback("✅ Импортировать", "import_confirm");
+    let cancel = InlineKeyboardButton::callback("Отмена", "import_cancel");
+
+    keyboard.push(vec![confirmation]);
+    keyboard.push(vec![cancel]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the keyboard that confirms adding a link similar to a recently deleted or an already added link.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with the "Добавить" (add) and "Отмена" (cancel) buttons.
+async fn create_add_link_confirmation_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let confirmation = InlineKeyboardButton::callback("✅ Добавить", "add_link_confirm");
+    let cancel = InlineKeyboardButton::callback("Отмена", "add_link_cancel");
+
+    keyboard.push(vec![confirmation]);
+    keyboard.push(vec![cancel]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Creates the keyboard with which a user answers the request to share the reports.
+///
+/// # Arguments
+///
+/// * `requester_id` - The ID of the user who sent the request.
+///
+/// # Returns
+///
+/// Returns an `InlineKeyboardMarkup` object with the "Принять" (accept) and "Отклонить" (decline) buttons.
+fn create_share_request_keyboard(requester_id: u64) -> InlineKeyboardMarkup {
+    let accept = InlineKeyboardButton::callback("✅ Принять", format!("share_accept:{requester_id}"));
+    let decline = InlineKeyboardButton::callback("Отклонить", format!("share_decline:{requester_id}"));
+
+    InlineKeyboardMarkup::new(vec![vec![accept, decline]])
+}
+
+/// Creates an inline keyboard markup for a confirmation menu.
+///
+/// The resulting inline keyboard will have two buttons: "Очистить" (clear) and "Отмена" (cancel).
+///
+/// # Returns
+///
+/// The resulting inline keyboard markup.
+async fn create_confirmation_menu_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+
+    let confirmation = InlineKeyboardButton::callback("Очистить", "confirm");
+    let cancel = InlineKeyboardButton::callback("Отмена", "cancel");
+
+    keyboard.push(vec![confirmation]);
+    keyboard.push(vec![cancel]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+//
+
+/// Retrieves all saved links by user ID and sends a message with the result
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `q`: Response from the user after pressing the button
+async fn get_all_links_from_user(bot: Bot, q: CallbackQuery) -> HandlerResult {
+    let user_id = q.from.id;
+    let histories = database::get_all_links_from_user(user_id.0, None);
+
+    info!("Receiving a request for all links from the user: {}", user_id);
+
+    if histories.is_empty() {
+        bot.send_message(user_id, "У вас нет сохраненных ссылок").await?;
+    }
+    else {
+        send_links_page(&bot, user_id.into(), None, user_id.0, &LinkList::Saved, 0).await?;
+    }
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Starts the process of deleting some links.
+///
+/// This function takes a `Bot`, `MyDialogue`, and `CallbackQuery` as inputs.
+/// It gets the `user_id` from the `CallbackQuery` and calls the function `get_all_links_from_user` from the `database` module
+/// to retrieve all the links associated with the user.
+///
+/// If there are no links for the user, it sends a message to the user.
+/// Otherwise, it sends the user the first page of the numbered list of their links.
+///
+/// Finally, it updates the `dialogue` with the `BotState::DeletingSomeLinks`.
+///
+/// # Arguments
+///
+/// * `bot` - The Bot instance.
+/// * `dialogue` - The MyDialogue instance.
+/// * `q` - The CallbackQuery instance.
+///
+/// # Returns
+///
+/// The HandlerResult.
+///
+/// # Examples
+///
+/// ```rust
+/// use crate::{Bot, MyDialogue, CallbackQuery};
+///
+/// let bot = Bot::new();
+/// let dialogue = MyDialogue::new();
+/// let q = CallbackQuery::new();
+///
+/// start_deleting_some_links(bot, dialogue, q).await;
+/// ```
+async fn start_deleting_some_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+    let user_id = q.from.id;
+    let histories = database::get_all_links_from_user(user_id.0, None);
+
+    if histories.is_empty() {
+        bot.send_message(user_id, "У вас нет ссылок для удаления").await?;
+    }
+    else {
+        send_links_page(&bot, user_id.into(), None, user_id.0, &LinkList::Deleting, 0).await?;
+        dialogue.update(BotState::DeletingSomeLinks).await?;
+    }
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Creates a formatted list of links
+///
+/// # Arguments
+///
+/// * `str`: Message to user
+/// * `histories`: List of links
+/// * `first_number`: Number of the first link, so the links on all pages of a list are numbered in order
+///
+/// returns: Message to user with a formatted list of links
+fn create_links_list(str: &str, links: &[Links], first_number: usize) -> String {
+    let mut str = str.to_string();
+
+    for (i, link) in links.iter().enumerate() {
+        let link = format!("\n[{}] {}", first_number + i, link.link);
+        str.push_str(&link);
+    }
+    str
+}
+
+/// Sends a page of the list of links with buttons for the other pages
+///
+/// The number of links on a page is chosen by the user with `/setpagesize`.
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `chat_id`: Chat to send the page to
+/// * `message_id`: Message with the current page to replace, or `None` to send a new message
+/// * `user_id`: User whose links are shown
+/// * `list`: List of links to show
+/// * `page`: Number of the page, starting from 0
+async fn send_links_page(bot: &Bot, chat_id: ChatId, message_id: Option<MessageId>, user_id: u64, list: &LinkList, page: u32) -> HandlerResult {
+    let page_size = user_preferences::get_user_preferences(user_id).links_per_page as u32;
+    let links = list.load(user_id);
+    let total_pages = (links.len() as u32).div_ceil(page_size).max(1);
+
+    // The list may have become shorter since the buttons were created
+    let page = page.min(total_pages - 1);
+    let first = (page * page_size) as usize;
+    let page_links = &links[first.min(links.len())..(first + page_size as usize).min(links.len())];
+
+    if matches!(list, LinkList::Saved | LinkList::Family(_)) {
+        link_views::record_link_views(user_id, &page_links.iter().map(|link| link.link.as_str()).collect::<Vec<&str>>());
+    }
+
+    let text = if page_links.is_empty() {
+        "Список пуст".to_string()
+    }
+    else {
+        create_links_list(&list.title(), page_links, first + 1)
+    };
+
+    let keyboard = create_page_keyboard(page, total_pages, |page| list.callback_data(page));
+
+    send_or_edit_page(bot, chat_id, message_id, text, keyboard).await
+}
+
+/// Shows the user another page of a list of links when the button is pressed
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message with the current page
+/// * `q`: Callback query with the list and the number of the page
+async fn show_links_page(bot: Bot, msg: Message, q: CallbackQuery) -> HandlerResult {
+    bot.answer_callback_query(q.id).await?;
+
+    let Some((list, page)) = q.data.as_deref().and_then(LinkList::parse_callback_data) else {
+        return Ok(());
+    };
+
+    send_links_page(&bot, msg.chat.id, Some(msg.id), q.from.id.0, &list, page).await
+}
+
+/// Switches the pages of the numbered list while the user is choosing the links to delete
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `q`: Callback query with the number of the page
+async fn deleting_links_page_callback_handler(bot: Bot, q: CallbackQuery) -> HandlerResult {
+    let Some(message) = q.message.clone() else {
+        return Ok(());
+    };
+
+    if !q.data.as_deref().is_some_and(|data| data.starts_with("delete_page:")) {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    }
+
+    show_links_page(bot, message, q).await
+}
+
+/// Removes some references from the database that the user enters and returns the result of
+/// the operation
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn delete_some_links(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    let user_id = msg.from().expect("Unable to determine user ID").id.0;
+
+    let numbers_string = match msg.text() {
+        Some(text) => text.to_string(),
+        None => {
+            bot.send_message(msg.chat.id, "Пожалуйста, введите номера элементов.").await?;
+            return Ok(());
+        }
+    };
+
+    let mut numbers: Vec<usize> = Vec::new();
+
+    for number_string in numbers_string.split(" ") {
+        let res = number_string.parse::<i32>();
+
+        match res {
+            Ok(number) => {
+                if number - 1 < 0 {
+                    bot.send_message(msg.chat.id, "Введенный текст не является корректным!").await?;
+                    return Ok(());
+                }
+
+                numbers.push((number - 1) as usize);
+            }
+            Err(_) => {
+                bot.send_message(msg.chat.id, "Введенный текст не является корректным!").await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let histories = database::get_all_links_from_user(user_id, None);
+    let mut links: Vec<&str> = Vec::new();
+
+    for number in numbers {
+        if let Some(link) = histories.get(number) {
+            links.push(&link.link);
+        }
+        else {
+            bot.send_message(msg.chat.id, "Некоторые элементы не существуют в списке!").await?;
+
+            return Ok(());
+        }
+    }
+
+    database::delete_some_links(user_id, links);
+
+    bot.send_message(msg.chat.id, "Выбранные вами элементы были удалены").await?;
+    dialogue.update(BotState::Default).await?;
+
+    show_main_menu(&bot, &msg).await?;
+
+    Ok(())
+}
+
+/// Removes all references from the database from the user and returns the result of the operation
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `q`: Response from the user after pressing the button
+async fn clear_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+    let user_id = q.from.id;
+    let msg = q.message.expect("Failed to retrieve message ");
+
+    database::clear_all_links(user_id.0);
+
+    info!("Completely deleted the user's history: {}", user_id);
+
+    bot.edit_message_text(user_id, msg.id, "Ваша история запросов успешно очищена!").await?;
+
+    dialogue.update(BotState::Default).await?;
+
+    show_main_menu(&bot, &msg).await?;
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
+}
+
+/// Checks whether the user is the bot administrator specified in the `ADMIN_ID` environment variable
+///
+/// # Arguments
+///
+/// * `user_id`: User ID in Telegram
+///
+/// returns: `true` if the user is the administrator, `false` if not or if `ADMIN_ID` is not set
+fn is_admin(user_id: UserId) -> bool {
+    match std::env::var("ADMIN_ID") {
+        Ok(admin_id) => admin_id.parse::<u64>().map(|id| id == user_id.0).unwrap_or(false),
+        Err(_) => false
+    }
+}
+
+/// Sends the administrator a list of links that are stored at least `min_copies` times, in several messages if it is long
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `min_copies`: Minimum number of copies of the link
+async fn show_deduplication_candidates(bot: Bot, msg: Message, min_copies: u32) -> HandlerResult {
+    let candidates = database::get_deduplication_candidates(min_copies);
+
+    if candidates.is_empty() {
+        bot.send_message(msg.chat.id, "Повторяющихся ссылок не найдено").await?;
+        return Ok(());
+    }
+
+    let mut text = format!("Ссылки, сохраненные не менее {min_copies} раз:\n");
+
+    for (link, copies) in candidates {
+        text.push_str(&format!("\n{link} — {copies}"));
+    }
+
+    for part in message_text::split_message(&text) {
+        bot.send_message(msg.chat.id, part).await?;
+    }
+
+    Ok(())
+}
+
+/// Removes duplicate links from the database, consolidates the global statistics and sends the
+/// result of the operation to the administrator
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+async fn deduplicate_links(bot: Bot, msg: Message) -> HandlerResult {
+    let report = database::deduplicate_global_stats()?;
+
+    info!("Deduplicated the links. Removed rows: {}", report.duplicate_rows_removed);
+
+    bot.send_message(msg.chat.id, format!("Удалено повторяющихся строк: {}\nОбъединено ссылок: {}", report.duplicate_rows_removed, report.links_consolidated)).await?;
+
+    Ok(())
+}
+
+/// Sends the administrator a list of saved links whose pages are similar to the page by the link
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `link`: Link to compare with
+async fn show_similar_links(bot: Bot, msg: Message, link: String) -> HandlerResult {
+    let similar_links = database::find_similar_links(&link, SIMILAR_PAGE_MAX_DISTANCE);
+
+    if similar_links.is_empty() {
+        bot.send_message(msg.chat.id, "Похожих страниц не найдено").await?;
+        return Ok(());
+    }
+
+    let text = create_links_list("Ссылки на похожие страницы:\n", &similar_links, 1);
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+/// Adds a custom rule from the text of the administrator's command
+///
+/// The text has the format `<regex|glob> <block|allow|flag> <pattern> <reason>`.
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `rule`: Text of the rule
+async fn add_custom_rule(bot: Bot, msg: Message, rule: String) -> HandlerResult {
+    let mut parts = rule.splitn(4, ' ');
+
+    let pattern_type = parts.next().and_then(PatternType::parse);
+    let action = parts.next().and_then(RuleAction::parse);
+    let pattern = parts.next();
+    let reason = parts.next().unwrap_or_default();
+
+    let (Some(pattern_type), Some(action), Some(pattern)) = (pattern_type, action, pattern) else {
+        bot.send_message(msg.chat.id, "Формат команды: /addrule <regex|glob> <block|allow|flag> <шаблон> <причина>").await?;
+        return Ok(());
+    };
+
+    match custom_scan_rules::add_custom_rule(pattern, pattern_type, action, reason) {
+        Ok(id) => {
+            info!("Added a custom rule: {}", id);
+            bot.send_message(msg.chat.id, format!("Правило №{id} добавлено")).await?;
+        }
+        Err(err) => {
+            bot.send_message(msg.chat.id, format!("Некорректный шаблон: {err}")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the administrator a list of custom rules
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+async fn show_custom_rules(bot: Bot, msg: Message) -> HandlerResult {
+    let rules = custom_scan_rules::get_custom_rules();
+
+    if rules.is_empty() {
+        bot.send_message(msg.chat.id, "Правил проверки нет").await?;
+        return Ok(());
+    }
+
+    let mut text = "Правила проверки:\n".to_string();
+
+    for rule in rules {
+        text.push_str(&format!("\n№{} [{} {}] {} — {}", rule.id, rule.pattern_type.as_str(), rule.action.as_str(), rule.pattern, rule.reason));
+    }
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+/// Deletes a custom rule at the request of the administrator
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `id`: ID of the rule
+async fn delete_custom_rule(bot: Bot, msg: Message, id: i64) -> HandlerResult {
+    custom_scan_rules::delete_custom_rule(id);
+
+    bot.send_message(msg.chat.id, format!("Правило №{id} удалено")).await?;
+
+    Ok(())
+}
+
+/// Sends the administrator the indicators of compromise found on the page by the link
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `link`: Link to the page
+async fn show_iocs(bot: Bot, msg: Message, link: String) -> HandlerResult {
+    let iocs = iocs::get_iocs(&link);
+
+    if iocs.is_empty() {
+        bot.send_message(msg.chat.id, "Индикаторов компрометации не найдено").await?;
+        return Ok(());
+    }
+
+    let mut text = "Индикаторы компрометации:\n".to_string();
+
+    for ioc in iocs {
+        text.push_str(&format!("\n[{}] {}", ioc.ioc_type, ioc.ioc_value));
+    }
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
+}
+
+/// Sends the administrator a JSON file with all data about the link collected across all users
 ///
-///     let begin = InlineKeyboardButton::callback("Приступим!", "begin");
+/// The access to the data of every user who added the link is recorded in the privacy audit log.
 ///
-///     keyboard.push(vec![begin]);
+/// # Arguments
 ///
-///     InlineKeyboardMarkup::new(keyboard)
-/// }
-/// ```
-async fn create_beginning_menu_keyboard() -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `request`: Link to the malicious site and the reason for the request
+async fn send_incident_report(bot: Bot, msg: Message, request: String) -> HandlerResult {
+    let admin_id = msg.from().expect("Unable to determine user ID").id;
 
-    let begin = InlineKeyboardButton::callback("Приступим!", "begin");
+    let (link, reason) = request.trim().split_once(' ').unwrap_or((request.trim(), ""));
 
-    keyboard.push(vec![begin]);
+    if link.is_empty() || reason.trim().is_empty() {
+        bot.send_message(msg.chat.id, "Формат команды: /incident <ссылка> <причина>").await?;
+        return Ok(());
+    }
 
-    InlineKeyboardMarkup::new(keyboard)
+    let link = url_normalizer::normalize_url(link);
+    let report = incident_report::generate_incident_report(&link);
+
+    if report.submitters.is_empty() {
+        bot.send_message(msg.chat.id, "Эту ссылку никто не добавлял").await?;
+        return Ok(());
+    }
+
+    for user_id in &report.submitters {
+        privacy_audit::require_audit_reason(admin_id.0, *user_id, "incident", reason)?;
+    }
+
+    let report_json = serde_json::to_vec_pretty(&report)?;
+
+    bot.send_document(msg.chat.id, InputFile::memory(report_json).file_name("incident.json"))
+        .caption(format!("Отчет об инциденте: {link}"))
+        .await?;
+
+    Ok(())
 }
 
-/// Creates the main menu keyboard with inline buttons.
+/// Sets how often the link is checked for all users who added it
 ///
-/// # Returns
+/// # Arguments
 ///
-/// Returns an `InlineKeyboardMarkup` object representing the main menu keyboard.
-async fn create_main_menu_keyboard() -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `request`: Link and the number of hours between the checks, 0 to check it every hour
+async fn set_rescan_interval(bot: Bot, msg: Message, request: String) -> HandlerResult {
+    let (link, hours) = request.trim().split_once(' ').unwrap_or((request.trim(), ""));
 
-    let check_link = InlineKeyboardButton::callback("🔭 Проанализировать сайт 🔭", "check_link");
-    let enter_links = InlineKeyboardButton::callback("✏️ Добавить ссылки ✏️", "enter_links");
-    let links = InlineKeyboardButton::callback("📒 Получить все ссылки 📒", "get_links");
-    let delete_some_histories = InlineKeyboardButton::callback("✂️ Удалить несколько ссылок ✂️", "delete_some_links");
-    let clear_all_links = InlineKeyboardButton::callback("❌ Очистить все ссылки ❌", "clear_all_links");
+    let (false, Ok(hours)) = (link.is_empty(), hours.trim().parse::<u32>()) else {
+        bot.send_message(msg.chat.id, "Формат команды: /setrescaninterval <ссылка> <часы>").await?;
+        return Ok(());
+    };
 
-    keyboard.push(vec![check_link]);
-    keyboard.push(vec![enter_links]);
-    keyboard.push(vec![links]);
-    keyboard.push(vec![delete_some_histories]);
-    keyboard.push(vec![clear_all_links]);
+    let link = url_normalizer::normalize_url(link);
+    let interval = Some(hours).filter(|hours| *hours > 1);
 
-    InlineKeyboardMarkup::new(keyboard)
+    if link_health::set_rescan_interval(&link, interval) == 0 {
+        bot.send_message(msg.chat.id, "Эту ссылку никто не добавлял").await?;
+        return Ok(());
+    }
+
+    info!("The rescan interval of {} was set to {:?} hours", link, interval);
+
+    let text = match interval {
+        Some(hours) => format!("Ссылка {link} будет проверяться раз в {hours} ч."),
+        None => format!("Ссылка {link} будет проверяться каждый час")
+    };
+
+    bot.send_message(msg.chat.id, text).await?;
+
+    Ok(())
 }
 
-/// Creates an inline keyboard markup for a confirmation menu.
+/// Compacts the database at the request of the administrator
 ///
-/// The resulting inline keyboard will have two buttons: "Очистить" (clear) and "Отмена" (cancel).
-///
-/// # Returns
+/// # Arguments
 ///
-/// The resulting inline keyboard markup.
-async fn create_confirmation_menu_keyboard() -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+async fn vacuum_database(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Сжатие базы данных...").await?;
 
-    let confirmation = InlineKeyboardButton::callback("Очистить", "confirm");
-    let cancel = InlineKeyboardButton::callback("Отмена", "cancel");
+    let result = tokio::task::spawn_blocking(database::vacuum_database).await?;
 
-    keyboard.push(vec![confirmation]);
-    keyboard.push(vec![cancel]);
+    match result {
+        Some(Ok(report)) => {
+            info!("The database was compacted from {} to {} bytes in {} ms", report.size_before_bytes, report.size_after_bytes, report.duration_ms);
+            bot.send_message(msg.chat.id, format!("База данных сжата за {} мс: {} → {} байт", report.duration_ms, report.size_before_bytes, report.size_after_bytes)).await?;
+        }
+        Some(Err(err)) => {
+            error!("Failed to compact the database: {}", err);
+            bot.send_message(msg.chat.id, format!("Не удалось сжать базу данных: {err}")).await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "База данных уже сжимается").await?;
+        }
+    }
 
-    InlineKeyboardMarkup::new(keyboard)
+    Ok(())
 }
-//
 
-/// Retrieves all saved links by user ID and sends a message with the result
+/// Sends the administrator a list of users who have not used the bot for the given number of days
+///
+/// A long list is sent in several messages. The access to the activity of every listed user is recorded in the privacy audit log.
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
-/// * `q`: Response from the user after pressing the button
-async fn get_all_links_from_user(bot: Bot, q: CallbackQuery) -> HandlerResult {
-    let user_id = q.from.id;
-    let histories = database::get_all_links_from_user(user_id.0, None);
+/// * `msg`: Message sent by the administrator
+/// * `request`: Number of days without activity and the reason for the request
+async fn show_inactive_users(bot: Bot, msg: Message, request: String) -> HandlerResult {
+    let admin_id = msg.from().expect("Unable to determine user ID").id;
 
-    info!("Receiving a request for all links from the user: {}", user_id);
+    let (days, reason) = request.trim().split_once(' ').unwrap_or((request.trim(), ""));
 
-    if histories.iter().count() == 0 {
-        bot.send_message(user_id, "У вас нет сохраненных ссылок").await?;
+    let (Ok(days), false) = (days.parse::<u32>(), reason.trim().is_empty()) else {
+        bot.send_message(msg.chat.id, "Формат команды: /inactive <дни> <причина>").await?;
+        return Ok(());
+    };
+
+    let user_ids = user_activity::get_inactive_users(chrono::Duration::days(days as i64));
+
+    if user_ids.is_empty() {
+        bot.send_message(msg.chat.id, format!("Нет пользователей, неактивных {days} дней")).await?;
+        return Ok(());
     }
-    else {
-        let str = create_links_list("Вот ваши сохраненные ссылки:\n", histories);
-        bot.send_message(user_id, str).await?;
+
+    for user_id in &user_ids {
+        privacy_audit::require_audit_reason(admin_id.0, *user_id, "inactive", reason)?;
     }
 
-    bot.answer_callback_query(q.id).await?;
+    let mut text = format!("Пользователи, неактивные {days} дней ({}):\n", user_ids.len());
+
+    for activity in user_ids.into_iter().filter_map(user_activity::get_user_activity) {
+        text.push_str(&format!("\n{} — {} ({})", activity.user_id, activity.last_seen.format("%d.%m.%Y %H:%M"), activity.last_command));
+    }
+
+    for part in message_text::split_message(&text) {
+        bot.send_message(msg.chat.id, part).await?;
+    }
 
     Ok(())
 }
 
-/// Starts the process of deleting some links.
+/// Sends the administrator a JSON file with all data the bot stores about the user
 ///
-/// This function takes a `Bot`, `MyDialogue`, and `CallbackQuery` as inputs.
-/// It gets the `user_id` from the `CallbackQuery` and calls the function `get_all_links_from_user` from the `database` module
-/// to retrieve all the links associated with the user.
-///
-/// If there are no links for the user, it sends a message to the user.
-/// Otherwise, it creates a string by calling the `create_links_list` function (passing a text
-/// message and the `histories` parameter) and sends it as a message to the user.
-///
-/// Finally, it updates the `dialogue` with the `BotState::DeletingSomeLinks`.
+/// The access is recorded in the privacy audit log, and the user can see it in their own export.
 ///
 /// # Arguments
 ///
-/// * `bot` - The Bot instance.
-/// * `dialogue` - The MyDialogue instance.
-/// * `q` - The CallbackQuery instance.
-///
-/// # Returns
-///
-/// The HandlerResult.
-///
-/// # Examples
-///
-/// ```rust
-/// use crate::{Bot, MyDialogue, CallbackQuery};
-///
-/// let bot = Bot::new();
-/// let dialogue = MyDialogue::new();
-/// let q = CallbackQuery::new();
-///
-/// start_deleting_some_links(bot, dialogue, q).await;
-/// ```
-async fn start_deleting_some_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
-    let user_id = q.from.id;
-    let histories = database::get_all_links_from_user(user_id.0, None);
+/// * `bot`: Bot instance
+/// * `msg`: Message sent by the administrator
+/// * `request`: User ID and the reason for the request
+async fn send_user_data_to_admin(bot: Bot, msg: Message, request: String) -> HandlerResult {
+    let admin_id = msg.from().expect("Unable to determine user ID").id;
 
-    if histories.iter().count() == 0 {
-        bot.send_message(user_id, "У вас нет ссылок для удаления").await?;
-    }
-    else {
-        let str = create_links_list("Выберите, какие элементы требуется удалить. Напишите номера элемента через пробел. Вы можете отменить удаление, введя команду /cancel.\n\nИстория запросов:\n", histories);
+    let (user_id, reason) = request.trim().split_once(' ').unwrap_or((request.trim(), ""));
 
-        bot.send_message(user_id, str).await?;
-        dialogue.update(BotState::DeletingSomeLinks).await?;
+    let Ok(user_id) = user_id.parse::<u64>() else {
+        bot.send_message(msg.chat.id, "Формат команды: /userdata <ID пользователя> <причина>").await?;
+        return Ok(());
+    };
+
+    if let Err(err) = privacy_audit::require_audit_reason(admin_id.0, user_id, "userdata", reason) {
+        info!("The administrator {} was denied access to the data of the user {}: {}", admin_id, user_id, err);
+        bot.send_message(msg.chat.id, "Укажите причину доступа к данным пользователя: /userdata <ID пользователя> <причина>").await?;
+
+        return Ok(());
     }
 
-    bot.answer_callback_query(q.id).await?;
+    let export_json = serde_json::to_vec_pretty(&user_data_export::export_all_user_data(user_id))?;
+
+    bot.send_document(msg.chat.id, InputFile::memory(export_json).file_name(format!("userdata_{user_id}.json")))
+        .caption(format!("Данные пользователя {user_id}"))
+        .await?;
 
     Ok(())
 }
 
-/// Creates a formatted list of links
+// Import wizard
+
+/// Starts the import wizard by asking the user for the format of the file
 ///
 /// # Arguments
 ///
-/// * `str`: Message to user
-/// * `histories`: List of links
-///
-/// returns: Message to user with a formatted list of links
-fn create_links_list(str: &str, links: Vec<Links>) -> String {
-    let mut str = str.to_string();
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn start_import(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    let keyboard = create_import_format_keyboard().await;
 
-    for i in 0..links.iter().count() {
-        let link = format!("\n[{}] {}", i + 1, links[i].link);
-        str.push_str(&link);
+    bot.send_message(msg.chat.id, "В каком формате файл со ссылками? Для отмены импорта введите команду /cancel").reply_markup(keyboard).await?;
+    dialogue.update(BotState::ImportChooseFormat).await?;
+
+    Ok(())
+}
+
+/// Saves the format chosen by the user and asks for the file
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `q`: Response from the user after pressing the button
+async fn receive_import_format(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
+    let format = q.data.as_deref()
+        .and_then(|data| data.strip_prefix("import_format:"))
+        .and_then(ImportFormat::parse);
+
+    if let (Some(format), Some(chat_id)) = (format, q.chat_id()) {
+        bot.send_message(chat_id, format!("Отправьте файл со ссылками (не более {} ссылок)", link_import::MAX_IMPORTED_LINKS)).await?;
+        dialogue.update(BotState::ImportReceiveFile { format }).await?;
     }
-    str
+
+    bot.answer_callback_query(q.id).await?;
+
+    Ok(())
 }
 
-/// Removes some references from the database that the user enters and returns the result of
-/// the operation
+/// Downloads the file sent by the user and shows a preview of the links found in it
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
 /// * `dialogue`: A handle for controlling dialogue state
 /// * `msg`: Message sent by the user
-async fn delete_some_links(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
-    let numbers_string: String;
-    let user_id = msg.from().expect("Unable to determine user ID").id.0;
+/// * `format`: Format of the file chosen by the user
+async fn receive_import_file(bot: Bot, dialogue: SparkleDialogue, msg: Message, format: ImportFormat) -> HandlerResult {
+    let Some(document) = msg.document() else {
+        bot.send_message(msg.chat.id, "Пожалуйста, отправьте файл.").await?;
+        return Ok(());
+    };
 
-    match msg.text() {
-        Some(text) => {
-            numbers_string = text.to_string();
+    let downloader = TelegramFileDownloader {
+        max_size_bytes: MAX_IMPORT_FILE_SIZE,
+        allowed_mime_types: IMPORT_MIME_TYPES.iter().map(|mime_type| mime_type.to_string()).collect(),
+    };
+
+    let content = match downloader.download(&bot, &document.file.id, document.mime_type.as_ref().map(|mime_type| mime_type.essence_str())).await {
+        Ok(content) => content,
+        Err(DownloadError::TooLarge { .. }) => {
+            bot.send_message(msg.chat.id, "Файл слишком большой").await?;
+            return Ok(());
         }
-        None => {
-            bot.send_message(msg.chat.id, "Пожалуйста, введите номера элементов.").await?;
+        Err(DownloadError::UnsupportedType(_)) => {
+            bot.send_message(msg.chat.id, "Этот тип файлов не поддерживается. Отправьте текстовый файл или таблицу CSV").await?;
             return Ok(());
         }
-    }
+        Err(err) => return Err(err.into())
+    };
 
-    let mut numbers: Vec<usize> = Vec::new();
+    let links = link_import::parse_links(&String::from_utf8_lossy(&content), format);
 
-    for number_string in numbers_string.split(" ") {
-        let res = number_string.parse::<i32>();
+    if links.is_empty() {
+        bot.send_message(msg.chat.id, "В файле не найдено ссылок. Отправьте другой файл или введите команду /cancel").await?;
+        return Ok(());
+    }
 
-        match res {
-            Ok(number) => {
-                if number - 1 < 0 {
-                    bot.send_message(msg.chat.id, "Введенный текст не является корректным!").await?;
-                    return Ok(());
-                }
+    let mut text = format!("Найдено ссылок: {}\n", links.len());
 
-                numbers.push((number - 1) as usize);
-            }
-            Err(_) => {
-                bot.send_message(msg.chat.id, "Введенный текст не является корректным!").await?;
-                return Ok(());
-            }
-        }
+    for (i, link) in links.iter().take(IMPORT_PREVIEW_SIZE).enumerate() {
+        text.push_str(&format!("\n[{}] {}", i + 1, link));
     }
 
-    let histories = database::get_all_links_from_user(user_id, None);
-    let mut links: Vec<&str> = Vec::new();
+    if links.len() > IMPORT_PREVIEW_SIZE {
+        text.push_str(&format!("\n...и еще {}", links.len() - IMPORT_PREVIEW_SIZE));
+    }
 
-    for number in numbers {
-        if let Some(link) = histories.get(number) {
-            links.push(&link.link);
-        }
-        else {
-            bot.send_message(msg.chat.id, "Некоторые элементы не существуют в списке!").await?;
+    let keyboard = create_import_confirmation_keyboard().await;
 
-            return Ok(());
+    bot.send_message(msg.chat.id, text).reply_markup(keyboard).await?;
+    dialogue.update(BotState::ImportConfirm { links }).await?;
+
+    Ok(())
+}
+
+/// Imports the links after the user confirms the preview, or cancels the import
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `q`: Response from the user after pressing the button
+/// * `links`: Links shown in the preview
+async fn import_confirm_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery, links: Vec<String>) -> HandlerResult {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+
+    match q.data.as_deref() {
+        Some("import_confirm") => {
+            let report = link_import::import_links(q.from.id.0, &links);
+
+            info!("The user {} imported {} links", q.from.id, report.added);
+
+            bot.edit_message_text(message.chat.id, message.id, format!("Импорт завершен. Добавлено ссылок: {}, пропущено: {}", report.added, report.skipped)).await?;
+        }
+        Some("import_cancel") => {
+            bot.edit_message_text(message.chat.id, message.id, "Импорт ссылок отменен").await?;
         }
+        _ => return Ok(())
     }
 
-    database::delete_some_links(user_id, links);
-
-    bot.send_message(msg.chat.id, "Выбранные вами элементы были удалены").await?;
     dialogue.update(BotState::Default).await?;
+    bot.answer_callback_query(q.id).await?;
 
-    show_main_menu(&bot, &msg).await?;
+    show_main_menu(&bot, message).await?;
 
     Ok(())
 }
 
-/// Removes all references from the database from the user and returns the result of the operation
+/// Adds the link after the user confirms it, or leaves it out
 ///
 /// # Arguments
 ///
 /// * `bot`: Bot instance
 /// * `dialogue`: A handle for controlling dialogue state
 /// * `q`: Response from the user after pressing the button
-async fn clear_links(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery) -> HandlerResult {
-    let user_id = q.from.id;
-    let msg = q.message.expect("Failed to retrieve message ");
+/// * `link`: Link that has to be confirmed
+async fn add_link_confirm_callback_handler(bot: Bot, dialogue: SparkleDialogue, q: CallbackQuery, link: String) -> HandlerResult {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
 
-    database::clear_all_links(user_id.0);
+    match q.data.as_deref() {
+        Some("add_link_confirm") => {
+            dialogue.update(BotState::Default).await?;
+            bot.edit_message_reply_markup(message.chat.id, message.id).await?;
+            save_link(&bot, &dialogue, message.chat.id, q.from.id, link, true).await?;
+        }
+        Some("add_link_cancel") => {
+            dialogue.update(BotState::Default).await?;
+            bot.edit_message_text(message.chat.id, message.id, "Ссылка не добавлена").await?;
+        }
+        _ => return Ok(())
+    }
 
-    info!("Completely deleted the user's history: {}", user_id);
+    bot.answer_callback_query(q.id).await?;
 
-    bot.edit_message_text(user_id, msg.id, "Ваша история запросов успешно очищена!").await?;
+    show_main_menu(&bot, message).await?;
 
-    dialogue.update(BotState::Default).await?;
+    Ok(())
+}
 
-    show_main_menu(&bot, &msg).await?;
+/// Cancels the import wizard at any step
+///
+/// # Arguments
+///
+/// * `bot`: Bot instance
+/// * `dialogue`: A handle for controlling dialogue state
+/// * `msg`: Message sent by the user
+async fn cancel_import(bot: Bot, dialogue: SparkleDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Вы отменили импорт ссылок").await?;
 
-    bot.answer_callback_query(q.id).await?;
+    dialogue.update(BotState::Default).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,235 @@
+use std::time::Duration;
+use sqlite3::State;
+use teloxide::{ApiError, RequestError};
+use teloxide::prelude::*;
+
+use crate::database;
+
+/// How often unsent messages are retried.
+const RETRY_INTERVAL_IN_SECONDS: u64 = 300;
+
+/// How many times the bot tries to resend a message before giving up.
+const MAX_RETRY_COUNT: u32 = 12;
+
+/// How long a message may be in the process of sending before it is considered lost, for example because the bot was stopped.
+const SENDING_TIMEOUT_IN_SECONDS: u64 = 300;
+
+/// How long sent messages and messages that were given up on are kept in the queue.
+const MESSAGE_RETENTION_DAYS: u32 = 7;
+
+/// Represents a message that has not been delivered yet.
+struct OutboundMessage {
+    id: i64,
+    user_id: u64,
+    message: String,
+}
+
+/// Saves the message to the outbound queue and then tries to send it to the user.
+///
+/// If Telegram cannot be reached, the message stays in the queue and is resent
+/// by the retry task launched with [`launch_message_retries`].
+///
+/// # Arguments
+///
+/// * `bot` - Bot instance.
+/// * `user_id` - User ID in Telegram.
+/// * `message` - Text of the message.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub async fn send_message_with_persistence(bot: &Bot, user_id: u64, message: &str) {
+    let id = enqueue_message(user_id, message);
+
+    try_send(bot, &OutboundMessage { id, user_id, message: message.to_string() }).await;
+}
+
+/// Launches a task that resends undelivered messages every `RETRY_INTERVAL_IN_SECONDS` seconds.
+///
+/// Messages that are being sent at the moment are skipped, and the messages that are no longer needed are removed from the queue.
+///
+/// # Arguments
+///
+/// * `bot` - Bot instance.
+pub fn launch_message_retries(bot: Bot) {
+    let mut interval = tokio::time::interval(Duration::from_secs(RETRY_INTERVAL_IN_SECONDS));
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            for message in get_unsent_messages() {
+                // The message may have been sent by another task since the list was read
+                if claim_message(message.id) {
+                    try_send(&bot, &message).await;
+                }
+            }
+
+            purge_finished_messages();
+        }
+    });
+}
+
+/// Sends the message and marks it as sent, or increases its retry counter if sending failed.
+///
+/// Messages that can never be delivered, for example because the user has blocked the bot, are dropped.
+async fn try_send(bot: &Bot, message: &OutboundMessage) {
+    match bot.send_message(UserId(message.user_id), &message.message).await {
+        Ok(_) => mark_as_sent(message.id),
+        Err(err) if is_permanent_error(&err) => {
+            info!("Dropped a message to the user {}: {}", message.user_id, err);
+            mark_as_dropped(message.id);
+        }
+        Err(err) => {
+            warn!("Failed to send a message to the user {}: {}", message.user_id, err);
+            increase_retry_count(message.id);
+        }
+    }
+}
+
+/// Checks whether the error means that resending the message will not help.
+fn is_permanent_error(err: &RequestError) -> bool {
+    matches!(err, RequestError::Api(
+        ApiError::BotBlocked | ApiError::BotKicked | ApiError::BotKickedFromSupergroup | ApiError::ChatNotFound |
+        ApiError::UserNotFound | ApiError::UserDeactivated | ApiError::CantInitiateConversation | ApiError::CantTalkWithBots |
+        ApiError::MessageIsTooLong | ApiError::MessageTextIsEmpty
+    ))
+}
+
+/// Adds a message to the outbound queue and returns its ID.
+///
+/// The message is marked as being sent, as it is sent right after it is added.
+fn enqueue_message(user_id: u64, message: &str) -> i64 {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO outbound_messages (user_id, message, created_at, sending_since) \
+        VALUES (?, ?, datetime('now'), datetime('now'))").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, message).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT last_insert_rowid()").unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap()
+}
+
+/// The condition of the messages that have to be resent: they have not been sent or dropped, have not run out of retries,
+/// and are not being sent at the moment. The maximum number of retries is bound to `?1`, the sending timeout to `?2`.
+const UNSENT_MESSAGE_CONDITION: &str = "sent = 0 AND dropped = 0 AND retry_count < ?1 \
+    AND (sending_since IS NULL OR sending_since < datetime('now', ?2))";
+
+/// Returns the messages that have not been sent yet, have not run out of retries and are not being sent at the moment.
+fn get_unsent_messages() -> Vec<OutboundMessage> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare(format!("SELECT id, user_id, message FROM outbound_messages WHERE {UNSENT_MESSAGE_CONDITION} ORDER BY id")).unwrap();
+
+    db.bind(1, MAX_RETRY_COUNT as i64).unwrap();
+    db.bind(2, format!("-{SENDING_TIMEOUT_IN_SECONDS} seconds").as_str()).unwrap();
+
+    let mut vec: Vec<OutboundMessage> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(OutboundMessage {
+            id: db.read::<i64>(0).unwrap(),
+            user_id: db.read::<i64>(1).unwrap() as u64,
+            message: db.read::<String>(2).unwrap(),
+        });
+    }
+
+    vec
+}
+
+/// Marks the message as being sent if it still has to be resent.
+///
+/// # Returns
+///
+/// `true` if the message has to be sent by the caller, `false` if it has been sent or is being sent by another task.
+fn claim_message(id: i64) -> bool {
+    let connection = database::open_connection();
+    let mut db = connection.prepare(format!("UPDATE outbound_messages SET sending_since = datetime('now') WHERE id = ?3 AND {UNSENT_MESSAGE_CONDITION}")).unwrap();
+
+    db.bind(1, MAX_RETRY_COUNT as i64).unwrap();
+    db.bind(2, format!("-{SENDING_TIMEOUT_IN_SECONDS} seconds").as_str()).unwrap();
+    db.bind(3, id).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT changes()").unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap() > 0
+}
+
+/// Marks the message as delivered.
+fn mark_as_sent(id: i64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE outbound_messages SET sent = 1, sent_at = datetime('now') WHERE id = ?").unwrap();
+
+    db.bind(1, id).unwrap();
+    db.next().unwrap();
+}
+
+/// Marks the message as one that can never be delivered, so it is not resent.
+fn mark_as_dropped(id: i64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE outbound_messages SET dropped = 1, sending_since = NULL WHERE id = ?").unwrap();
+
+    db.bind(1, id).unwrap();
+    db.next().unwrap();
+}
+
+/// Increases the number of failed attempts to deliver the message, so it is resent later.
+fn increase_retry_count(id: i64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE outbound_messages SET retry_count = retry_count + 1, sending_since = NULL WHERE id = ?").unwrap();
+
+    db.bind(1, id).unwrap();
+    db.next().unwrap();
+}
+
+/// Removes the messages that were sent, dropped or ran out of retries more than `MESSAGE_RETENTION_DAYS` days ago.
+fn purge_finished_messages() {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("DELETE FROM outbound_messages WHERE (sent = 1 OR dropped = 1 OR retry_count >= ?) \
+        AND created_at < datetime('now', ?)").unwrap();
+
+    db.bind(1, MAX_RETRY_COUNT as i64).unwrap();
+    db.bind(2, format!("-{MESSAGE_RETENTION_DAYS} days").as_str()).unwrap();
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod message_queue_tests {
+    use crate::message_queue::*;
+
+    #[test]
+    fn test_unsent_messages_are_retried_until_sent() {
+        let id = enqueue_message(91001, "Hello world!");
+
+        // The message is being sent right after it is added, so it is not resent at the same time
+        assert!(!get_unsent_messages().iter().any(|message| message.id == id));
+        assert!(!claim_message(id));
+
+        increase_retry_count(id);
+        assert!(get_unsent_messages().iter().any(|message| message.id == id && message.user_id == 91001));
+
+        assert!(claim_message(id));
+        assert!(!claim_message(id));
+
+        mark_as_sent(id);
+        assert!(!get_unsent_messages().iter().any(|message| message.id == id));
+    }
+
+    #[test]
+    fn test_dropped_messages_are_not_retried() {
+        let id = enqueue_message(91002, "Hello world!");
+
+        increase_retry_count(id);
+        mark_as_dropped(id);
+
+        assert!(!get_unsent_messages().iter().any(|message| message.id == id));
+        assert!(is_permanent_error(&RequestError::Api(ApiError::BotBlocked)));
+        assert!(!is_permanent_error(&RequestError::RetryAfter(Duration::from_secs(5))));
+    }
+}
@@ -0,0 +1,115 @@
+/// The maximum length of the text of a Telegram message, in UTF-16 code units.
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Returns the length of the text the way Telegram counts it.
+fn telegram_length(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Splits the text into parts that fit into Telegram messages.
+///
+/// The text is split between lines, and only lines that do not fit into a message on their own are split in the middle.
+/// Parts without visible text are left out, as Telegram does not send empty messages.
+///
+/// # Arguments
+///
+/// * `text` - The text of the message.
+pub fn split_message(text: &str) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in text.split('\n') {
+        for piece in split_line(line) {
+            current = Some(match current.take() {
+                Some(mut message) if telegram_length(&message) + 1 + telegram_length(&piece) <= MAX_MESSAGE_LENGTH => {
+                    message.push('\n');
+                    message.push_str(&piece);
+                    message
+                }
+                Some(message) => {
+                    parts.push(message);
+                    piece
+                }
+                None => piece
+            });
+        }
+    }
+
+    parts.extend(current);
+    parts.retain(|part| !part.trim().is_empty());
+
+    parts
+}
+
+/// Splits the line into pieces that fit into a message.
+fn split_line(line: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = vec![String::new()];
+    let mut length = 0;
+
+    for char in line.chars() {
+        if length + char.len_utf16() > MAX_MESSAGE_LENGTH {
+            pieces.push(String::new());
+            length = 0;
+        }
+
+        pieces.last_mut().unwrap().push(char);
+        length += char.len_utf16();
+    }
+
+    pieces
+}
+
+/// Shortens the text so it fits into a Telegram message, marking the end of the shortened text with an ellipsis.
+///
+/// # Arguments
+///
+/// * `text` - The text of the message.
+pub fn truncate_message(text: &str) -> String {
+    if telegram_length(text) <= MAX_MESSAGE_LENGTH {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut length = 0;
+
+    // One code unit is left for the ellipsis
+    for char in text.chars() {
+        if length + char.len_utf16() > MAX_MESSAGE_LENGTH - 1 {
+            break;
+        }
+
+        truncated.push(char);
+        length += char.len_utf16();
+    }
+
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod message_text_tests {
+    use crate::message_text::*;
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("Short\nmessage"), vec!["Short\nmessage"]);
+
+        let line = "a".repeat(3000);
+        let parts = split_message(&format!("{line}\n{line}\n{}", "b".repeat(5000)));
+
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], line);
+        assert_eq!(parts[2], "b".repeat(MAX_MESSAGE_LENGTH));
+        assert!(parts.iter().all(|part| telegram_length(part) <= MAX_MESSAGE_LENGTH));
+    }
+
+    #[test]
+    fn test_truncate_message() {
+        assert_eq!(truncate_message("Short message"), "Short message");
+
+        let truncated = truncate_message(&"⚠️".repeat(3000));
+
+        assert_eq!(telegram_length(&truncated), MAX_MESSAGE_LENGTH);
+        assert!(truncated.ends_with('…'));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use serde::Serialize;
+use sqlite3::State;
+
+use crate::database;
+
+/// Represents the reasons an administrator is not allowed to access user data.
+#[derive(PartialEq, Debug)]
+pub enum AuditError {
+    /// The administrator has not explained why they need the data.
+    MissingReason
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::MissingReason => write!(f, "the reason for accessing user data is not specified")
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+/// Represents an access of an administrator to the data of a user.
+#[derive(Serialize)]
+pub struct PrivacyAuditEntry {
+    pub admin_user_id: u64,
+    /// The name of the operation, for example `incident`.
+    pub operation: String,
+    pub reason: String,
+    pub accessed_at: String,
+}
+
+/// Records the access of an administrator to the data of a user in the privacy audit log.
+///
+/// Must be called before every administrator operation that reads or changes the data of a specific user,
+/// and the operation must not be performed if it fails.
+///
+/// # Arguments
+///
+/// * `admin_id` - The ID of the administrator.
+/// * `target_user_id` - The ID of the user whose data is accessed.
+/// * `op` - The name of the operation.
+/// * `reason` - Why the administrator needs the data.
+///
+/// # Returns
+///
+/// * An `Ok` variant if the access was recorded.
+/// * An `Err` variant containing an `AuditError` if the access is not allowed.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn require_audit_reason(admin_id: u64, target_user_id: u64, op: &str, reason: &str) -> Result<(), AuditError> {
+    let reason = reason.trim();
+
+    if reason.is_empty() {
+        return Err(AuditError::MissingReason);
+    }
+
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO privacy_audit_log (admin_user_id, accessed_user_id, operation, reason, accessed_at) \
+        VALUES (?, ?, ?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, admin_id as i64).unwrap();
+    db.bind(2, target_user_id as i64).unwrap();
+    db.bind(3, op).unwrap();
+    db.bind(4, reason).unwrap();
+
+    db.next().unwrap();
+
+    Ok(())
+}
+
+/// Returns the accesses of administrators to the data of the user, the oldest first.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_privacy_audit_log(user_id: u64) -> Vec<PrivacyAuditEntry> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT admin_user_id, operation, reason, accessed_at FROM privacy_audit_log \
+        WHERE accessed_user_id = ? ORDER BY id").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut vec: Vec<PrivacyAuditEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(PrivacyAuditEntry {
+            admin_user_id: db.read::<i64>(0).unwrap() as u64,
+            operation: db.read::<String>(1).unwrap(),
+            reason: db.read::<String>(2).unwrap(),
+            accessed_at: db.read::<String>(3).unwrap(),
+        });
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod privacy_audit_tests {
+    use crate::database;
+    use crate::privacy_audit::*;
+
+    #[test]
+    fn test_require_audit_reason() {
+        database::open_connection().execute("DELETE FROM privacy_audit_log WHERE accessed_user_id = 98001").unwrap();
+
+        assert_eq!(require_audit_reason(1, 98001, "incident", "  "), Err(AuditError::MissingReason));
+        assert!(get_privacy_audit_log(98001).is_empty());
+
+        assert!(require_audit_reason(1, 98001, "incident", "Abuse report #12").is_ok());
+
+        let log = get_privacy_audit_log(98001);
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].admin_user_id, 1);
+        assert_eq!(log[0].operation, "incident");
+        assert_eq!(log[0].reason, "Abuse report #12");
+    }
+}
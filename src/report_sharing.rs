@@ -0,0 +1,160 @@
+use sqlite3::State;
+
+use crate::database;
+use crate::database::Links;
+
+/// Saves the request of a user to share the scan reports with another user until it is answered.
+///
+/// # Arguments
+///
+/// * `requester_id` - The ID of the user who sent the request.
+/// * `target_id` - The ID of the user who has to answer the request.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_share_request(requester_id: u64, target_id: u64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR REPLACE INTO share_requests (requester_id, target_id, requested_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, requester_id as i64).unwrap();
+    db.bind(2, target_id as i64).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Removes the pending request to share the scan reports, so it can be answered only once.
+///
+/// # Arguments
+///
+/// * `requester_id` - The ID of the user who sent the request.
+/// * `target_id` - The ID of the user who answers the request.
+///
+/// # Returns
+///
+/// `true` if the request was sent and has not been answered yet.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn take_share_request(requester_id: u64, target_id: u64) -> bool {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("DELETE FROM share_requests WHERE requester_id = ? AND target_id = ?").unwrap();
+
+    db.bind(1, requester_id as i64).unwrap();
+    db.bind(2, target_id as i64).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT changes()").unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap() > 0
+}
+
+/// Records that two users have agreed to share their scan reports with each other.
+///
+/// The agreement is mutual, so the order of the users does not matter.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user who requested the sharing.
+/// * `partner_id` - The ID of the user who accepted the request.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_share_agreement(user_id: u64, partner_id: u64) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR IGNORE INTO share_agreements (user_id_a, user_id_b, agreed_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+    // Each pair of users is stored once, with the smaller ID first
+    db.bind(1, user_id.min(partner_id) as i64).unwrap();
+    db.bind(2, user_id.max(partner_id) as i64).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Returns the flagged links of the user and of all the users who have agreed to share their reports with them.
+///
+/// A link saved by several of these users is returned once.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_shared_links(user_id: u64) -> Vec<Links> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT MIN(user_id), link FROM links \
+        WHERE is_hashed = 0 AND link IN (SELECT link FROM link_flags) \
+        AND (user_id = ?1 \
+            OR user_id IN (SELECT user_id_b FROM share_agreements WHERE user_id_a = ?1) \
+            OR user_id IN (SELECT user_id_a FROM share_agreements WHERE user_id_b = ?1)) \
+        GROUP BY link ORDER BY MIN(rowid)").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut vec: Vec<Links> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(Links {
+            user_id: db.read::<f64>(0).unwrap(),
+            link: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod report_sharing_tests {
+    use crate::{database, link_flags};
+    use crate::report_sharing::*;
+
+    #[test]
+    fn test_get_shared_links() {
+        database::open_connection().execute("DELETE FROM share_agreements WHERE user_id_a IN (40701, 40702, 40703); \
+            DELETE FROM link_flags WHERE link IN ('https://shared-phishing.example', 'https://partner-phishing.example', 'https://stranger-phishing.example')").unwrap();
+
+        database::add_link(40701, "https://shared-phishing.example");
+        database::add_link(40702, "https://partner-phishing.example");
+        database::add_link(40702, "https://partner-safe.example");
+        database::add_link(40703, "https://stranger-phishing.example");
+
+        link_flags::flag_link("https://shared-phishing.example", "custom_rule", "Phishing");
+        link_flags::flag_link("https://partner-phishing.example", "custom_rule", "Phishing");
+        link_flags::flag_link("https://stranger-phishing.example", "custom_rule", "Phishing");
+
+        let links_of = |user_id: u64| get_shared_links(user_id).into_iter().map(|link| link.link).collect::<Vec<String>>();
+
+        assert_eq!(links_of(40702), vec!["https://partner-phishing.example"]);
+
+        add_share_agreement(40702, 40701);
+
+        let expected = vec!["https://shared-phishing.example", "https://partner-phishing.example"];
+
+        assert_eq!(links_of(40701), expected);
+        assert_eq!(links_of(40702), expected);
+
+        for user_id in [40701, 40702, 40703] {
+            database::clear_all_links(user_id);
+        }
+    }
+
+    #[test]
+    fn test_share_request_is_answered_once() {
+        assert!(!take_share_request(40711, 40712));
+
+        add_share_request(40711, 40712);
+
+        assert!(!take_share_request(40712, 40711));
+        assert!(take_share_request(40711, 40712));
+        assert!(!take_share_request(40711, 40712));
+    }
+}
@@ -0,0 +1,137 @@
+use std::net::IpAddr;
+use dns_lookup::{LookupError, LookupErrorKind};
+
+use crate::{database, domain_reputation};
+
+/// Represents an IP address of a site together with its PTR record.
+pub struct IpRecord {
+    pub ip: IpAddr,
+    pub ptr_record: Option<String>,
+}
+
+/// Looks up the PTR record of the IP address.
+///
+/// # Arguments
+///
+/// * `ip` - The IP address.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the host name from the PTR record, or `None` if the address has no PTR record.
+/// * An `Err` variant containing a `LookupError` if the lookup failed.
+pub async fn fetch_ptr_record(ip: IpAddr) -> Result<Option<String>, LookupError> {
+    let result = tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip)).await
+        .expect("The PTR lookup task panicked");
+
+    match result {
+        Ok(ptr_record) => Ok(Some(ptr_record)),
+        Err(err) if matches!(err.kind(), LookupErrorKind::NoName | LookupErrorKind::NoData) => Ok(None),
+        Err(err) => Err(err)
+    }
+}
+
+/// Checks whether the PTR record does not belong to the domain.
+///
+/// Legitimate sites usually point the PTR records of their servers to their own domain,
+/// while a mismatch is a common indicator of bulletproof hosting.
+///
+/// # Arguments
+///
+/// * `domain` - The domain of the site.
+/// * `ptr` - The host name from the PTR record of one of the site's IP addresses.
+///
+/// # Returns
+///
+/// `true` if the PTR record does not contain the domain, `false` otherwise.
+pub fn is_ptr_suspicious(domain: &str, ptr: &str) -> bool {
+    let domain = domain.trim_start_matches("www.").trim_end_matches('.').to_lowercase();
+    let ptr = ptr.trim_end_matches('.').to_lowercase();
+
+    !ptr.contains(&domain)
+}
+
+/// Resolves the IP addresses of the domain and looks up their PTR records.
+///
+/// Addresses whose PTR record could not be looked up are returned without a PTR record.
+///
+/// # Arguments
+///
+/// * `domain` - The domain of the site.
+///
+/// # Returns
+///
+/// The IP addresses of the domain, or an empty vector if the domain could not be resolved.
+pub async fn fetch_ip_records(domain: &str) -> Vec<IpRecord> {
+    let host = domain.to_string();
+    let ips = tokio::task::spawn_blocking(move || dns_lookup::lookup_host(&host).map(|ips| ips.collect::<Vec<IpAddr>>())).await
+        .expect("The DNS lookup task panicked");
+
+    let mut ips = match ips {
+        Ok(ips) => ips,
+        Err(err) => {
+            warn!("Failed to resolve the domain {}: {}", domain, err);
+            return Vec::new();
+        }
+    };
+
+    ips.sort();
+    ips.dedup();
+
+    let mut records: Vec<IpRecord> = Vec::new();
+
+    for ip in ips {
+        records.push(IpRecord {
+            ip,
+            ptr_record: fetch_ptr_record(ip).await.unwrap_or(None),
+        });
+    }
+
+    records
+}
+
+/// Saves the IP addresses of the link and their PTR records.
+///
+/// # Arguments
+///
+/// * `link` - The link.
+/// * `records` - The IP addresses of the link's domain.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn store_ip_records(link: &str, records: &[IpRecord]) {
+    let connection = database::open_connection();
+    let domain = domain_reputation::get_domain(link);
+
+    for record in records {
+        let mut db = connection.prepare("INSERT OR REPLACE INTO link_ips (link, ip, ptr_record, domain, resolved_at) VALUES (?, ?, ?, ?, datetime('now'))").unwrap();
+
+        db.bind(1, link).unwrap();
+        db.bind(2, record.ip.to_string().as_str()).unwrap();
+
+        match &record.ptr_record {
+            Some(ptr_record) => db.bind(3, ptr_record.as_str()).unwrap(),
+            None => db.bind(3, ()).unwrap()
+        }
+
+        match &domain {
+            Some(domain) => db.bind(4, domain.as_str()).unwrap(),
+            None => db.bind(4, ()).unwrap()
+        }
+
+        db.next().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod reverse_dns_tests {
+    use crate::reverse_dns::is_ptr_suspicious;
+
+    #[test]
+    fn test_is_ptr_suspicious() {
+        assert!(!is_ptr_suspicious("www.example.com", "server1.example.com."));
+        assert!(!is_ptr_suspicious("example.com", "EXAMPLE.COM"));
+        assert!(is_ptr_suspicious("example.com", "vps-1234.cheap-hosting.net"));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::time::Duration;
+use reqwest::{Client, Url};
+use robotstxt::DefaultMatcher;
+use sqlite3::State;
+
+use crate::database;
+
+/// The user agent the bot looks for in robots.txt files.
+const ROBOTS_USER_AGENT: &str = "SparkleScannerBot";
+
+/// How long a downloaded robots.txt file is considered fresh.
+const ROBOTS_CACHE_TTL_HOURS: u32 = 24;
+
+/// Represents the reasons the robots.txt file of a site could not be downloaded.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The URL cannot be parsed or has no host.
+    InvalidUrl(String),
+    /// The request for the file failed.
+    Request(reqwest::Error)
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::InvalidUrl(url) => write!(f, "the URL {url} has no host"),
+            FetchError::Request(err) => write!(f, "failed to download robots.txt: {err}")
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Represents the robots.txt file of a domain.
+pub struct RobotsTxt {
+    pub domain: String,
+    pub content: String,
+}
+
+/// Returns the robots.txt file for the site the URL belongs to.
+///
+/// The file is taken from the `robots_cache` table if it was downloaded less than
+/// `ROBOTS_CACHE_TTL_HOURS` hours ago, otherwise it is downloaded again and cached.
+/// If the site does not have a robots.txt file, an empty file is returned, which allows everything.
+///
+/// # Arguments
+///
+/// * `base_url` - Any URL of the site.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the robots.txt file.
+/// * An `Err` variant containing a `FetchError` if the URL is invalid or the file could not be downloaded.
+pub async fn fetch_robots_txt(base_url: &str) -> Result<RobotsTxt, FetchError> {
+    let url = Url::parse(base_url).map_err(|_| FetchError::InvalidUrl(base_url.to_string()))?;
+    let domain = url.host_str().ok_or_else(|| FetchError::InvalidUrl(base_url.to_string()))?.to_string();
+
+    if let Some(content) = get_cached_robots_txt(&domain) {
+        return Ok(RobotsTxt { domain, content });
+    }
+
+    let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
+    let resp = client.get(format!("{}://{}/robots.txt", url.scheme(), domain)).send().await.map_err(FetchError::Request)?;
+
+    // Sites without robots.txt allow everything
+    let content = if resp.status().is_success() {
+        resp.text().await.map_err(FetchError::Request)?
+    }
+    else {
+        String::new()
+    };
+
+    cache_robots_txt(&domain, &content);
+
+    Ok(RobotsTxt { domain, content })
+}
+
+/// Checks whether the robots.txt file allows the bot to fetch the URL.
+///
+/// # Arguments
+///
+/// * `robots` - The robots.txt file of the site.
+/// * `url` - The URL to check.
+///
+/// # Returns
+///
+/// `true` if crawling the URL is allowed, `false` otherwise.
+pub fn is_crawl_allowed(robots: &RobotsTxt, url: &str) -> bool {
+    let mut matcher = DefaultMatcher::default();
+    matcher.one_agent_allowed_by_robots(&robots.content, ROBOTS_USER_AGENT, url)
+}
+
+/// Downloads the robots.txt file of the site and checks whether it allows the bot to fetch the URL.
+///
+/// If the robots.txt file cannot be downloaded or the URL is invalid, the URL is considered allowed,
+/// and the request for the page itself reports the problem.
+///
+/// # Arguments
+///
+/// * `url` - The URL to check.
+pub async fn is_url_crawl_allowed(url: &str) -> bool {
+    match fetch_robots_txt(url).await {
+        Ok(robots) => {
+            let allowed = is_crawl_allowed(&robots, url);
+
+            if !allowed {
+                info!("The robots.txt file of {} does not allow fetching: {}", robots.domain, url);
+            }
+
+            allowed
+        }
+        Err(_) => true
+    }
+}
+
+/// Returns the robots.txt file of the domain from the cache if it is still fresh.
+fn get_cached_robots_txt(domain: &str) -> Option<String> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT robots_txt FROM robots_cache WHERE domain = ? AND cached_at > datetime('now', ?)").unwrap();
+
+    db.bind(1, domain).unwrap();
+    db.bind(2, format!("-{ROBOTS_CACHE_TTL_HOURS} hours").as_str()).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => Some(db.read::<String>(0).unwrap()),
+        State::Done => None
+    }
+}
+
+/// Saves the robots.txt file of the domain to the cache.
+fn cache_robots_txt(domain: &str, content: &str) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR REPLACE INTO robots_cache (domain, robots_txt, cached_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, domain).unwrap();
+    db.bind(2, content).unwrap();
+
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod robots_txt_cache_tests {
+    use crate::robots_txt_cache::{fetch_robots_txt, is_crawl_allowed, FetchError, RobotsTxt};
+
+    #[test]
+    fn test_is_crawl_allowed() {
+        let robots = RobotsTxt {
+            domain: "example.com".to_string(),
+            content: "User-agent: *\nDisallow: /private/\n\nUser-agent: SparkleScannerBot\nDisallow: /secret/".to_string(),
+        };
+
+        assert!(is_crawl_allowed(&robots, "https://example.com/"));
+        assert!(is_crawl_allowed(&robots, "https://example.com/private/page"));
+        assert!(!is_crawl_allowed(&robots, "https://example.com/secret/page"));
+    }
+
+    #[test]
+    fn test_empty_robots_allows_everything() {
+        let robots = RobotsTxt {
+            domain: "example.com".to_string(),
+            content: String::new(),
+        };
+
+        assert!(is_crawl_allowed(&robots, "https://example.com/anything"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_robots_txt_rejects_invalid_urls() {
+        assert!(matches!(fetch_robots_txt("not a url").await, Err(FetchError::InvalidUrl(_))));
+        assert!(matches!(fetch_robots_txt("mailto:admin@example.com").await, Err(FetchError::InvalidUrl(_))));
+    }
+}
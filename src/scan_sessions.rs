@@ -0,0 +1,184 @@
+use sqlite3::State;
+
+use crate::database;
+
+/// The maximum number of links that can be submitted to a session.
+pub const MAX_SESSION_LINKS: u32 = 50;
+
+/// Represents the result of submitting a link to a session.
+#[derive(PartialEq, Debug)]
+pub enum SessionLinkStatus {
+    Added,
+    /// The link has already been submitted to the session.
+    AlreadySubmitted,
+    /// The session already has `MAX_SESSION_LINKS` links.
+    SessionFull
+}
+
+/// Represents a link submitted to a group scan session.
+pub struct SessionLink {
+    pub user_id: u64,
+    pub link: String,
+}
+
+/// Starts a scan session in the group.
+///
+/// # Arguments
+///
+/// * `group_id` - The ID of the group chat.
+/// * `started_by` - The ID of the user who started the session.
+///
+/// # Returns
+///
+/// The ID of the new session, or `None` if the group already has an active session.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn start_session(group_id: i64, started_by: u64) -> Option<i64> {
+    if get_active_session(group_id).is_some() {
+        return None;
+    }
+
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO scan_sessions (group_id, started_by, started_at) VALUES (?, ?, datetime('now'))").unwrap();
+
+    db.bind(1, group_id).unwrap();
+    db.bind(2, started_by as i64).unwrap();
+    db.next().unwrap();
+
+    get_active_session(group_id)
+}
+
+/// Returns the ID of the active scan session of the group, or `None` if the group has no active session.
+///
+/// # Arguments
+///
+/// * `group_id` - The ID of the group chat.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_active_session(group_id: i64) -> Option<i64> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT id FROM scan_sessions WHERE group_id = ? AND completed_at IS NULL ORDER BY id DESC LIMIT 1").unwrap();
+
+    db.bind(1, group_id).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => Some(db.read::<i64>(0).unwrap()),
+        State::Done => None
+    }
+}
+
+/// Adds a link submitted by a member of the group to the session.
+///
+/// # Arguments
+///
+/// * `session_id` - The ID of the session.
+/// * `user_id` - The ID of the member who submitted the link.
+/// * `link` - The link.
+///
+/// # Returns
+///
+/// Whether the link was added, or why it was not.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn add_session_link(session_id: i64, user_id: u64, link: &str) -> SessionLinkStatus {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT 1 FROM session_links WHERE session_id = ? AND link = ?").unwrap();
+
+    db.bind(1, session_id).unwrap();
+    db.bind(2, link).unwrap();
+
+    if let State::Row = db.next().unwrap() {
+        return SessionLinkStatus::AlreadySubmitted;
+    }
+
+    let mut db = connection.prepare("SELECT COUNT(*) FROM session_links WHERE session_id = ?").unwrap();
+
+    db.bind(1, session_id).unwrap();
+    db.next().unwrap();
+
+    if db.read::<i64>(0).unwrap() >= MAX_SESSION_LINKS as i64 {
+        return SessionLinkStatus::SessionFull;
+    }
+
+    let mut db = connection.prepare("INSERT INTO session_links (session_id, user_id, link) VALUES (?, ?, ?)").unwrap();
+
+    db.bind(1, session_id).unwrap();
+    db.bind(2, user_id as i64).unwrap();
+    db.bind(3, link).unwrap();
+    db.next().unwrap();
+
+    SessionLinkStatus::Added
+}
+
+/// Ends the session.
+///
+/// # Arguments
+///
+/// * `session_id` - The ID of the session.
+///
+/// # Returns
+///
+/// The links submitted to the session in the order they were submitted.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn complete_session(session_id: i64) -> Vec<SessionLink> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("UPDATE scan_sessions SET completed_at = datetime('now') WHERE id = ?").unwrap();
+
+    db.bind(1, session_id).unwrap();
+    db.next().unwrap();
+
+    let mut db = connection.prepare("SELECT user_id, link FROM session_links WHERE session_id = ? ORDER BY rowid").unwrap();
+
+    db.bind(1, session_id).unwrap();
+
+    let mut vec: Vec<SessionLink> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(SessionLink {
+            user_id: db.read::<i64>(0).unwrap() as u64,
+            link: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod scan_sessions_tests {
+    use crate::scan_sessions::*;
+
+    #[test]
+    fn test_scan_session() {
+        let session_id = start_session(-100245, 1).unwrap();
+
+        assert_eq!(start_session(-100245, 2), None);
+        assert_eq!(add_session_link(session_id, 1, "https://one.example.com"), SessionLinkStatus::Added);
+        assert_eq!(add_session_link(session_id, 2, "https://two.example.com"), SessionLinkStatus::Added);
+        assert_eq!(add_session_link(session_id, 2, "https://one.example.com"), SessionLinkStatus::AlreadySubmitted);
+
+        for i in 2..MAX_SESSION_LINKS {
+            add_session_link(session_id, 1, &format!("https://{i}.example.com"));
+        }
+
+        assert_eq!(add_session_link(session_id, 1, "https://too-many.example.com"), SessionLinkStatus::SessionFull);
+
+        let links = complete_session(session_id);
+
+        assert_eq!(links.len(), MAX_SESSION_LINKS as usize);
+        assert_eq!(links[1].user_id, 2);
+        assert_eq!(get_active_session(-100245), None);
+    }
+}
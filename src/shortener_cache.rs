@@ -0,0 +1,108 @@
+use std::env;
+use sqlite3::State;
+
+use crate::database;
+
+/// How long a resolved short link is kept in the cache if `SHORTENER_CACHE_TTL_HOURS` is not set.
+const DEFAULT_SHORTENER_CACHE_TTL_HOURS: u32 = 24;
+
+/// Represents a short link that has already been resolved.
+pub struct CachedResolution {
+    pub resolved_url: String,
+    pub hop_count: u8,
+    pub chain: Vec<String>,
+}
+
+/// Returns the number of hours a resolved short link stays in the cache.
+///
+/// The value is taken from the `SHORTENER_CACHE_TTL_HOURS` environment variable, 24 hours by default.
+fn get_cache_ttl_hours() -> u32 {
+    env::var("SHORTENER_CACHE_TTL_HOURS").ok()
+        .and_then(|hours| hours.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_SHORTENER_CACHE_TTL_HOURS)
+}
+
+/// Returns the resolved URL for the short link if it was resolved recently.
+///
+/// Entries older than the cache TTL are ignored.
+///
+/// # Arguments
+///
+/// * `short_url` - The short link.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_cached_resolution(short_url: &str) -> Option<CachedResolution> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT resolved_url, hop_count, redirect_chain_json FROM shortener_cache WHERE short_url = ? AND resolved_at > datetime('now', ?)").unwrap();
+
+    db.bind(1, short_url).unwrap();
+    db.bind(2, format!("-{} hours", get_cache_ttl_hours()).as_str()).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => {
+            let resolved_url = db.read::<String>(0).unwrap();
+
+            // Entries cached before the chains were stored only know the ends of the chain
+            let chain = db.read::<String>(2).ok()
+                .and_then(|chain| serde_json::from_str::<Vec<String>>(&chain).ok())
+                .unwrap_or_else(|| vec![short_url.to_string(), resolved_url.clone()]);
+
+            Some(CachedResolution {
+                resolved_url,
+                hop_count: db.read::<i64>(1).unwrap() as u8,
+                chain,
+            })
+        }
+        State::Done => None
+    }
+}
+
+/// Saves the resolved URL of the short link to the cache.
+///
+/// # Arguments
+///
+/// * `short_url` - The short link.
+/// * `resolved_url` - The URL the short link leads to.
+/// * `hop_count` - The number of redirects between the short link and the resolved URL.
+/// * `chain` - All URLs from the short link to the resolved URL.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn cache_resolution(short_url: &str, resolved_url: &str, hop_count: u8, chain: &[String]) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT OR REPLACE INTO shortener_cache (short_url, resolved_url, resolved_at, hop_count, redirect_chain_json) VALUES (?, ?, datetime('now'), ?, ?)").unwrap();
+
+    db.bind(1, short_url).unwrap();
+    db.bind(2, resolved_url).unwrap();
+    db.bind(3, hop_count as i64).unwrap();
+    db.bind(4, serde_json::to_string(chain).unwrap().as_str()).unwrap();
+
+    db.next().unwrap();
+}
+
+#[cfg(test)]
+mod shortener_cache_tests {
+    use crate::shortener_cache::*;
+
+    #[test]
+    fn test_cache_resolution_keeps_chain() {
+        let chain = vec![
+            "https://bit.ly/cache-test".to_string(),
+            "https://redirector.example/r?to=final".to_string(),
+            "https://final.example/".to_string(),
+        ];
+
+        cache_resolution("https://bit.ly/cache-test", "https://final.example/", 2, &chain);
+
+        let cached = get_cached_resolution("https://bit.ly/cache-test").unwrap();
+
+        assert_eq!(cached.resolved_url, "https://final.example/");
+        assert_eq!(cached.hop_count, 2);
+        assert_eq!(cached.chain, chain);
+    }
+}
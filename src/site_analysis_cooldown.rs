@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+
+use crate::website;
+use crate::website::SiteInformation;
+
+/// How long the result of a site analysis is served to everyone who asks about the same URL.
+const SITE_ANALYSIS_COOLDOWN: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    /// The time of the last analysis of every URL together with its result.
+    static ref SITE_ANALYSIS_CACHE: Mutex<HashMap<String, (Instant, Arc<SiteInformation>)>> = Mutex::new(HashMap::new());
+}
+
+/// Analyzes the site, but not more often than once per `SITE_ANALYSIS_COOLDOWN` for the same URL.
+///
+/// When many users check the same site at once (for example, a link spreading in group chats),
+/// only the first request reaches the site, and the rest get the same result.
+/// Failed analyses are not cached.
+///
+/// # Arguments
+///
+/// * `url` - A string slice that holds the URL of the site.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the information about the site.
+/// * An `Err` variant containing a `reqwest::Error` if an error occurs during the analysis.
+pub async fn get_site_information(url: &str) -> Result<Arc<SiteInformation>, reqwest::Error> {
+    if let Some(site_information) = get_recent_analysis(url) {
+        info!("The site information was taken from the cache: {}", url);
+        return Ok(site_information);
+    }
+
+    let site_information = Arc::new(website::get_site_information(url).await?);
+    store_analysis(url, site_information.clone());
+
+    Ok(site_information)
+}
+
+/// Returns the result of the analysis of the URL if it was done less than `SITE_ANALYSIS_COOLDOWN` ago.
+fn get_recent_analysis(url: &str) -> Option<Arc<SiteInformation>> {
+    let cache = SITE_ANALYSIS_CACHE.lock().unwrap();
+
+    cache.get(url)
+        .filter(|(analyzed_at, _)| analyzed_at.elapsed() < SITE_ANALYSIS_COOLDOWN)
+        .map(|(_, site_information)| site_information.clone())
+}
+
+/// Saves the result of the analysis of the URL and forgets the results whose cooldown is over.
+fn store_analysis(url: &str, site_information: Arc<SiteInformation>) {
+    let mut cache = SITE_ANALYSIS_CACHE.lock().unwrap();
+
+    cache.retain(|_, (analyzed_at, _)| analyzed_at.elapsed() < SITE_ANALYSIS_COOLDOWN);
+    cache.insert(url.to_string(), (Instant::now(), site_information));
+}
+
+#[cfg(test)]
+mod site_analysis_cooldown_tests {
+    use crate::site_analysis_cooldown::*;
+
+    #[test]
+    fn test_recent_analysis_is_reused() {
+        let site_information = Arc::new(SiteInformation {
+            status_code: 200,
+            has_robots: 200,
+            has_sitemap: 404,
+            duration: 120,
+            crawl_allowed: true,
+            ip_records: Vec::new(),
+            csp: None,
+            certificate: None,
+        });
+
+        store_analysis("https://cooldown-test.example/", site_information);
+
+        assert_eq!(get_recent_analysis("https://cooldown-test.example/").unwrap().status_code, 200);
+        assert!(get_recent_analysis("https://cooldown-test.example/other").is_none());
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::io;
+use checkssl::{Cert, CheckSSL};
+use chrono::Utc;
+use http::Uri;
+use serde::Serialize;
+
+/// The number of days before the expiry of a certificate from which it is considered invalid.
+pub const CERTIFICATE_EXPIRY_WARNING_DAYS: i32 = 7;
+
+/// Represents the result of verifying the certificate chain of an HTTPS site.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct TlsVerification {
+    /// The chain leads to a trusted root certificate.
+    pub chain_valid: bool,
+    pub cert_expired: bool,
+    /// The certificate is not issued by a known authority, which is usually the case for self-signed certificates.
+    pub cert_self_signed: bool,
+    /// The number of days until the certificate expires, or 0 if the chain is invalid.
+    pub days_until_expiry: i32,
+}
+
+/// Represents the reasons the certificate of a site could not be verified.
+#[derive(Debug)]
+pub enum TlsError {
+    /// The URL has no host.
+    InvalidUrl,
+    /// The connection to the site failed before the certificate was received.
+    Connection(io::Error)
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::InvalidUrl => write!(f, "the URL has no host"),
+            TlsError::Connection(err) => write!(f, "failed to connect to the site: {err}")
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+/// Connects to the site and verifies its certificate chain against the trusted root certificates.
+///
+/// # Arguments
+///
+/// * `url` - The HTTPS URL of the site.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the result of the verification, also if the chain is invalid.
+/// * An `Err` variant containing a `TlsError` if the certificate could not be received.
+pub async fn verify_tls_chain(url: &str) -> Result<TlsVerification, TlsError> {
+    let uri = url.parse::<Uri>().map_err(|_| TlsError::InvalidUrl)?;
+    let host = uri.host().ok_or(TlsError::InvalidUrl)?.to_string();
+
+    // The certificate is checked with a blocking connection
+    let result = tokio::task::spawn_blocking(move || CheckSSL::from_domain(&host)).await
+        .map_err(|err| TlsError::Connection(io::Error::other(err)))?;
+
+    verification_from_result(result)
+}
+
+/// Converts the result of `CheckSSL::from_domain` into the result of the verification.
+///
+/// A handshake with an untrusted certificate fails with an `InvalidData` error that names the reason,
+/// for example `invalid certificate: UnknownIssuer`.
+fn verification_from_result(result: Result<Cert, io::Error>) -> Result<TlsVerification, TlsError> {
+    match result {
+        Ok(cert) => Ok(TlsVerification {
+            chain_valid: true,
+            cert_expired: !cert.server.is_valid,
+            cert_self_signed: false,
+            days_until_expiry: (cert.server.not_after - Utc::now()).num_days() as i32,
+        }),
+        Err(err) if err.kind() == io::ErrorKind::InvalidData && err.to_string().contains("invalid certificate") => {
+            let description = err.to_string();
+
+            Ok(TlsVerification {
+                chain_valid: false,
+                cert_expired: description.contains("CertExpired"),
+                cert_self_signed: description.contains("UnknownIssuer"),
+                days_until_expiry: 0,
+            })
+        }
+        Err(err) => Err(TlsError::Connection(err))
+    }
+}
+
+/// Checks whether the certificate should not be trusted: the chain is invalid or the certificate expires soon.
+pub fn is_certificate_invalid(verification: &TlsVerification) -> bool {
+    !verification.chain_valid || verification.cert_expired || verification.days_until_expiry < CERTIFICATE_EXPIRY_WARNING_DAYS
+}
+
+#[cfg(test)]
+mod tls_verification_tests {
+    use crate::tls_verification::*;
+
+    #[test]
+    fn test_verification_from_result() {
+        let verification = verification_from_result(Err(io::Error::new(io::ErrorKind::InvalidData, "invalid certificate: UnknownIssuer"))).unwrap();
+
+        assert!(!verification.chain_valid);
+        assert!(verification.cert_self_signed);
+        assert!(!verification.cert_expired);
+        assert!(is_certificate_invalid(&verification));
+
+        let verification = verification_from_result(Err(io::Error::new(io::ErrorKind::InvalidData, "invalid certificate: CertExpired"))).unwrap();
+
+        assert!(verification.cert_expired);
+
+        assert!(verification_from_result(Err(io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"))).is_err());
+    }
+
+    #[test]
+    fn test_is_certificate_invalid() {
+        let verification = TlsVerification {
+            chain_valid: true,
+            cert_expired: false,
+            cert_self_signed: false,
+            days_until_expiry: 90,
+        };
+
+        assert!(!is_certificate_invalid(&verification));
+        assert!(is_certificate_invalid(&TlsVerification { days_until_expiry: 3, ..verification }));
+    }
+}
@@ -0,0 +1,154 @@
+use percent_encoding::percent_decode_str;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::website;
+
+/// Characters that are not displayed, but make two identical-looking URLs different.
+const ZERO_WIDTH_CHARACTERS: [char; 6] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}', '\u{00AD}'];
+
+/// How many times a URL is percent-decoded at most. Legitimate URLs are never encoded this many times.
+const MAX_DECODING_ROUNDS: usize = 10;
+
+/// Query parameters added by advertising and analytics services, which do not change the page.
+const TRACKING_PARAMETERS: [&str; 12] = ["fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_ga", "_openstat"];
+
+/// Reverts the tricks used to hide the real address from simple detectors.
+///
+/// The following steps are applied in order:
+/// * zero-width characters are removed;
+/// * the URL is percent-decoded until it stops changing, which also reverts double encoding;
+/// * the URL is normalized to the Unicode NFC form;
+/// * the punycode labels of the host are decoded, so `xn--` domains show their real characters.
+///
+/// # Arguments
+///
+/// * `url` - The URL.
+///
+/// # Returns
+///
+/// The URL as a human would read it. It may contain non-ASCII characters, use `normalize_url` to store it.
+pub fn deobfuscate_url(url: &str) -> String {
+    let mut url: String = url.chars().filter(|char| !ZERO_WIDTH_CHARACTERS.contains(char)).collect();
+
+    for _ in 0..MAX_DECODING_ROUNDS {
+        let decoded = percent_decode_str(&url).decode_utf8_lossy().to_string();
+
+        if decoded == url {
+            break;
+        }
+
+        url = decoded;
+    }
+
+    let url: String = url.nfc().collect();
+
+    map_host(&url, |host| idna::domain_to_unicode(host).0)
+}
+
+/// Brings the URL entered by a user to the form it is stored in.
+///
+/// The URL is deobfuscated, gets the `https` scheme if it has no scheme,
+/// and its host is converted back to lowercase ASCII (punycode), which every HTTP client understands.
+///
+/// # Arguments
+///
+/// * `url` - The URL entered by a user.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(normalize_url(" EX%41MPLE.com/login "), "https://example.com/login");
+/// ```
+pub fn normalize_url(url: &str) -> String {
+    let url = deobfuscate_url(url.trim());
+
+    let url = if website::has_http_or_https(&url) { url } else { format!("https://{url}") };
+
+    map_host(&url, |host| idna::domain_to_ascii(host).unwrap_or_else(|_| host.to_lowercase()))
+}
+
+/// Removes the tracking parameters from the query of the URL, so links to the same page can be recognized.
+///
+/// The `utm_*` parameters and the parameters in `TRACKING_PARAMETERS` are removed, the order of the other parameters is kept.
+///
+/// # Arguments
+///
+/// * `url` - The normalized URL.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(compute_canonical_url("https://example.com/?id=1&utm_source=mail#top"), "https://example.com/?id=1#top");
+/// ```
+pub fn compute_canonical_url(url: &str) -> String {
+    let (url, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, "")
+    };
+
+    let Some((path, query)) = url.split_once('?') else {
+        return format!("{url}{fragment}");
+    };
+
+    let parameters: Vec<&str> = query.split('&')
+        .filter(|parameter| {
+            let name = parameter.split('=').next().unwrap_or_default().to_lowercase();
+            !parameter.is_empty() && !name.starts_with("utm_") && !TRACKING_PARAMETERS.contains(&name.as_str())
+        })
+        .collect();
+
+    if parameters.is_empty() {
+        format!("{path}{fragment}")
+    }
+    else {
+        format!("{path}?{}{fragment}", parameters.join("&"))
+    }
+}
+
+/// Replaces the host of the URL with the result of the function.
+///
+/// URLs without a scheme, and URLs with an IPv6 address as the host, are returned unchanged.
+fn map_host(url: &str, f: impl Fn(&str) -> String) -> String {
+    let Some(scheme_end) = url.find("://").map(|index| index + 3) else {
+        return url.to_string();
+    };
+
+    let authority_end = url[scheme_end..].find(['/', '?', '#']).map(|index| scheme_end + index).unwrap_or(url.len());
+    let host_start = url[scheme_end..authority_end].rfind('@').map(|index| scheme_end + index + 1).unwrap_or(scheme_end);
+
+    if url[host_start..].starts_with('[') {
+        return url.to_string();
+    }
+
+    let host_end = url[host_start..authority_end].find(':').map(|index| host_start + index).unwrap_or(authority_end);
+
+    format!("{}{}{}", &url[..host_start], f(&url[host_start..host_end]), &url[host_end..])
+}
+
+#[cfg(test)]
+mod url_normalizer_tests {
+    use crate::url_normalizer::*;
+
+    #[test]
+    fn test_deobfuscate_url() {
+        assert_eq!(deobfuscate_url("https://ex%61mple.com/%2541dmin"), "https://example.com/Admin");
+        assert_eq!(deobfuscate_url("https://exa\u{200B}mple.com/"), "https://example.com/");
+        assert_eq!(deobfuscate_url("https://xn--e1afmkfd.xn--p1ai:8080/"), "https://пример.рф:8080/");
+        assert_eq!(deobfuscate_url("https://example.com/cafe\u{301}"), "https://example.com/caf\u{e9}");
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalize_url(" EX%41MPLE.com/login "), "https://example.com/login");
+        assert_eq!(normalize_url("пример.рф"), "https://xn--e1afmkfd.xn--p1ai");
+        assert_eq!(normalize_url("http://user@Example.com:8080/?q=1"), "http://user@example.com:8080/?q=1");
+    }
+
+    #[test]
+    fn test_compute_canonical_url() {
+        assert_eq!(compute_canonical_url("https://example.com/?id=1&utm_source=mail#top"), "https://example.com/?id=1#top");
+        assert_eq!(compute_canonical_url("https://example.com/page?UTM_Campaign=x&fbclid=abc"), "https://example.com/page");
+        assert_eq!(compute_canonical_url("https://example.com/page?b=2&a=1"), "https://example.com/page?b=2&a=1");
+        assert_eq!(compute_canonical_url("https://example.com/page"), "https://example.com/page");
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use sqlite3::State;
+
+use crate::database;
+
+/// The format of the timestamps created by SQLite's `datetime('now')`.
+pub const SQLITE_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Represents the last interaction of a user with the bot.
+pub struct UserActivity {
+    pub user_id: u64,
+    pub last_seen: DateTime<Utc>,
+    /// The command the user sent last, or `message` if it was an ordinary message.
+    pub last_command: String,
+}
+
+/// Saves the last interaction of the user with the bot.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `activity` - The interaction.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn record_user_activity(user_id: u64, activity: UserActivity) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO user_settings (user_id, last_seen, last_command) VALUES (?, ?, ?) \
+        ON CONFLICT(user_id) DO UPDATE SET last_seen = excluded.last_seen, last_command = excluded.last_command").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, activity.last_seen.format(SQLITE_DATETIME_FORMAT).to_string().as_str()).unwrap();
+    db.bind(3, activity.last_command.as_str()).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Saves the Telegram username of the user, so other users can refer to them by it.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `username` - The username without `@`, or `None` if the user has none.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn record_username(user_id: u64, username: Option<&str>) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO user_settings (user_id, username) VALUES (?, ?) \
+        ON CONFLICT(user_id) DO UPDATE SET username = excluded.username").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    match username {
+        Some(username) => db.bind(2, username).unwrap(),
+        None => db.bind(2, ()).unwrap()
+    }
+
+    db.next().unwrap();
+}
+
+/// Returns the ID of the user with the Telegram username. Usernames are compared case-insensitively.
+///
+/// # Arguments
+///
+/// * `username` - The username, with or without `@`.
+///
+/// # Returns
+///
+/// `None` if no user with this username has interacted with the bot.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn find_user_by_username(username: &str) -> Option<u64> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT user_id FROM user_settings WHERE username = ? COLLATE NOCASE").unwrap();
+
+    db.bind(1, username.trim().trim_start_matches('@')).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => Some(db.read::<i64>(0).unwrap() as u64),
+        State::Done => None
+    }
+}
+
+/// Returns the last interaction of the user with the bot.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Returns
+///
+/// `None` if the user has not interacted with the bot since the activity started being tracked.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_user_activity(user_id: u64) -> Option<UserActivity> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT last_seen, last_command FROM user_settings WHERE user_id = ? AND last_seen IS NOT NULL").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => {
+            let last_seen = NaiveDateTime::parse_from_str(&db.read::<String>(0).unwrap(), SQLITE_DATETIME_FORMAT).ok()?;
+
+            Some(UserActivity {
+                user_id,
+                last_seen: last_seen.and_utc(),
+                last_command: db.read::<String>(1).unwrap_or_default(),
+            })
+        }
+        State::Done => None
+    }
+}
+
+/// Returns the IDs of the users who have not interacted with the bot for the given time.
+///
+/// # Arguments
+///
+/// * `inactive_for` - How long the users have been inactive.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_inactive_users(inactive_for: Duration) -> Vec<u64> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT user_id FROM user_settings WHERE last_seen < ? ORDER BY last_seen").unwrap();
+
+    // The timestamps are stored as text in the same format, so they can be compared as strings
+    db.bind(1, (Utc::now() - inactive_for).format(SQLITE_DATETIME_FORMAT).to_string().as_str()).unwrap();
+
+    let mut vec: Vec<u64> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        vec.push(db.read::<i64>(0).unwrap() as u64);
+    }
+
+    vec
+}
+
+#[cfg(test)]
+mod user_activity_tests {
+    use crate::user_activity::*;
+
+    #[test]
+    fn test_get_inactive_users() {
+        record_user_activity(91001, UserActivity {
+            user_id: 91001,
+            last_seen: Utc::now() - Duration::days(40),
+            last_command: "/addlink".to_string(),
+        });
+        record_user_activity(91002, UserActivity {
+            user_id: 91002,
+            last_seen: Utc::now(),
+            last_command: "message".to_string(),
+        });
+
+        let inactive_users = get_inactive_users(Duration::days(30));
+
+        assert!(inactive_users.contains(&91001));
+        assert!(!inactive_users.contains(&91002));
+        assert_eq!(get_user_activity(91001).unwrap().last_command, "/addlink");
+    }
+}
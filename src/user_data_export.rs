@@ -0,0 +1,388 @@
+use chrono::Utc;
+use serde::Serialize;
+use sqlite3::State;
+
+use crate::{database, link_flags};
+use crate::link_flags::LinkFlag;
+use crate::privacy_audit::{self, PrivacyAuditEntry};
+
+/// Represents everything the bot stores about a user, for the user to download.
+#[derive(Serialize)]
+pub struct UserDataExport {
+    pub user_id: u64,
+    /// The time the export was generated, in RFC 3339 format.
+    pub generated_at: String,
+    pub settings: Option<SettingsEntry>,
+    pub links: Vec<LinkEntry>,
+    /// The results of the hourly checks of the user's links, the oldest first.
+    pub health_checks: Vec<LinkHealthCheckEntry>,
+    pub deleted_links: Vec<DeletedLinkEntry>,
+    /// The unfinished dialogue with the user, if there is one.
+    pub dialogue: Option<DialogueEntry>,
+    /// The scan sessions the user started in groups.
+    pub started_sessions: Vec<ScanSessionEntry>,
+    /// The links the user submitted to scan sessions in groups.
+    pub session_links: Vec<SessionLinkEntry>,
+    /// The messages the bot has sent or is going to send to the user.
+    pub messages: Vec<MessageEntry>,
+    /// How many times the user has viewed each of their links.
+    pub link_views: Vec<LinkViewEntry>,
+    /// The reminders about changes of the check results of the user's links.
+    pub reminders: Vec<ReminderEntry>,
+    /// The unanswered requests to share the reports the user has sent or received.
+    pub share_requests: Vec<ShareRequestEntry>,
+    /// The users the user shares the reports with.
+    pub share_agreements: Vec<ShareAgreementEntry>,
+    /// The accesses of administrators to the user's data.
+    pub data_accesses: Vec<PrivacyAuditEntry>,
+}
+
+/// Represents the stored settings and the last activity of a user.
+#[derive(Serialize)]
+pub struct SettingsEntry {
+    pub privacy_mode: bool,
+    pub last_seen: Option<String>,
+    pub last_command: Option<String>,
+    pub username: Option<String>,
+}
+
+/// Represents a stored link of a user together with the information collected about it.
+#[derive(Serialize)]
+pub struct LinkEntry {
+    /// The link, or its SHA-256 hash if it was added in the privacy mode.
+    pub link: String,
+    pub is_hashed: bool,
+    pub created_at: Option<String>,
+    pub domain_family: Option<String>,
+    pub page_title: Option<String>,
+    pub favicon_hash: Option<String>,
+    /// The URLs from the short link to the link, if the link was added as a short link.
+    pub redirect_chain: Vec<String>,
+    pub flags: Vec<LinkFlag>,
+}
+
+/// Represents a stored result of checking one of the user's links.
+#[derive(Serialize)]
+pub struct LinkHealthCheckEntry {
+    pub link: String,
+    pub checked_at: String,
+    pub status_code: Option<u16>,
+    pub redirect_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Represents a link the user has deleted.
+#[derive(Serialize)]
+pub struct DeletedLinkEntry {
+    pub link: String,
+    pub deleted_at: String,
+}
+
+/// Represents the stored state of the dialogue with the user.
+#[derive(Serialize)]
+pub struct DialogueEntry {
+    /// The state of the dialogue, in JSON format.
+    pub state: String,
+    pub updated_at: String,
+}
+
+/// Represents a scan session the user started.
+#[derive(Serialize)]
+pub struct ScanSessionEntry {
+    pub session_id: i64,
+    pub group_id: i64,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// Represents a link the user submitted to a scan session.
+#[derive(Serialize)]
+pub struct SessionLinkEntry {
+    pub session_id: i64,
+    pub link: String,
+}
+
+/// Represents a message for the user stored by the message queue.
+#[derive(Serialize)]
+pub struct MessageEntry {
+    pub message: String,
+    pub created_at: String,
+    pub sent: bool,
+}
+
+/// Represents how many times the user has viewed one of their links.
+#[derive(Serialize)]
+pub struct LinkViewEntry {
+    pub link: String,
+    pub view_count: u64,
+    pub last_viewed_at: String,
+}
+
+/// Represents a reminder the user set for one of their links.
+#[derive(Serialize)]
+pub struct ReminderEntry {
+    pub link: String,
+    /// The change of the check result the reminder waits for.
+    pub trigger_on: String,
+    /// The time the reminder was sent, or `None` if it has not been sent yet.
+    pub reminded_at: Option<String>,
+}
+
+/// Represents an unanswered request to share the reports.
+#[derive(Serialize)]
+pub struct ShareRequestEntry {
+    pub requester_id: u64,
+    pub target_id: u64,
+    pub requested_at: String,
+}
+
+/// Represents an agreement of the user to share the reports with another user.
+#[derive(Serialize)]
+pub struct ShareAgreementEntry {
+    pub partner_id: u64,
+    pub agreed_at: String,
+}
+
+/// Collects all data stored about the user from every table.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn export_all_user_data(user_id: u64) -> UserDataExport {
+    let connection = database::open_connection();
+
+    let mut db = connection.prepare("SELECT privacy_mode, last_seen, last_command, username FROM user_settings WHERE user_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let settings = match db.next().unwrap() {
+        State::Row => Some(SettingsEntry {
+            privacy_mode: db.read::<i64>(0).unwrap() != 0,
+            last_seen: db.read::<String>(1).ok(),
+            last_command: db.read::<String>(2).ok(),
+            username: db.read::<String>(3).ok(),
+        }),
+        State::Done => None
+    };
+
+    let mut db = connection.prepare("SELECT link, is_hashed, created_at, domain_family, page_title, favicon_hash, redirect_chain_json FROM links \
+        WHERE user_id = ? ORDER BY rowid").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut links: Vec<LinkEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let link = db.read::<String>(0).unwrap();
+
+        links.push(LinkEntry {
+            flags: link_flags::get_link_flags(&link),
+            link,
+            is_hashed: db.read::<i64>(1).unwrap() != 0,
+            created_at: db.read::<String>(2).ok(),
+            domain_family: db.read::<String>(3).ok(),
+            page_title: db.read::<String>(4).ok(),
+            favicon_hash: db.read::<String>(5).ok(),
+            redirect_chain: db.read::<String>(6).ok()
+                .and_then(|redirect_chain_json| serde_json::from_str(&redirect_chain_json).ok())
+                .unwrap_or_default(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT link, checked_at, status_code, redirect_url, error FROM link_health_checks \
+        WHERE link IN (SELECT link FROM links WHERE user_id = ?) ORDER BY id").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut health_checks: Vec<LinkHealthCheckEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        health_checks.push(LinkHealthCheckEntry {
+            link: db.read::<String>(0).unwrap(),
+            checked_at: db.read::<String>(1).unwrap(),
+            status_code: db.read::<String>(2).ok().and_then(|status_code| status_code.parse().ok()),
+            redirect_url: db.read::<String>(3).ok(),
+            error: db.read::<String>(4).ok(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT link, deleted_at FROM link_deletions WHERE user_id = ? ORDER BY deleted_at").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut deleted_links: Vec<DeletedLinkEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        deleted_links.push(DeletedLinkEntry {
+            link: db.read::<String>(0).unwrap(),
+            deleted_at: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    // The dialogues are stored by the ID of the chat, which is the ID of the user in private chats
+    let mut db = connection.prepare("SELECT state_json, updated_at FROM dialogues WHERE chat_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let dialogue = match db.next().unwrap() {
+        State::Row => Some(DialogueEntry {
+            state: db.read::<String>(0).unwrap(),
+            updated_at: db.read::<String>(1).unwrap(),
+        }),
+        State::Done => None
+    };
+
+    let mut db = connection.prepare("SELECT id, group_id, started_at, completed_at FROM scan_sessions WHERE started_by = ? ORDER BY id").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut started_sessions: Vec<ScanSessionEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        started_sessions.push(ScanSessionEntry {
+            session_id: db.read::<i64>(0).unwrap(),
+            group_id: db.read::<i64>(1).unwrap(),
+            started_at: db.read::<String>(2).unwrap(),
+            completed_at: db.read::<String>(3).ok(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT session_id, link FROM session_links WHERE user_id = ? ORDER BY rowid").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut session_links: Vec<SessionLinkEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        session_links.push(SessionLinkEntry {
+            session_id: db.read::<i64>(0).unwrap(),
+            link: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT message, created_at, sent FROM outbound_messages WHERE user_id = ? ORDER BY id").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut messages: Vec<MessageEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        messages.push(MessageEntry {
+            message: db.read::<String>(0).unwrap(),
+            created_at: db.read::<String>(1).unwrap(),
+            sent: db.read::<i64>(2).unwrap() != 0,
+        });
+    }
+
+    let mut db = connection.prepare("SELECT link, view_count, last_viewed_at FROM link_view_counts WHERE user_id = ? ORDER BY link").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut link_views: Vec<LinkViewEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        link_views.push(LinkViewEntry {
+            link: db.read::<String>(0).unwrap(),
+            view_count: db.read::<i64>(1).unwrap() as u64,
+            last_viewed_at: db.read::<String>(2).unwrap(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT link, trigger_on, reminded_at FROM link_reminders WHERE user_id = ? ORDER BY id").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut reminders: Vec<ReminderEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        reminders.push(ReminderEntry {
+            link: db.read::<String>(0).unwrap(),
+            trigger_on: db.read::<String>(1).unwrap(),
+            reminded_at: db.read::<String>(2).ok(),
+        });
+    }
+
+    let mut db = connection.prepare("SELECT requester_id, target_id, requested_at FROM share_requests \
+        WHERE requester_id = ?1 OR target_id = ?1 ORDER BY requested_at").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut share_requests: Vec<ShareRequestEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        share_requests.push(ShareRequestEntry {
+            requester_id: db.read::<i64>(0).unwrap() as u64,
+            target_id: db.read::<i64>(1).unwrap() as u64,
+            requested_at: db.read::<String>(2).unwrap(),
+        });
+    }
+
+    // Each agreement is stored once for both users, so the partner is in whichever column the user is not
+    let mut db = connection.prepare("SELECT CASE WHEN user_id_a = ?1 THEN user_id_b ELSE user_id_a END, agreed_at FROM share_agreements \
+        WHERE user_id_a = ?1 OR user_id_b = ?1 ORDER BY agreed_at").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let mut share_agreements: Vec<ShareAgreementEntry> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        share_agreements.push(ShareAgreementEntry {
+            partner_id: db.read::<i64>(0).unwrap() as u64,
+            agreed_at: db.read::<String>(1).unwrap(),
+        });
+    }
+
+    UserDataExport {
+        user_id,
+        generated_at: Utc::now().to_rfc3339(),
+        settings,
+        links,
+        health_checks,
+        deleted_links,
+        dialogue,
+        started_sessions,
+        session_links,
+        messages,
+        link_views,
+        reminders,
+        share_requests,
+        share_agreements,
+        data_accesses: privacy_audit::get_privacy_audit_log(user_id),
+    }
+}
+
+#[cfg(test)]
+mod user_data_export_tests {
+    use crate::{database, link_flags, link_reminders, report_sharing, scan_sessions};
+    use crate::link_reminders::ReminderTrigger;
+    use crate::user_data_export::export_all_user_data;
+
+    #[test]
+    fn test_export_all_user_data() {
+        database::add_link(95001, "https://export.example.com/");
+        database::add_link(95001, "https://export-deleted.example.com/");
+        database::delete_some_links(95001, vec!["https://export-deleted.example.com/"]);
+        link_flags::flag_link("https://export.example.com/", "custom_rule", "Phishing");
+        link_reminders::add_reminder(95001, "https://export.example.com/", ReminderTrigger::Available);
+        report_sharing::add_share_agreement(95001, 95002);
+        report_sharing::add_share_request(95003, 95001);
+        let session_id = scan_sessions::start_session(-95001, 95001).unwrap();
+        database::open_connection().execute("INSERT OR REPLACE INTO dialogues (chat_id, state_json, updated_at) \
+            VALUES (95001, '\"ReceiveLink\"', datetime('now'))").unwrap();
+
+        let export = export_all_user_data(95001);
+
+        assert_eq!(export.links.len(), 1);
+        assert_eq!(export.links[0].link, "https://export.example.com/");
+        assert_eq!(export.links[0].flags.len(), 1);
+        assert_eq!(export.deleted_links.len(), 1);
+        assert_eq!(export.dialogue.as_ref().map(|dialogue| dialogue.state.as_str()), Some("\"ReceiveLink\""));
+        assert_eq!(export.reminders.len(), 1);
+        assert_eq!(export.reminders[0].trigger_on, "available");
+        assert_eq!(export.share_agreements.iter().map(|agreement| agreement.partner_id).collect::<Vec<u64>>(), vec![95002]);
+        assert_eq!(export.share_requests.iter().map(|request| request.requester_id).collect::<Vec<u64>>(), vec![95003]);
+        assert!(export.started_sessions.iter().any(|session| session.session_id == session_id && session.group_id == -95001));
+        assert!(serde_json::to_string(&export).is_ok());
+
+        database::clear_all_links(95001);
+        database::open_connection().execute("DELETE FROM link_deletions WHERE user_id = 95001; \
+            DELETE FROM link_flags WHERE link = 'https://export.example.com/'; \
+            DELETE FROM scan_sessions WHERE started_by = 95001; \
+            DELETE FROM dialogues WHERE chat_id = 95001; \
+            DELETE FROM share_agreements WHERE user_id_a = 95001; \
+            DELETE FROM share_requests WHERE target_id = 95001").unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use sqlite3::State;
+
+use crate::database;
+
+/// The number of items on a page of a list if the user has not chosen another one.
+pub const DEFAULT_LINKS_PER_PAGE: u8 = 10;
+/// The largest number of items on a page of a list a user can choose.
+pub const MAX_LINKS_PER_PAGE: u8 = 20;
+
+/// Represents the settings a user has chosen.
+pub struct UserPreferences {
+    /// New links of the user are stored as SHA-256 hashes instead of plain text.
+    pub privacy_mode: bool,
+    /// The number of items on a page of a list, from 1 to `MAX_LINKS_PER_PAGE`.
+    pub links_per_page: u8,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        UserPreferences {
+            privacy_mode: false,
+            links_per_page: DEFAULT_LINKS_PER_PAGE,
+        }
+    }
+}
+
+/// Returns the settings of the user, or the default settings if the user has not changed them.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_user_preferences(user_id: u64) -> UserPreferences {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT privacy_mode, links_per_page FROM user_settings WHERE user_id = ?").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+
+    match db.next().unwrap() {
+        State::Row => UserPreferences {
+            privacy_mode: db.read::<i64>(0).unwrap() != 0,
+            links_per_page: db.read::<i64>(1).ok()
+                .filter(|links_per_page| (1..=MAX_LINKS_PER_PAGE as i64).contains(links_per_page))
+                .map_or(DEFAULT_LINKS_PER_PAGE, |links_per_page| links_per_page as u8),
+        },
+        State::Done => UserPreferences::default()
+    }
+}
+
+/// Turns the privacy mode of the user on or off.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `privacy_mode` - Whether new links of the user are stored as hashes.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_privacy_mode(user_id: u64, privacy_mode: bool) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO user_settings (user_id, privacy_mode) VALUES (?, ?) \
+        ON CONFLICT(user_id) DO UPDATE SET privacy_mode = excluded.privacy_mode").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, privacy_mode as i64).unwrap();
+
+    db.next().unwrap();
+}
+
+/// Sets the number of items on a page of the lists the user is shown.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `links_per_page` - The number of items on a page, from 1 to `MAX_LINKS_PER_PAGE`.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn set_links_per_page(user_id: u64, links_per_page: u8) {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("INSERT INTO user_settings (user_id, links_per_page) VALUES (?, ?) \
+        ON CONFLICT(user_id) DO UPDATE SET links_per_page = excluded.links_per_page").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, links_per_page as i64).unwrap();
+
+    db.next().unwrap();
+}
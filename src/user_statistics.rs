@@ -0,0 +1,195 @@
+use chrono::{Duration, NaiveDate, Utc};
+use sqlite3::State;
+
+use crate::{database, domain_reputation};
+
+/// The names of the days of the week in the order of SQLite's `strftime('%w')`, which starts with Sunday.
+const WEEKDAYS: [&str; 7] = ["воскресенье", "понедельник", "вторник", "среда", "четверг", "пятница", "суббота"];
+
+/// Represents the statistics of a user's links.
+pub struct DetailedUserStats {
+    pub total_links: u64,
+    /// The number of links with warnings.
+    pub malicious_found: u64,
+    /// The number of links without warnings.
+    pub clean_links: u64,
+    /// The age of the oldest link whose time of adding is known.
+    pub oldest_link_age_days: u32,
+    /// The average reputation of the domains of the user's links, from 0 to 1.
+    pub average_safety_score: f32,
+    /// The day of the week on which the user added the most links, or an empty string if it is unknown.
+    pub most_active_day: String,
+    /// The number of checks of the user's links.
+    pub total_scans_triggered: u64,
+}
+
+/// Collects the statistics of the user's links.
+///
+/// Links stored as hashes are counted, but their domains are unknown, so they do not affect the average safety score.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_user_stats_detailed(user_id: u64) -> DetailedUserStats {
+    let average_safety_score = get_average_safety_score(user_id);
+
+    let connection = database::open_connection();
+
+    let mut db = connection.prepare("SELECT COUNT(DISTINCT link), \
+            COUNT(DISTINCT CASE WHEN link IN (SELECT link FROM link_flags) THEN link END), \
+            COALESCE(CAST(julianday('now') - julianday(MIN(created_at)) AS INTEGER), 0) \
+        FROM links WHERE user_id = ?").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
+    let total_links = db.read::<i64>(0).unwrap() as u64;
+    let malicious_found = db.read::<i64>(1).unwrap() as u64;
+    let oldest_link_age_days = db.read::<i64>(2).unwrap() as u32;
+
+    let mut db = connection.prepare("SELECT CAST(strftime('%w', created_at) AS INTEGER) AS weekday FROM links \
+        WHERE user_id = ? AND created_at IS NOT NULL \
+        GROUP BY weekday \
+        ORDER BY COUNT(*) DESC, weekday \
+        LIMIT 1").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+
+    let most_active_day = match db.next().unwrap() {
+        State::Row => WEEKDAYS[db.read::<i64>(0).unwrap() as usize].to_string(),
+        State::Done => String::new()
+    };
+
+    let mut db = connection.prepare("SELECT COUNT(*) FROM link_health_checks WHERE link IN (SELECT link FROM links WHERE user_id = ?)").unwrap();
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
+    let total_scans_triggered = db.read::<i64>(0).unwrap() as u64;
+
+    DetailedUserStats {
+        total_links,
+        malicious_found,
+        clean_links: total_links - malicious_found,
+        oldest_link_age_days,
+        average_safety_score,
+        most_active_day,
+        total_scans_triggered,
+    }
+}
+
+/// Returns the average reputation of the domains of the user's links that are not stored as hashes, or 0 if there are none.
+fn get_average_safety_score(user_id: u64) -> f32 {
+    let mut domains: Vec<String> = Vec::new();
+
+    // The reputations are cached through another connection, which cannot write while this one is reading
+    {
+        let connection = database::open_connection();
+        let mut db = connection.prepare("SELECT DISTINCT link FROM links WHERE user_id = ? AND is_hashed = 0").unwrap();
+        db.bind(1, user_id as i64).unwrap();
+
+        while let State::Row = db.next().unwrap() {
+            if let Some(domain) = domain_reputation::get_domain(&db.read::<String>(0).unwrap()) {
+                domains.push(domain);
+            }
+        }
+    }
+
+    domains.sort();
+    domains.dedup();
+
+    if domains.is_empty() {
+        return 0.0;
+    }
+
+    domains.iter().map(|domain| domain_reputation::get_domain_reputation(domain).score).sum::<f32>() / domains.len() as f32
+}
+
+/// Returns the number of links the user added on each of the last days, the oldest first.
+///
+/// Days without added links are included with 0, so the result always has `days` entries, the last one being today.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `days` - The number of days, including today.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_user_submission_trend(user_id: u64, days: u32) -> Vec<(NaiveDate, u32)> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare("SELECT date(created_at), COUNT(*) FROM links \
+        WHERE user_id = ? AND date(created_at) > date('now', ?) \
+        GROUP BY date(created_at)").unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, format!("-{} days", days).as_str()).unwrap();
+
+    let mut submissions: Vec<(String, u32)> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        submissions.push((db.read::<String>(0).unwrap(), db.read::<i64>(1).unwrap() as u32));
+    }
+
+    // SQLite's date('now') is in UTC as well
+    let today = Utc::now().date_naive();
+
+    (0..days as i64).rev()
+        .map(|days_ago| today - Duration::days(days_ago))
+        .map(|date| {
+            let date_text = date.format("%Y-%m-%d").to_string();
+            let count = submissions.iter().find(|(day, _)| *day == date_text).map_or(0, |(_, count)| *count);
+
+            (date, count)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod user_statistics_tests {
+    use crate::{database, link_flags};
+    use crate::user_statistics::{get_user_stats_detailed, get_user_submission_trend};
+
+    #[test]
+    fn test_get_user_stats_detailed() {
+        database::add_link(96001, "https://stats.example.com/");
+        database::add_link(96001, "https://stats-flagged.example.com/");
+        link_flags::flag_link("https://stats-flagged.example.com/", "custom_rule", "Phishing");
+
+        let stats = get_user_stats_detailed(96001);
+
+        assert_eq!(stats.total_links, 2);
+        assert_eq!(stats.malicious_found, 1);
+        assert_eq!(stats.clean_links, 1);
+        assert_eq!(stats.oldest_link_age_days, 0);
+        assert!(!stats.most_active_day.is_empty());
+        assert!(stats.average_safety_score > 0.0 && stats.average_safety_score <= 1.0);
+
+        let stats = get_user_stats_detailed(96002);
+
+        assert_eq!(stats.total_links, 0);
+        assert!(stats.most_active_day.is_empty());
+
+        database::clear_all_links(96001);
+        database::open_connection().execute("DELETE FROM link_deletions WHERE user_id = 96001; \
+            DELETE FROM link_flags WHERE link = 'https://stats-flagged.example.com/'").unwrap();
+    }
+
+    #[test]
+    fn test_get_user_submission_trend() {
+        database::add_link(96003, "https://trend.example.com/first");
+        database::add_link(96003, "https://trend.example.com/second");
+
+        let trend = get_user_submission_trend(96003, 7);
+
+        assert_eq!(trend.len(), 7);
+        assert_eq!(trend.iter().map(|(_, count)| count).collect::<Vec<&u32>>(), vec![&0, &0, &0, &0, &0, &0, &2]);
+        assert!(trend.windows(2).all(|days| days[0].0 < days[1].0));
+
+        database::clear_all_links(96003);
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlite3::State;
+
+use crate::database;
+use crate::user_activity::SQLITE_DATETIME_FORMAT;
+
+/// Represents something that happened to one of the user's links.
+#[derive(PartialEq, Debug)]
+pub enum TimelineEvent {
+    LinkAdded {
+        link: String,
+        timestamp: DateTime<Utc>,
+    },
+    ScanCompleted {
+        link: String,
+        /// The status code of the response, or the description of the error if the link could not be checked.
+        result: String,
+        timestamp: DateTime<Utc>,
+    },
+    LinkDeleted {
+        link: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// The events of the links of the user bound to `?1`: the kind of the event, the link, the result of the check and the time.
+const TIMELINE_EVENTS_QUERY: &str = "SELECT 'added', link, NULL, created_at AS timestamp FROM links \
+        WHERE user_id = ?1 AND created_at IS NOT NULL \
+    UNION ALL \
+    SELECT 'scanned', link, COALESCE(CAST(status_code AS TEXT), error, ''), checked_at FROM link_health_checks \
+        WHERE EXISTS (SELECT 1 FROM links WHERE links.user_id = ?1 AND links.link = link_health_checks.link \
+            AND link_health_checks.checked_at >= COALESCE(links.created_at, '')) \
+    UNION ALL \
+    SELECT 'deleted', link, NULL, deleted_at FROM link_deletions \
+        WHERE user_id = ?1";
+
+/// Returns a page of the events of the user's links, the newest first.
+///
+/// Links added before the time of adding was saved have no `LinkAdded` event. Only the checks made while the link
+/// was in the user's list are included, so the checks of deleted links and the checks made before the user added the link are left out.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+/// * `page` - The number of the page, starting from 0.
+/// * `page_size` - The number of events on a page.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn get_user_timeline(user_id: u64, page: u32, page_size: u32) -> Vec<TimelineEvent> {
+    let connection = database::open_connection();
+    let mut db = connection.prepare(format!("{TIMELINE_EVENTS_QUERY} ORDER BY timestamp DESC LIMIT ?2 OFFSET ?3")).unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.bind(2, page_size as i64).unwrap();
+    db.bind(3, page as i64 * page_size as i64).unwrap();
+
+    let mut vec: Vec<TimelineEvent> = Vec::new();
+
+    while let State::Row = db.next().unwrap() {
+        let link = db.read::<String>(1).unwrap();
+        let Ok(timestamp) = NaiveDateTime::parse_from_str(&db.read::<String>(3).unwrap(), SQLITE_DATETIME_FORMAT) else {
+            continue;
+        };
+        let timestamp = timestamp.and_utc();
+
+        vec.push(match db.read::<String>(0).unwrap().as_str() {
+            "added" => TimelineEvent::LinkAdded { link, timestamp },
+            "scanned" => TimelineEvent::ScanCompleted { link, result: db.read::<String>(2).unwrap(), timestamp },
+            _ => TimelineEvent::LinkDeleted { link, timestamp }
+        });
+    }
+
+    vec
+}
+
+/// Returns the number of events of the user's links.
+///
+/// # Arguments
+///
+/// * `user_id` - The ID of the user.
+///
+/// # Panics
+///
+/// This function will panic if the `DATABASE_URL` environment variable is not set
+/// or if there is a problem connecting to the database.
+pub fn count_user_timeline_events(user_id: u64) -> u32 {
+    let connection = database::open_connection();
+    let mut db = connection.prepare(format!("SELECT COUNT(*) FROM ({TIMELINE_EVENTS_QUERY})")).unwrap();
+
+    db.bind(1, user_id as i64).unwrap();
+    db.next().unwrap();
+
+    db.read::<i64>(0).unwrap() as u32
+}
+
+#[cfg(test)]
+mod user_timeline_tests {
+    use crate::database;
+    use crate::link_health::{self, HealthCheckRecord};
+    use crate::user_timeline::*;
+
+    #[test]
+    fn test_get_user_timeline() {
+        database::clear_all_links(94001);
+        database::clear_all_links(94002);
+
+        let connection = database::open_connection();
+        connection.execute("DELETE FROM link_deletions WHERE user_id IN (94001, 94002); \
+            DELETE FROM link_health_checks WHERE link IN ('https://timeline.example.com/', 'https://timeline-two.example.com/')").unwrap();
+
+        // A check made long before the user added the link is not a part of their history
+        connection.execute("INSERT INTO link_health_checks (link, checked_at, status_code) \
+            VALUES ('https://timeline-two.example.com/', '2000-01-01 00:00:00', 200)").unwrap();
+
+        database::add_link(94001, "https://timeline.example.com/");
+        link_health::batch_record_health_checks(&[HealthCheckRecord {
+            link: "https://timeline.example.com/".to_string(),
+            status_code: Some(200),
+            redirect_url: None,
+            error: None,
+        }]).unwrap();
+        database::delete_some_links(94001, vec!["https://timeline.example.com/"]);
+
+        let timeline = get_user_timeline(94001, 0, 10);
+
+        assert_eq!(timeline.len(), 1);
+        assert!(matches!(&timeline[0], TimelineEvent::LinkDeleted { link, .. } if link == "https://timeline.example.com/"));
+
+        database::add_link(94002, "https://timeline-two.example.com/");
+        link_health::batch_record_health_checks(&[HealthCheckRecord {
+            link: "https://timeline-two.example.com/".to_string(),
+            status_code: None,
+            redirect_url: None,
+            error: Some("timeout".to_string()),
+        }]).unwrap();
+
+        let timeline = get_user_timeline(94002, 0, 10);
+
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(count_user_timeline_events(94002), 2);
+        assert!(timeline.iter().any(|event| matches!(event, TimelineEvent::LinkAdded { .. })));
+        assert!(timeline.iter().any(|event| matches!(event, TimelineEvent::ScanCompleted { result, .. } if result == "timeout")));
+        assert_eq!(get_user_timeline(94002, 1, 1).len(), 1);
+        assert!(get_user_timeline(94002, 1, 10).is_empty());
+
+        database::clear_all_links(94002);
+        connection.execute("DELETE FROM link_deletions WHERE user_id IN (94001, 94002); \
+            DELETE FROM link_health_checks WHERE link IN ('https://timeline.example.com/', 'https://timeline-two.example.com/')").unwrap();
+    }
+}
@@ -5,32 +5,64 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use reqwest::Client;
 
+use crate::{csp, reverse_dns, robots_txt_cache, shortener_cache};
+use crate::csp::CspAnalysis;
+use crate::reverse_dns::IpRecord;
+
 const HTTP_OR_HTTPS_REGEX: &str = "^(http|https)://";
 
+/// The maximum number of bytes of a page downloaded to analyze its HTML code, the rest of the page is ignored.
+const MAX_PAGE_HTML_BYTES: usize = 2 * 1024 * 1024;
+
+/// The maximum size of a favicon in bytes, larger files are not downloaded.
+const MAX_FAVICON_BYTES: usize = 256 * 1024;
+
+/// The maximum number of redirects followed when resolving a short link.
+pub const MAX_REDIRECT_HOPS: u8 = 10;
+
+/// Hosts of popular link shortening services.
+const SHORTENER_HOSTS: [&str; 12] = [
+    "bit.ly", "goo.su", "tinyurl.com", "t.co", "ow.ly", "is.gd",
+    "clck.ru", "cutt.ly", "rebrand.ly", "shorturl.at", "s.id", "vk.cc"
+];
+
 /// Represents information about a website.
 pub struct SiteInformation {
     pub status_code: u16,
     pub has_robots: u16,
     pub has_sitemap: u16,
     pub duration: u128,
+    pub crawl_allowed: bool,
+    pub ip_records: Vec<IpRecord>,
+    pub csp: Option<CspAnalysis>,
     pub certificate: Option<Cert>
 }
 
+/// Represents the result of checking the availability of a link.
+pub struct LinkHealth {
+    pub status_code: u16,
+    pub redirect_url: Option<String>,
+}
+
 lazy_static! {
     static ref RE_HTTP_OR_HTTPS: Regex = {
         Regex::new(HTTP_OR_HTTPS_REGEX).unwrap()
     };
 }
 
-/// Sends a GET request to the specified URL and returns the status code.
+/// Sends a GET request to the specified URL and returns the status code and, if the request
+/// was redirected, the URL of the page it was redirected to.
+///
+/// If the robots.txt file of the site does not allow the bot to fetch the page,
+/// a HEAD request is sent instead, so the page content is not downloaded.
 ///
 /// # Arguments
 ///
-/// * `url` - A string slice that holds the URL of the API endpoint.
+/// * `url` - A string slice that holds the URL of the site.
 ///
 /// # Returns
 ///
-/// * An `Ok` variant containing the status code as a `u16` if the request is successful.
+/// * An `Ok` variant containing the `LinkHealth` of the site if the request is successful.
 /// * An `Err` variant containing a `reqwest::Error` if an error occurs during the request.
 ///
 /// # Examples
@@ -40,18 +72,53 @@ lazy_static! {
 ///
 /// async fn example() -> Result<u16, Error> {
 ///     let url = "https://example.com";
-///     let status_code = get_request_code(url).await?;
-///     println!("Status code: {}", status_code);
-///     Ok(status_code)
+///     let health = check_link_health(url).await?;
+///     println!("Status code: {}", health.status_code);
+///     Ok(health.status_code)
 /// }
 /// ```
-pub async fn get_request_code(url: &str) -> Result<u16, reqwest::Error> {
+pub async fn check_link_health(url: &str) -> Result<LinkHealth, reqwest::Error> {
     let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
 
-    let resp = client.get(url).send().await?;
-    let status_code = resp.status().as_u16();
+    let resp = if robots_txt_cache::is_url_crawl_allowed(url).await {
+        client.get(url).send().await?
+    }
+    else {
+        client.head(url).send().await?
+    };
+
+    let final_url = resp.url().to_string();
+    let redirect_url = if final_url.trim_end_matches('/') != url.trim_end_matches('/') {
+        Some(final_url)
+    }
+    else {
+        None
+    };
 
-    Ok(status_code)
+    Ok(LinkHealth {
+        status_code: resp.status().as_u16(),
+        redirect_url,
+    })
+}
+
+/// Checks whether the site redirected the link to another host.
+///
+/// Redirects within the same host (for example, from HTTP to HTTPS or to another page)
+/// and redirects between the host with and without the "www." prefix are considered expected.
+///
+/// # Arguments
+///
+/// * `url` - The link that was requested.
+/// * `redirect_url` - The URL the link was redirected to.
+///
+/// # Returns
+///
+/// * `bool` - "true" if the link was redirected to another host, "false" otherwise.
+pub fn is_unexpected_redirect(url: &str, redirect_url: &str) -> bool {
+    let host = |url: &str| url.parse::<Uri>().ok()
+        .and_then(|uri| uri.host().map(|host| host.trim_start_matches("www.").to_lowercase()));
+
+    host(url) != host(redirect_url)
 }
 
 /// Fetches site information for a given URL.
@@ -63,6 +130,11 @@ pub async fn get_request_code(url: &str) -> Result<u16, reqwest::Error> {
 /// - Existence of SSL certificate for the domain
 /// - Status code of the response for the robots.txt file
 /// - Status code of the response for the sitemap.xml file
+/// - Whether the robots.txt file allows the bot to fetch the page
+/// - IP addresses of the site and their PTR records
+/// - Weaknesses of the Content-Security-Policy header, if the site sends it
+///
+/// If fetching the page is not allowed, only a HEAD request is sent to the page.
 ///
 /// # Arguments
 ///
@@ -74,25 +146,278 @@ pub async fn get_request_code(url: &str) -> Result<u16, reqwest::Error> {
 pub async fn get_site_information(url: &str) -> Result<SiteInformation, reqwest::Error> {
     let client = reqwest::Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
 
+    let crawl_allowed = robots_txt_cache::is_url_crawl_allowed(url).await;
+
     let time_now = std::time::Instant::now();
-    let resp_site = client.get(url).send().await?;
+    let resp_site = if crawl_allowed {
+        client.get(url).send().await?
+    }
+    else {
+        client.head(url).send().await?
+    };
     let elapsed_time = time_now.elapsed();
 
-    let uri = url.parse::<Uri>().unwrap();
-    let cert = CheckSSL::from_domain(uri.host().unwrap());
+    let csp = resp_site.headers().get(reqwest::header::CONTENT_SECURITY_POLICY)
+        .and_then(|header| header.to_str().ok())
+        .map(csp::analyze_csp);
+
+    let (scheme, host) = parse_site_url(&client, url)?;
+    let cert = CheckSSL::from_domain(&host);
 
-    let resp_robots = client.get(format!("{}://{}/robots.txt", uri.scheme_str().unwrap(), uri.host().unwrap())).send().await?;
-    let resp_sitemap = client.get(format!("{}://{}/sitemap.xml", uri.scheme_str().unwrap(), uri.host().unwrap())).send().await?;
+    let resp_robots = client.get(format!("{scheme}://{host}/robots.txt")).send().await?;
+    let resp_sitemap = client.get(format!("{scheme}://{host}/sitemap.xml")).send().await?;
+
+    let ip_records = reverse_dns::fetch_ip_records(&host).await;
+    reverse_dns::store_ip_records(url, &ip_records);
 
     Ok(SiteInformation {
         status_code: resp_site.status().as_u16(),
         duration: elapsed_time.as_millis(),
+        crawl_allowed,
+        ip_records,
+        csp,
         certificate: cert.ok(),
         has_robots: resp_robots.status().as_u16(),
         has_sitemap: resp_sitemap.status().as_u16()
     })
 }
 
+/// Returns the scheme and the host of the URL.
+///
+/// The URL is parsed the same way reqwest parses it for a request, so an invalid URL or a URL without a host
+/// is reported as a request error instead of causing a panic.
+fn parse_site_url(client: &Client, url: &str) -> Result<(String, String), reqwest::Error> {
+    let request = client.get(url).build()?;
+
+    Ok((request.url().scheme().to_string(), request.url().host_str().unwrap_or_default().to_string()))
+}
+
+/// Downloads the HTML code of the page.
+///
+/// The page is not downloaded if the robots.txt file of the site does not allow it or if it is not an HTML page.
+/// Only the first `MAX_PAGE_HTML_BYTES` bytes of the page are downloaded, so a link to a large file cannot exhaust the memory.
+///
+/// # Arguments
+///
+/// * `url` - A string slice that holds the URL of the page.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the HTML code of the page, or `None` if the page may not be downloaded.
+/// * An `Err` variant containing a `reqwest::Error` if an error occurs during the request.
+pub async fn fetch_page_html(url: &str) -> Result<Option<String>, reqwest::Error> {
+    if !robots_txt_cache::is_url_crawl_allowed(url).await {
+        return Ok(None);
+    }
+
+    let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
+    let mut resp = client.get(url).send().await?;
+
+    let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|header| header.to_str().ok());
+
+    if !is_html_content_type(content_type) {
+        return Ok(None);
+    }
+
+    let mut body: Vec<u8> = Vec::new();
+
+    // The body is read in chunks, so the download stops as soon as the limit is reached
+    while let Some(chunk) = resp.chunk().await? {
+        let remaining = MAX_PAGE_HTML_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+
+        if body.len() >= MAX_PAGE_HTML_BYTES {
+            break;
+        }
+    }
+
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Checks whether the Content-Type header describes an HTML page. Parameters of the type, such as the charset, are ignored.
+fn is_html_content_type(content_type: Option<&str>) -> bool {
+    content_type.and_then(|content_type| content_type.split(';').next())
+        .map(|essence| essence.trim().to_lowercase())
+        .is_some_and(|essence| essence == "text/html" || essence == "application/xhtml+xml")
+}
+
+/// Downloads the favicon of the site the URL belongs to.
+///
+/// The favicon is not downloaded if the robots.txt file of the site does not allow it,
+/// and the download stops if the file is larger than `MAX_FAVICON_BYTES`.
+///
+/// # Arguments
+///
+/// * `url` - Any URL of the site.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the favicon, or `None` if the site has no favicon, it may not be downloaded or it is too large.
+/// * An `Err` variant containing a `reqwest::Error` if an error occurs during the request.
+pub async fn fetch_favicon(url: &str) -> Result<Option<Vec<u8>>, reqwest::Error> {
+    let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
+
+    let (scheme, host) = parse_site_url(&client, url)?;
+    let favicon_url = format!("{scheme}://{host}/favicon.ico");
+
+    if !robots_txt_cache::is_url_crawl_allowed(&favicon_url).await {
+        return Ok(None);
+    }
+
+    let mut resp = client.get(favicon_url).send().await?;
+
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+
+    let mut favicon: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = resp.chunk().await? {
+        if favicon.len() + chunk.len() > MAX_FAVICON_BYTES {
+            return Ok(None);
+        }
+
+        favicon.extend_from_slice(&chunk);
+    }
+
+    Ok(if favicon.is_empty() { None } else { Some(favicon) })
+}
+
+/// Sends a HEAD request to the URL and returns its Content-Security-Policy header.
+///
+/// # Arguments
+///
+/// * `url` - A string slice that holds the URL of the page.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the value of the header, or `None` if the site does not send it.
+/// * An `Err` variant containing a `reqwest::Error` if an error occurs during the request.
+pub async fn fetch_csp_header(url: &str) -> Result<Option<String>, reqwest::Error> {
+    let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
+    let resp = client.head(url).send().await?;
+
+    Ok(resp.headers().get(reqwest::header::CONTENT_SECURITY_POLICY)
+        .and_then(|header| header.to_str().ok())
+        .map(|header| header.to_string()))
+}
+
+/// Sends a HEAD request to the URL and returns the MIME type of the resource.
+///
+/// # Arguments
+///
+/// * `url` - A string slice that holds the URL of the resource.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the lowercase MIME type without parameters, or `None` if the site does not send the Content-Type header.
+/// * An `Err` variant containing a `reqwest::Error` if an error occurs during the request.
+pub async fn fetch_content_type(url: &str) -> Result<Option<String>, reqwest::Error> {
+    let client = Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
+    let resp = client.head(url).send().await?;
+
+    Ok(resp.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.split(';').next())
+        .map(|content_type| content_type.trim().to_lowercase())
+        .filter(|content_type| !content_type.is_empty()))
+}
+
+/// Checks if the URL belongs to a known link shortening service.
+///
+/// # Arguments
+///
+/// * `url` - A string slice representing the URL to be checked.
+///
+/// # Returns
+///
+/// * `bool` - "true" if the host of the URL is a link shortener, "false" otherwise.
+pub fn is_shortened_url(url: &str) -> bool {
+    // Parsed the same way as in `resolve_url`, so a link is only treated as short if it can be resolved
+    match reqwest::Url::parse(url) {
+        Ok(url) => url.host_str().map(|host| SHORTENER_HOSTS.contains(&host)).unwrap_or(false),
+        Err(_) => false
+    }
+}
+
+/// Represents a short link together with the redirects it leads through.
+pub struct ResolvedUrl {
+    pub original: String,
+    /// All URLs from the original link to the final URL, including both of them.
+    pub chain: Vec<String>,
+    pub final_url: String,
+    pub hop_count: u8,
+}
+
+/// Resolves a short link to the URL it redirects to.
+///
+/// The result is taken from the shortener cache if the link was resolved recently,
+/// otherwise redirects are followed one by one and the result is cached.
+///
+/// # Arguments
+///
+/// * `url` - A string slice that holds the short link.
+/// * `link_resolution_depth` - The maximum number of redirects to follow, usually `MAX_REDIRECT_HOPS`.
+///
+/// # Returns
+///
+/// * An `Ok` variant containing the final URL and the chain of redirects leading to it.
+/// * An `Err` variant containing a `reqwest::Error` if the link is not a valid URL or an error occurs during one of the requests.
+pub async fn resolve_url(url: &str, link_resolution_depth: u8) -> Result<ResolvedUrl, reqwest::Error> {
+    if let Some(cached) = shortener_cache::get_cached_resolution(url) {
+        info!("The short link {} was resolved from the cache ({} redirects)", url, cached.hop_count);
+
+        return Ok(ResolvedUrl {
+            original: url.to_string(),
+            chain: cached.chain,
+            final_url: cached.resolved_url,
+            hop_count: cached.hop_count,
+        });
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(15))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let mut current_url = client.get(url).build()?.url().clone();
+    let mut chain = vec![url.to_string()];
+
+    while chain.len() <= link_resolution_depth as usize {
+        let resp = client.get(current_url.clone()).send().await?;
+
+        if !resp.status().is_redirection() {
+            break;
+        }
+
+        let next_url = resp.headers().get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| current_url.join(location).ok());
+
+        match next_url {
+            Some(next_url) => {
+                current_url = next_url;
+                chain.push(current_url.to_string());
+            }
+            None => break
+        }
+    }
+
+    let hop_count = (chain.len() - 1) as u8;
+
+    info!("The short link {} was resolved ({} redirects)", url, hop_count);
+
+    shortener_cache::cache_resolution(url, current_url.as_str(), hop_count, &chain);
+
+    Ok(ResolvedUrl {
+        original: url.to_string(),
+        chain,
+        final_url: current_url.to_string(),
+        hop_count,
+    })
+}
+
 /// Checks if a given URL has either "http" or "https" protocol.
 ///
 /// # Arguments
@@ -115,6 +440,7 @@ pub fn has_http_or_https(url: &str) -> bool {
 }
 
 #[cfg(test)]
+#[allow(dead_code, clippy::assertions_on_constants)]
 mod website_checker_tests {
     use crate::website;
 
@@ -124,6 +450,21 @@ mod website_checker_tests {
     static RSVPU: &str = "https://rsvpu.ru/programs/bakalavriat";
     static HTTP: &str = "http://info.cern.ch/";
 
+    #[tokio::test]
+    async fn test_invalid_urls_are_errors() {
+        assert!(website::fetch_favicon("not a url").await.is_err());
+        assert!(website::fetch_favicon("mailto:admin@example.com").await.is_err());
+        assert!(website::get_site_information("mailto:admin@example.com").await.is_err());
+    }
+
+    #[test]
+    fn test_is_html_content_type() {
+        assert!(website::is_html_content_type(Some("text/html; charset=utf-8")));
+        assert!(website::is_html_content_type(Some("application/xhtml+xml")));
+        assert!(!website::is_html_content_type(Some("application/octet-stream")));
+        assert!(!website::is_html_content_type(None));
+    }
+
     #[tokio::test]
     async fn test_get_certificate() {
         let google_information = website::get_site_information(GOOGLE).await;
@@ -155,10 +496,30 @@ mod website_checker_tests {
         }
     }
 
+    #[test]
+    fn test_is_shortened_url() {
+        assert!(website::is_shortened_url("https://bit.ly/3abcDEF"));
+        assert!(!website::is_shortened_url(GOOGLE));
+        assert!(!website::is_shortened_url(RSVPU));
+        assert!(!website::is_shortened_url("http://bit.ly:99999/a"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_invalid_url() {
+        assert!(website::resolve_url("http://bit.ly:99999/a", website::MAX_REDIRECT_HOPS).await.is_err());
+    }
+
+    #[test]
+    fn test_is_unexpected_redirect() {
+        assert!(!website::is_unexpected_redirect("http://google.com", "https://www.google.com/"));
+        assert!(!website::is_unexpected_redirect(RSVPU, "https://rsvpu.ru/programs/"));
+        assert!(website::is_unexpected_redirect(GOOGLE, "https://evil.example/"));
+    }
+
     #[tokio::test]
     async fn test_get_request_code() {
-        let google_request = website::get_request_code("https://latitude.google.com/").await.unwrap();
-        println!("Request code: {}", google_request);
+        let google_request = website::check_link_health("https://latitude.google.com/").await.unwrap();
+        println!("Request code: {}", google_request.status_code);
 
         assert!(true)
     }